@@ -1,27 +1,45 @@
 #![feature(test)]
+#![allow(clippy::let_unit_value)]
 
+extern crate indicatif;
 extern crate rand;
 extern crate test;
 extern crate ycsb_rs;
 
-use ycsb_rs::{Client, CoreWorkload, Db, MockDb, WorkloadSpec};
+use indicatif::ProgressBar;
+use ycsb_rs::{Client, CoreWorkload, Db, MockDb, Operation, RngKind, WorkloadSpec};
 
 use test::Bencher;
 
 #[bench]
 fn bench_next_sequence_key(b: &mut Bencher) {
-    let spec = WorkloadSpec::default();
+    let spec = WorkloadSpec::default().record_count(1000);
     let workload = CoreWorkload::new(spec).unwrap();
 
-    b.iter(|| workload.next_sequence_key());
+    b.iter(|| workload.next_sequence_key().1);
 }
 
 #[bench]
 fn bench_next_transaction_key(b: &mut Bencher) {
-    let spec = WorkloadSpec::default();
+    let spec = WorkloadSpec::default().record_count(1000);
     let workload = CoreWorkload::new(spec).unwrap();
 
-    b.iter(|| workload.next_transaction_key());
+    b.iter(|| workload.next_transaction_key(workload.next_table()));
+}
+
+#[bench]
+fn bench_next_operation(b: &mut Bencher) {
+    let spec = WorkloadSpec::default()
+        .record_count(1000)
+        .read_proportion(0.3)
+        .update_proportion(0.2)
+        .insert_proportion(0.1)
+        .scan_proportion(0.1)
+        .rmw_proportion(0.2)
+        .delete_proportion(0.1);
+    let workload = CoreWorkload::new(spec).unwrap();
+
+    b.iter(|| workload.next_operation());
 }
 
 #[bench]
@@ -31,20 +49,151 @@ fn bench_next_zipfian_key(b: &mut Bencher) {
         .record_count(10000);
     let workload = CoreWorkload::new(spec).unwrap();
 
-    b.iter(|| workload.next_transaction_key());
+    b.iter(|| workload.next_transaction_key(workload.next_table()));
+}
+
+/// Operations and keys drawn per iteration by the batched benchmarks and
+/// their one-at-a-time counterparts.
+const BATCH: usize = 100;
+
+fn zipfian_workload() -> CoreWorkload {
+    let spec = WorkloadSpec::default()
+        .record_count(1000)
+        .read_proportion(0.3)
+        .update_proportion(0.2)
+        .insert_proportion(0.1)
+        .scan_proportion(0.1)
+        .rmw_proportion(0.2)
+        .delete_proportion(0.1)
+        .request_zipfian(0.99);
+
+    CoreWorkload::new(spec).unwrap()
+}
+
+#[bench]
+fn bench_next_operation_loop(b: &mut Bencher) {
+    let workload = zipfian_workload();
+
+    b.iter(|| {
+        (0..BATCH)
+            .map(|_| workload.next_operation())
+            .collect::<Vec<_>>()
+    });
+}
+
+#[bench]
+fn bench_next_operations(b: &mut Bencher) {
+    let workload = zipfian_workload();
+
+    b.iter(|| workload.next_operations(BATCH));
+}
+
+#[bench]
+fn bench_next_zipfian_key_loop(b: &mut Bencher) {
+    let workload = zipfian_workload();
+    let table = &workload.tables()[0];
+
+    b.iter(|| {
+        (0..BATCH)
+            .map(|_| workload.next_transaction_key(table))
+            .collect::<Vec<_>>()
+    });
+}
+
+#[bench]
+fn bench_next_zipfian_keys(b: &mut Bencher) {
+    let workload = zipfian_workload();
+    let table = &workload.tables()[0];
+
+    b.iter(|| workload.next_transaction_keys(table, BATCH));
 }
 
 #[bench]
 fn bench_next_value(b: &mut Bencher) {
-    let spec = WorkloadSpec::default();
+    let spec = WorkloadSpec::default().record_count(1000);
     let workload = CoreWorkload::new(spec).unwrap();
 
     b.iter(|| workload.next_field_value());
 }
 
+#[bench]
+fn bench_build_values(b: &mut Bencher) {
+    let spec = WorkloadSpec::default().record_count(1000);
+    let workload = CoreWorkload::new(spec).unwrap();
+    let key = workload.next_sequence_key().1;
+
+    b.iter(|| workload.build_values(&key));
+}
+
+/// Builds values from a session drawing from a `kind` generator, which
+/// spends most of its time on random numbers.
+fn bench_build_values_rng(b: &mut Bencher, kind: RngKind) {
+    let spec = WorkloadSpec::default().record_count(1000).rng(kind);
+    let workload = CoreWorkload::new(spec).unwrap();
+    let key = workload.next_sequence_key().1;
+    let mut session = workload.session(0);
+    let mut values = Vec::new();
+
+    b.iter(|| session.build_values_into(&key, &mut values));
+}
+
+#[bench]
+fn bench_build_values_small(b: &mut Bencher) {
+    bench_build_values_rng(b, RngKind::Small);
+}
+
+#[bench]
+fn bench_build_values_chacha8(b: &mut Bencher) {
+    bench_build_values_rng(b, RngKind::ChaCha8);
+}
+
+#[bench]
+fn bench_build_values_chacha20(b: &mut Bencher) {
+    bench_build_values_rng(b, RngKind::ChaCha20);
+}
+
+fn bench_next_zipfian_key_rng(b: &mut Bencher, kind: RngKind) {
+    let spec = WorkloadSpec::default()
+        .request_zipfian(0.99)
+        .record_count(10000)
+        .rng(kind);
+    let workload = CoreWorkload::new(spec).unwrap();
+    let mut session = workload.session(0);
+
+    b.iter(|| {
+        let table = session.next_table();
+        session.next_transaction_key(table)
+    });
+}
+
+#[bench]
+fn bench_next_zipfian_key_small(b: &mut Bencher) {
+    bench_next_zipfian_key_rng(b, RngKind::Small);
+}
+
+#[bench]
+fn bench_next_zipfian_key_chacha8(b: &mut Bencher) {
+    bench_next_zipfian_key_rng(b, RngKind::ChaCha8);
+}
+
+#[bench]
+fn bench_next_zipfian_key_chacha20(b: &mut Bencher) {
+    bench_next_zipfian_key_rng(b, RngKind::ChaCha20);
+}
+
+#[bench]
+fn bench_build_values_into(b: &mut Bencher) {
+    let spec = WorkloadSpec::default().record_count(1000);
+    let workload = CoreWorkload::new(spec).unwrap();
+    let key = workload.next_sequence_key().1;
+    let mut values = Vec::new();
+
+    b.iter(|| workload.build_values_into(&key, &mut values));
+}
+
 #[bench]
 fn bench_read_txn(b: &mut Bencher) {
-    let spec = WorkloadSpec::default();
+    let spec = WorkloadSpec::default().record_count(1000);
     let workload = CoreWorkload::new(spec).unwrap();
     let db = MockDb::new(true);
     let client = Client::new(&db, &workload);
@@ -66,7 +215,7 @@ fn bench_read_txn(b: &mut Bencher) {
 
 #[bench]
 fn bench_insert_txn(b: &mut Bencher) {
-    let spec = WorkloadSpec::default();
+    let spec = WorkloadSpec::default().record_count(1000);
     let workload = CoreWorkload::new(spec).unwrap();
     let db = MockDb::new(true);
     let client = Client::new(&db, &workload);
@@ -76,8 +225,9 @@ fn bench_insert_txn(b: &mut Bencher) {
         let res = client.insert_txn(&mut txn);
 
         match res {
-            Ok(_) => {
+            Ok((table, key_num, _)) => {
                 db.commit_transaction(txn).unwrap();
+                workload.acknowledge_insert(table, key_num);
             }
             _ => {
                 db.abort_transaction(txn).unwrap();
@@ -85,3 +235,102 @@ fn bench_insert_txn(b: &mut Bencher) {
         }
     });
 }
+
+/// Four workers run reads and updates against a database that does nothing
+/// and report them to one hidden progress bar, `batch` operations at a time.
+/// Comparing a batch of 1 with the harness's 1024 shows what contending on
+/// the bar for every operation costs.
+fn bench_worker_loop(b: &mut Bencher, batch: u64) {
+    let db = MockDb::new(true);
+    let spec = WorkloadSpec::default()
+        .record_count(1000)
+        .read_proportion(0.5)
+        .update_proportion(0.5);
+    let workload = CoreWorkload::new(spec).unwrap();
+    let pb = ProgressBar::hidden();
+
+    b.iter(|| {
+        std::thread::scope(|s| {
+            for i in 0..4 {
+                let (db, workload, pb) = (&db, &workload, &pb);
+
+                s.spawn(move || {
+                    let client = Client::with_session(db, workload.session(i));
+                    let mut pending = 0;
+
+                    for _ in 0..10_000 {
+                        let mut txn = db.start_transaction().unwrap();
+                        match client.next_operation() {
+                            Operation::Read => {
+                                test::black_box(client.read_txn(&mut txn).unwrap());
+                            }
+                            _ => {
+                                test::black_box(client.update_txn(&mut txn).unwrap());
+                            }
+                        }
+                        db.commit_transaction(txn).unwrap();
+
+                        pending += 1;
+                        if pending == batch {
+                            pb.inc(pending);
+                            pending = 0;
+                        }
+                    }
+                    pb.inc(pending);
+                });
+            }
+        })
+    });
+}
+
+#[bench]
+fn bench_worker_loop_per_op_progress(b: &mut Bencher) {
+    bench_worker_loop(b, 1);
+}
+
+#[bench]
+fn bench_worker_loop_batched_progress(b: &mut Bencher) {
+    bench_worker_loop(b, 1024);
+}
+
+/// Each of `threads` threads draws the same number of keys from its own
+/// session, so the time per iteration stays flat as long as key generation
+/// scales with the threads.
+fn bench_session_keys(b: &mut Bencher, threads: usize) {
+    let spec = WorkloadSpec::default()
+        .request_zipfian(0.99)
+        .record_count(10000);
+    let workload = CoreWorkload::new(spec).unwrap();
+
+    b.iter(|| {
+        std::thread::scope(|s| {
+            for i in 0..threads {
+                let workload = &workload;
+
+                s.spawn(move || {
+                    let mut session = workload.session(i);
+
+                    for _ in 0..10_000 {
+                        let table = session.next_table();
+                        test::black_box(session.next_transaction_key(table));
+                    }
+                });
+            }
+        })
+    });
+}
+
+#[bench]
+fn bench_session_keys_1_thread(b: &mut Bencher) {
+    bench_session_keys(b, 1);
+}
+
+#[bench]
+fn bench_session_keys_8_threads(b: &mut Bencher) {
+    bench_session_keys(b, 8);
+}
+
+#[bench]
+fn bench_session_keys_64_threads(b: &mut Bencher) {
+    bench_session_keys(b, 64);
+}
@@ -0,0 +1,871 @@
+//! Async harness for `AsyncDb` adapters, enabled by the `async` feature.
+//!
+//! Instead of one worker per OS thread, `run_phases_async_with` runs one task
+//! per worker on the tokio runtime it is awaited on, with the same options and
+//! measurements as `run_phases_with`.
+
+use crate::{
+    check_record, check_sla, finish_load_phase, finish_run_phase, finish_verify_phase, group_ops,
+    measurement::{Measurements, ThreadStats, ThroughputSeries},
+    partition, payload_bytes, phase_progress_bar, phase_workload, prepare_run, ramp_up_delay,
+    sample_progress, start_load_phase, start_run_phase, Attempt, BatchedProgress, CoreWorkload,
+    Error, LoadKeys, Operation, Phase, PhaseResult, Record, Result, RunOptions, RunResult, Table,
+    ThreadGroup, TxnRecorder, VerificationReport, WorkloadSession, WorkloadSource, WorkloadSpec,
+};
+
+#[cfg(feature = "prometheus")]
+use crate::serve_metrics;
+
+use std::{
+    future::Future,
+    ops::Range,
+    path::Path,
+    sync::{mpsc, Arc},
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use indicatif::ProgressBar;
+
+#[async_trait]
+pub trait AsyncDb: Sync + Send {
+    type Transaction: Send;
+
+    async fn start_transaction(&self) -> Result<Self::Transaction>;
+    async fn commit_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction>;
+    async fn abort_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction>;
+
+    async fn read(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: &str,
+        fields: Option<Vec<String>>,
+    ) -> Result<Option<Vec<(String, String)>>>;
+
+    async fn insert(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()>;
+
+    async fn update(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()>;
+
+    async fn scan(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: &str,
+        length: usize,
+        fields: Option<Vec<String>>,
+    ) -> Result<Vec<Vec<(String, String)>>>;
+
+    /// Deletes a record. See `Db::delete`.
+    async fn delete(&self, _txn: &mut Self::Transaction, _table: &str, _key: &str) -> Result<()> {
+        Err(Error::InvalidArgument("delete is not supported".to_owned()))
+    }
+}
+
+/// Like `Client`, issuing the operations of a session on an `AsyncDb`.
+struct AsyncClient<'a, T> {
+    db: &'a dyn AsyncDb<Transaction = T>,
+    workload: &'a CoreWorkload,
+    session: WorkloadSession<'a>,
+    verification_errors: u64,
+    /// Table and key number of the last successful insert, which the caller
+    /// acknowledges once its transaction has finished.
+    inserted: Option<(&'a Table, u64)>,
+    /// Whether the last read was of a key that was never inserted.
+    missed: bool,
+}
+
+impl<'a, T: Send> AsyncClient<'a, T> {
+    fn new(db: &'a dyn AsyncDb<Transaction = T>, session: WorkloadSession<'a>) -> Self {
+        Self {
+            db,
+            workload: session.workload(),
+            session,
+            verification_errors: 0,
+            inserted: None,
+            missed: false,
+        }
+    }
+
+    fn check_values(&mut self, key: &str, record: Option<&[(String, String)]>) {
+        if let Some(values) = record {
+            if !self.workload.check_values(key, values) {
+                self.verification_errors += 1;
+            }
+        }
+    }
+
+    /// Runs `op` in `txn`, returning the bytes read and written.
+    async fn execute(&mut self, op: Operation, txn: &mut T) -> Result<(u64, u64)> {
+        let (db, workload) = (self.db, self.workload);
+        let table = self.session.next_table();
+        self.missed = false;
+
+        match op {
+            Operation::Read => {
+                let (key, missing) = match self.session.next_missing_key() {
+                    Some(key) => (key, true),
+                    None => (
+                        self.session
+                            .next_transaction_key_for(Operation::Read, table),
+                        false,
+                    ),
+                };
+                let fields = self.session.next_read_fields();
+
+                let record = db.read(txn, table.name(), &key, fields).await?;
+                if !missing {
+                    self.check_values(&key, record.as_deref());
+                }
+                self.missed = missing;
+
+                Ok((record.as_deref().map_or(0, payload_bytes), 0))
+            }
+            Operation::Update => {
+                let key = self
+                    .session
+                    .next_transaction_key_for(Operation::Update, table);
+                let values = self.session.next_update_values(&key);
+                let bytes = payload_bytes(&values);
+
+                db.update(txn, table.name(), key, values).await?;
+
+                Ok((0, bytes))
+            }
+            Operation::Insert => {
+                let key_num = workload.next_insert_key_num(table);
+                let key = workload.get_key_name(key_num);
+                let mut values = Vec::new();
+                self.session.build_values_into(&key, &mut values);
+                let bytes = payload_bytes(&values);
+
+                if let Err(err) = db.insert(txn, table.name(), key, values).await {
+                    workload.acknowledge_insert(table, key_num);
+                    return Err(err);
+                }
+                self.inserted = Some((table, key_num));
+
+                Ok((0, bytes))
+            }
+            Operation::Scan => {
+                let (key, length) = self.session.next_scan(table);
+                let fields = self.session.next_read_fields();
+
+                let rows = db.scan(txn, table.name(), &key, length, fields).await?;
+                let corrupted = rows.iter().filter(|row| !workload.check_row(row)).count();
+                self.verification_errors += corrupted as u64;
+
+                Ok((rows.iter().map(|row| payload_bytes(row)).sum(), 0))
+            }
+            Operation::ReadModifyWrite => {
+                let key = self
+                    .session
+                    .next_transaction_key_for(Operation::ReadModifyWrite, table);
+                let fields = self.session.next_read_fields();
+
+                let record = db.read(txn, table.name(), &key, fields.clone()).await?;
+                self.check_values(&key, record.as_deref());
+
+                let read = match record {
+                    Some(record) => payload_bytes(&record),
+                    None => return Ok((0, 0)),
+                };
+
+                let values = self.session.next_rmw_values(&key, fields.as_deref());
+                let bytes = payload_bytes(&values);
+
+                db.update(txn, table.name(), key, values).await?;
+
+                Ok((read, bytes))
+            }
+            Operation::Delete => {
+                let key = self.session.next_delete_key(table);
+
+                db.delete(txn, table.name(), &key).await.map(|_| (0, 0))
+            }
+        }
+    }
+}
+
+async fn load_db_async<T: Send>(
+    db: &dyn AsyncDb<Transaction = T>,
+    workload: &CoreWorkload,
+    stream: usize,
+    keys: LoadKeys<'_>,
+    batch_size: usize,
+    pb: &ProgressBar,
+    measurements: &Measurements,
+) -> Result<(usize, u64)> {
+    let mut total_count = 0;
+    let mut total_bytes = 0;
+    let mut committed_batches = 0;
+    let num_ops = keys.len();
+
+    let mut batch: Vec<Record> = Vec::with_capacity(batch_size);
+    let mut session = workload.session(stream);
+    let mut progress = BatchedProgress::new(pb);
+
+    for b in (0..num_ops).step_by(batch_size) {
+        let count = std::cmp::min(batch_size, num_ops - b);
+        let bytes = keys.next_batch(&mut session, b, count, &mut batch);
+
+        loop {
+            let mut txn = db.start_transaction().await?;
+            let mut res = Ok(());
+
+            for (table, key, values) in &batch {
+                res = db
+                    .insert(&mut txn, table, key.to_owned(), values.clone())
+                    .await;
+
+                if res.is_err() {
+                    break;
+                }
+            }
+
+            match res {
+                Ok(()) => {
+                    db.commit_transaction(txn).await?;
+                    total_count += count;
+                    total_bytes += bytes;
+                    progress.add(count as u64);
+                    measurements.add_completed(count as u64);
+
+                    committed_batches += 1;
+                    keys.batch_committed(stream, b, count, committed_batches)?;
+                    break;
+                }
+                Err(Error::TransactionAborted) => {
+                    db.abort_transaction(txn).await?;
+                    continue;
+                }
+                Err(err) => {
+                    db.abort_transaction(txn).await?;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    Ok((total_count, total_bytes))
+}
+
+async fn bench_txn_async<T: Send>(
+    db: &dyn AsyncDb<Transaction = T>,
+    workload: &CoreWorkload,
+    stream: usize,
+    num_ops: usize,
+    options: &RunOptions<T>,
+    pb: &ProgressBar,
+    measurements: &Measurements,
+) -> Result<ThreadStats> {
+    let mut client = AsyncClient::new(db, workload.session(stream));
+    let mut recorder = TxnRecorder::new(options, pb, measurements);
+
+    for _ in 0..num_ops {
+        let op = client.session.next_operation();
+        let arrival = recorder.next_arrival(client.session.rng());
+        if let Some(arrival) = arrival {
+            tokio::time::sleep_until(arrival.into()).await;
+        }
+        let op = recorder.issue(op, arrival);
+
+        loop {
+            let started = Instant::now();
+            let mut committing = None;
+
+            let res = match db.start_transaction().await {
+                Ok(mut txn) => {
+                    let res = client.execute(op.op, &mut txn).await;
+
+                    if options.latency_breakdown {
+                        committing = Some(Instant::now());
+                    }
+
+                    match res {
+                        Ok(bytes) => db.commit_transaction(txn).await.map(|_| bytes),
+                        Err(err) => match db.abort_transaction(txn).await {
+                            Ok(_) => Err(err),
+                            Err(abort_err) => Err(abort_err),
+                        },
+                    }
+                }
+                Err(err) => Err(err),
+            };
+
+            // Only now is a committed insert visible to the other tasks. A
+            // failed one is released as well so that later inserts become
+            // visible.
+            if let Some((table, key_num)) = client.inserted.take() {
+                workload.acknowledge_insert(table, key_num);
+            }
+
+            let attempt = Attempt {
+                res,
+                started,
+                committing,
+                missed: client.missed,
+            };
+            if recorder.record(&op, attempt)? {
+                break;
+            }
+        }
+
+        if let Some(think_time) = client.session.next_think_time() {
+            tokio::time::sleep(think_time).await;
+        }
+    }
+
+    recorder.finish(client.verification_errors)
+}
+
+async fn verify_keys_async<T: Send>(
+    db: &dyn AsyncDb<Transaction = T>,
+    workload: &CoreWorkload,
+    key_nums: Range<usize>,
+    pb: &ProgressBar,
+) -> Result<VerificationReport> {
+    let mut expected = workload.field_names();
+    expected.sort();
+
+    let mut report = VerificationReport::default();
+    let mut progress = BatchedProgress::new(pb);
+
+    for i in key_nums {
+        let (table, key_num) = workload.load_key_num(i);
+
+        if workload.is_deleted(table, key_num) {
+            progress.inc();
+            continue;
+        }
+
+        let key = workload.get_key_name(key_num);
+
+        let record = loop {
+            let mut txn = db.start_transaction().await?;
+
+            match db.read(&mut txn, table.name(), &key, None).await {
+                Ok(record) => {
+                    db.commit_transaction(txn).await?;
+                    break record;
+                }
+                Err(Error::TransactionAborted) => {
+                    db.abort_transaction(txn).await?;
+                    continue;
+                }
+                Err(err) => {
+                    db.abort_transaction(txn).await?;
+                    return Err(err);
+                }
+            }
+        };
+
+        check_record(workload, &key, record, &expected, &mut report);
+        progress.inc();
+    }
+
+    Ok(report)
+}
+
+/// What the tasks of a phase share: the database, the options, the phase's
+/// progress bar and measurements, and `work`, what the phase splits over the
+/// tasks.
+struct PhaseTasks<T, W> {
+    db: Arc<dyn AsyncDb<Transaction = T>>,
+    options: Arc<RunOptions<T>>,
+    pb: ProgressBar,
+    measurements: Measurements,
+    work: W,
+}
+
+impl<T: Send + 'static, W: Send + Sync + 'static> PhaseTasks<T, W> {
+    fn new(
+        db: &Arc<dyn AsyncDb<Transaction = T>>,
+        options: &Arc<RunOptions<T>>,
+        pb: ProgressBar,
+        measurements: Measurements,
+        work: W,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            db: db.clone(),
+            options: options.clone(),
+            pb,
+            measurements,
+            work,
+        })
+    }
+
+    /// Takes the phase back once all of its tasks have finished.
+    fn finish(phase: Arc<Self>) -> Self {
+        Arc::try_unwrap(phase)
+            .ok()
+            .expect("phase task still running")
+    }
+
+    /// Spawns `worker` for each of the phase's tasks and waits for all of
+    /// them, so that none is left running when one fails.
+    async fn run<R, F, Fut>(phase: &Arc<Self>, worker: F) -> Result<Vec<R>>
+    where
+        R: Send + 'static,
+        F: Fn(Arc<Self>, usize) -> Fut,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+    {
+        let tasks = (0..phase.options.threads)
+            .map(|i| tokio::spawn(worker(phase.clone(), i)))
+            .collect::<Vec<_>>();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await.expect("benchmark task panicked"));
+        }
+
+        results.into_iter().collect()
+    }
+
+    /// Like `run`, while a blocking sampler records the phase's throughput
+    /// once per second and shows it as the message of the progress bar.
+    async fn run_measured<R, F, Fut>(
+        phase: &Arc<Self>,
+        worker: F,
+    ) -> Result<(Vec<R>, ThroughputSeries)>
+    where
+        R: Send + 'static,
+        F: Fn(Arc<Self>, usize) -> Fut,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+
+        let sampler = {
+            let phase = phase.clone();
+            tokio::task::spawn_blocking(move || {
+                sample_progress(&phase.measurements, &phase.pb, stopped)
+            })
+        };
+
+        let results = Self::run(phase, worker).await;
+        drop(stop);
+        let series = sampler.await.expect("throughput sampler panicked");
+
+        Ok((results?, series))
+    }
+}
+
+async fn load_phase_async<T: Send + 'static>(
+    db: &Arc<dyn AsyncDb<Transaction = T>>,
+    workload: &Arc<CoreWorkload>,
+    record_count: usize,
+    options: &Arc<RunOptions<T>>,
+) -> Result<PhaseResult> {
+    let (plan, pb, measurements) = start_load_phase(workload, record_count, options)?;
+    let phase = PhaseTasks::new(db, options, pb, measurements, (workload.clone(), plan));
+
+    let res = PhaseTasks::run_measured(&phase, |phase, i| async move {
+        let (workload, plan) = &phase.work;
+
+        load_db_async(
+            &*phase.db,
+            workload,
+            i,
+            plan.keys(i),
+            phase.options.load_batch_size,
+            &phase.pb,
+            &phase.measurements,
+        )
+        .await
+    })
+    .await;
+
+    let PhaseTasks {
+        pb,
+        measurements,
+        work: (_, plan),
+        ..
+    } = PhaseTasks::finish(phase);
+
+    finish_load_phase(&plan, res, measurements, &pb)
+}
+
+async fn run_phase_async<T: Send + 'static>(
+    db: &Arc<dyn AsyncDb<Transaction = T>>,
+    groups: Vec<ThreadGroup>,
+    options: &Arc<RunOptions<T>>,
+) -> Result<PhaseResult> {
+    let nr_threads = options.threads;
+    let ops = group_ops(&groups, options)?;
+    let work = Arc::new((groups, ops));
+
+    if options.warmup_ops > 0 {
+        let phase = PhaseTasks::new(
+            db,
+            options,
+            ProgressBar::hidden(),
+            Measurements::new(None)?,
+            work.clone(),
+        );
+
+        PhaseTasks::run(&phase, |phase, i| async move {
+            let (groups, ops) = &*phase.work;
+            let (workload, (warmup_ops, _)) = ThreadGroup::worker_ops(groups, ops, i);
+
+            // Apart from the run phase's streams, so the warmup doesn't touch
+            // exactly the keys the run is about to.
+            bench_txn_async(
+                &*phase.db,
+                workload,
+                nr_threads + i,
+                warmup_ops,
+                &phase.options,
+                &phase.pb,
+                &phase.measurements,
+            )
+            .await
+        })
+        .await?;
+    }
+
+    let (pb, measurements) = start_run_phase(&work.0, &work.1, options)?;
+    let phase = PhaseTasks::new(db, options, pb, measurements, work);
+
+    let res = PhaseTasks::run_measured(&phase, |phase, i| async move {
+        if let Some(delay) = ramp_up_delay(&phase.options, i) {
+            tokio::time::sleep(delay).await;
+        }
+
+        let (groups, ops) = &*phase.work;
+        let (workload, (_, run_ops)) = ThreadGroup::worker_ops(groups, ops, i);
+
+        bench_txn_async(
+            &*phase.db,
+            workload,
+            i,
+            run_ops,
+            &phase.options,
+            &phase.pb,
+            &phase.measurements,
+        )
+        .await
+    })
+    .await;
+
+    let PhaseTasks {
+        pb,
+        measurements,
+        work,
+        ..
+    } = PhaseTasks::finish(phase);
+    let (thread_stats, series) = res?;
+
+    finish_run_phase(&work.0, thread_stats, series, measurements, &pb, options)
+}
+
+async fn verify_phase_async<T: Send + 'static>(
+    db: &Arc<dyn AsyncDb<Transaction = T>>,
+    workload: &Arc<CoreWorkload>,
+    loaded: usize,
+    options: &Arc<RunOptions<T>>,
+) -> Result<VerificationReport> {
+    let key_nums = workload.load_key_range(loaded);
+
+    let pb = phase_progress_bar(key_nums.len(), "verify", None);
+    let phase = PhaseTasks::new(
+        db,
+        options,
+        pb,
+        Measurements::new(None)?,
+        (workload.clone(), key_nums),
+    );
+
+    let reports = PhaseTasks::run(&phase, |phase, i| async move {
+        let (workload, key_nums) = &phase.work;
+
+        verify_keys_async(
+            &*phase.db,
+            workload,
+            partition(key_nums.clone(), phase.options.threads, i),
+            &phase.pb,
+        )
+        .await
+    })
+    .await?;
+
+    Ok(finish_verify_phase(reports, &phase.pb))
+}
+
+/// Runs `phases` in order with `options`, like `run_phases_with`, with each
+/// worker thread replaced by a task on the tokio runtime this is awaited on.
+///
+/// The hooks take a `Db` and CPU pinning needs threads of our own, so
+/// options with hooks or a CPU set are rejected.
+pub async fn run_phases_async_with<T: Send + 'static, W: Into<WorkloadSource>>(
+    db: Arc<dyn AsyncDb<Transaction = T>>,
+    phases: Vec<W>,
+    mut options: RunOptions<T>,
+) -> Result<Vec<RunResult>> {
+    if !options.hooks.is_empty() {
+        return Err(Error::InvalidArgument(
+            "hooks are not supported by async runs".to_owned(),
+        ));
+    }
+
+    if !options.cpu_set.is_empty() {
+        return Err(Error::InvalidArgument(
+            "a CPU set is not supported by async runs".to_owned(),
+        ));
+    }
+
+    let mut phases = phases.into_iter().map(Into::into).collect::<Vec<_>>();
+    let thread_groups = prepare_run(&mut phases, &mut options)?;
+
+    #[cfg(feature = "prometheus")]
+    let _metrics_server = serve_metrics(&mut options)?;
+
+    let options = Arc::new(options);
+    let mut results = Vec::new();
+    let mut key_counts: Option<Vec<usize>> = None;
+    let mut loaded = None;
+
+    for source in phases {
+        let (groups, record_count, workload) =
+            phase_workload(source, key_counts.as_ref(), &thread_groups, &options)?;
+
+        let load = match (&key_counts, options.phase) {
+            (Some(_), _) => None,
+            (None, Phase::RunOnly) => {
+                workload.skip_load(record_count);
+                loaded = Some((workload.clone(), record_count));
+                None
+            }
+            (None, _) => {
+                let load = load_phase_async(&db, &workload, record_count, &options).await?;
+
+                // A resumed load only counts the records it wrote itself.
+                let count = match options.load_checkpoint {
+                    Some(_) => record_count,
+                    None => load.operations,
+                };
+                loaded = Some((workload.clone(), count));
+                Some(load)
+            }
+        };
+
+        if options.phase == Phase::LoadOnly {
+            results.push(RunResult {
+                load,
+                run: None,
+                verification: None,
+                workload: Some(workload.effective_spec()),
+            });
+            break;
+        }
+
+        let run = run_phase_async(&db, groups, &options).await?;
+
+        key_counts = Some(workload.table_key_counts());
+        results.push(RunResult {
+            load,
+            run: Some(run),
+            verification: None,
+            workload: Some(workload.effective_spec()),
+        });
+    }
+
+    if options.verify {
+        if let (Some((workload, count)), Some(last)) = (&loaded, results.last_mut()) {
+            last.verification = Some(verify_phase_async(&db, workload, *count, &options).await?);
+        }
+    }
+
+    check_sla(results, &options)
+}
+
+/// Loads and runs a single workload with `options`. See
+/// `run_phases_async_with` for details.
+pub async fn run_ycsb_async_with<T: Send + 'static, W: Into<WorkloadSource>>(
+    db: Arc<dyn AsyncDb<Transaction = T>>,
+    workload: W,
+    options: RunOptions<T>,
+) -> Result<RunResult> {
+    let mut results = run_phases_async_with(db, vec![workload], options).await?;
+
+    Ok(results.remove(0))
+}
+
+/// Loads the database and runs the transaction phase with `concurrency`
+/// concurrent tasks.
+///
+/// Tasks are spawned with `tokio::spawn`, so this must be awaited from within
+/// a tokio runtime; a multi-threaded runtime spreads the tasks over its
+/// worker threads.
+pub async fn run_ycsb_async<P: AsRef<Path>, T: Send + 'static>(
+    db: Arc<dyn AsyncDb<Transaction = T>>,
+    workload_path: P,
+    concurrency: usize,
+) -> Result<RunResult> {
+    run_ycsb_async_with(
+        db,
+        WorkloadSpec::from_path(workload_path)?,
+        RunOptions::new().threads(concurrency),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingDb {
+        aborts_left: AtomicUsize,
+        commits: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl AsyncDb for CountingDb {
+        type Transaction = ();
+
+        async fn start_transaction(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn commit_transaction(&self, _txn: ()) -> Result<()> {
+            self.commits.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        async fn abort_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        async fn read(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Option<Vec<(String, String)>>> {
+            let aborted = self
+                .aborts_left
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok();
+
+            if aborted {
+                Err(Error::TransactionAborted)
+            } else {
+                Ok(None)
+            }
+        }
+
+        async fn insert(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn update(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn scan(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _length: usize,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Vec<Vec<(String, String)>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_run_async() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .build()
+            .unwrap();
+        let db = Arc::new(CountingDb {
+            aborts_left: AtomicUsize::new(100),
+            ..Default::default()
+        });
+
+        let result = rt
+            .block_on(run_ycsb_async(db.clone(), "workloads/workload_a.json", 50))
+            .unwrap();
+
+        let loaded = result.load.unwrap().operations;
+        let txns = result.run.unwrap().operations;
+
+        assert_eq!(loaded, 100000);
+        assert_eq!(txns, 100000);
+        // 50 tasks each load 2000 records in batches of 32
+        assert_eq!(db.commits.load(Ordering::Relaxed), 50 * 63 + txns);
+    }
+
+    #[test]
+    fn test_run_phases_async() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        let db: Arc<dyn AsyncDb<Transaction = ()>> = Arc::new(CountingDb {
+            aborts_left: AtomicUsize::new(10),
+            ..Default::default()
+        });
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(2000)
+                .read_proportion(0.5)
+                .update_proportion(0.5)
+        };
+
+        let options = RunOptions::new().threads(4).warmup_ops(400);
+        let results = rt
+            .block_on(run_phases_async_with(
+                db.clone(),
+                vec![spec(), spec()],
+                options,
+            ))
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[1].load.is_none());
+        for result in &results {
+            let run = result.run.as_ref().unwrap();
+
+            assert_eq!(run.operations, 1600);
+            assert!(run.latency.is_some());
+            assert_eq!(run.per_op.len(), 2);
+            assert_eq!(run.threads.len(), 4);
+        }
+
+        let options = RunOptions::new().before_run(|_| Ok(()));
+        assert!(rt
+            .block_on(run_ycsb_async_with(db, spec(), options))
+            .is_err());
+    }
+}
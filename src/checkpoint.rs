@@ -0,0 +1,177 @@
+//! Load progress saved to a file, so that an interrupted load can pick up
+//! where it stopped; see `RunOptions::load_checkpoint`.
+
+use crate::{Error, Result};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CheckpointFile {
+    /// `CoreWorkload::load_spec_hash` of the workload being loaded.
+    spec_hash: u64,
+    record_count: usize,
+    /// Next load position of each worker's partition.
+    positions: Vec<usize>,
+}
+
+/// Load positions of the workers, saved after their batches commit.
+pub(crate) struct LoadCheckpoint {
+    path: PathBuf,
+    /// Committed batches after which a worker saves the checkpoint.
+    interval: usize,
+    /// The positions, and the number of snapshots taken of them.
+    state: Mutex<(CheckpointFile, u64)>,
+    /// Held while the file is written, with the number of the snapshot last
+    /// written, so that a snapshot overtaken by a newer one is dropped.
+    written: Mutex<u64>,
+}
+
+impl LoadCheckpoint {
+    /// Resumes from the checkpoint at `path` if there is one, or starts a
+    /// new one with a worker per partition.
+    pub(crate) fn open(
+        path: &Path,
+        interval: usize,
+        spec_hash: u64,
+        partitions: &[Range<usize>],
+    ) -> Result<Self> {
+        let record_count = partitions.iter().map(|p| p.end - p.start).sum();
+
+        let state = match fs::read(path) {
+            Ok(data) => {
+                let file: CheckpointFile = serde_json::from_slice(&data).map_err(|e| {
+                    Error::InvalidArgument(format!("checkpoint {}: {}", path.display(), e))
+                })?;
+
+                if file.spec_hash != spec_hash || file.record_count != record_count {
+                    return Err(Error::InvalidArgument(format!(
+                        "checkpoint {} belongs to a different workload",
+                        path.display()
+                    )));
+                }
+
+                if file.positions.len() != partitions.len()
+                    || file
+                        .positions
+                        .iter()
+                        .zip(partitions)
+                        .any(|(pos, p)| *pos < p.start || *pos > p.end)
+                {
+                    return Err(Error::InvalidArgument(format!(
+                        "checkpoint {} was written by a load on {} threads",
+                        path.display(),
+                        file.positions.len()
+                    )));
+                }
+
+                file
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => CheckpointFile {
+                spec_hash,
+                record_count,
+                positions: partitions.iter().map(|p| p.start).collect(),
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: path.to_owned(),
+            interval,
+            state: Mutex::new((state, 0)),
+            written: Mutex::new(0),
+        })
+    }
+
+    /// Load position worker `i` resumes from.
+    pub(crate) fn position(&self, i: usize) -> usize {
+        self.state.lock().unwrap().0.positions[i]
+    }
+
+    /// Records that worker `i` has committed everything before `next`, its
+    /// `batches`-th committed batch, and saves the checkpoint every
+    /// `interval` batches.
+    pub(crate) fn advance(&self, i: usize, next: usize, batches: usize) -> Result<()> {
+        let snapshot = {
+            let mut state = self.state.lock().unwrap();
+            state.0.positions[i] = next;
+
+            if !batches.is_multiple_of(self.interval) {
+                return Ok(());
+            }
+
+            Self::snapshot(&mut state)?
+        };
+
+        self.write(snapshot)
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        let snapshot = Self::snapshot(&mut self.state.lock().unwrap())?;
+
+        self.write(snapshot)
+    }
+
+    fn snapshot(state: &mut (CheckpointFile, u64)) -> Result<(u64, Vec<u8>)> {
+        state.1 += 1;
+        let data = serde_json::to_vec(&state.0).map_err(io::Error::from)?;
+
+        Ok((state.1, data))
+    }
+
+    /// Replaces the file in one rename, once the new contents are on disk,
+    /// so a crash never leaves half of it.
+    fn write(&self, (snapshot, data): (u64, Vec<u8>)) -> Result<()> {
+        let mut written = self.written.lock().unwrap();
+        if *written > snapshot {
+            return Ok(());
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+
+        *written = snapshot;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint() {
+        let path = std::env::temp_dir().join(format!(
+            "ycsb_rs_test_checkpoint_{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let partitions = [0..50, 50..100];
+
+        let checkpoint = LoadCheckpoint::open(&path, 2, 42, &partitions).unwrap();
+        assert_eq!(checkpoint.position(1), 50);
+        checkpoint.advance(0, 10, 1).unwrap();
+        assert!(!path.exists());
+        checkpoint.advance(1, 70, 2).unwrap();
+
+        let checkpoint = LoadCheckpoint::open(&path, 2, 42, &partitions).unwrap();
+        assert_eq!((checkpoint.position(0), checkpoint.position(1)), (10, 70));
+
+        assert!(LoadCheckpoint::open(&path, 2, 43, &partitions).is_err());
+        assert!(LoadCheckpoint::open(&path, 2, 42, &[0..30, 30..60, 60..100]).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
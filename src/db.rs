@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{Error, Result};
 
 pub trait Db: Sync + Send {
     type Transaction;
@@ -39,6 +39,12 @@ pub trait Db: Sync + Send {
         length: usize,
         fields: Option<Vec<String>>,
     ) -> Result<Vec<Vec<(String, String)>>>;
+
+    /// Deletes a record. Databases that cannot delete keep this default,
+    /// which fails with `Error::InvalidArgument`.
+    fn delete(&self, _txn: &mut Self::Transaction, _table: &str, _key: &str) -> Result<()> {
+        Err(Error::InvalidArgument("delete is not supported".to_owned()))
+    }
 }
 
 pub struct MockDb {
@@ -165,4 +171,12 @@ impl Db for MockDb {
 
         Ok(Vec::new())
     }
+
+    fn delete(&self, _txn: &mut Self::Transaction, table: &str, key: &str) -> Result<()> {
+        if !self.quiet {
+            println!("DELETE {} {}", table, key);
+        }
+
+        Ok(())
+    }
 }
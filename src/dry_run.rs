@@ -0,0 +1,325 @@
+//! Dry runs without a database.
+//!
+//! `NullDb` accepts every operation without storing anything and aggregates
+//! what it was asked to do, so a workload can be checked, and the harness's
+//! own throughput ceiling measured, with the full threading and batching
+//! machinery but no database behind it:
+//!
+//! ```no_run
+//! use ycsb_rs::{run_ycsb_with, NullDb, RunOptions, WorkloadSpec};
+//!
+//! let db = NullDb::new();
+//! let spec = WorkloadSpec::default()
+//!     .record_count(1000)
+//!     .operation_count(10000);
+//!
+//! run_ycsb_with(&db, spec, RunOptions::new().threads(4)).unwrap();
+//! println!("{}", db.summary());
+//! ```
+
+use crate::{Db, Operation, Result};
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+
+/// Independently locked key counters, so threads rarely wait on each other.
+const KEY_SHARDS: usize = 64;
+
+/// Number of most frequently accessed keys in the summary.
+const TOP_KEYS: usize = 20;
+
+/// Count, minimum, maximum and mean of a series of lengths.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LengthStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+}
+
+impl Display for LengthStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "count {}, min {}, max {}, mean {:.1}",
+            self.count, self.min, self.max, self.mean
+        )
+    }
+}
+
+struct LengthCounter {
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl LengthCounter {
+    fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, len: usize) {
+        let len = len as u64;
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(len, Ordering::Relaxed);
+        self.min.fetch_min(len, Ordering::Relaxed);
+        self.max.fetch_max(len, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> LengthStats {
+        let count = self.count.load(Ordering::Relaxed);
+
+        if count == 0 {
+            return LengthStats::default();
+        }
+
+        LengthStats {
+            count,
+            min: self.min.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+            mean: self.sum.load(Ordering::Relaxed) as f64 / count as f64,
+        }
+    }
+}
+
+/// What a `NullDb` was asked to do.
+#[derive(Clone, Debug, Serialize)]
+pub struct DryRunSummary {
+    /// Calls per operation type. A read-modify-write reaches the database as
+    /// a read and an update, so it never shows up here.
+    pub operations: Vec<(Operation, u64)>,
+    /// The most frequently accessed keys with their access counts, most
+    /// popular first.
+    pub top_keys: Vec<(String, u64)>,
+    /// Lengths of the values written by inserts and updates.
+    pub field_length: LengthStats,
+    /// Lengths requested by scans.
+    pub scan_length: LengthStats,
+}
+
+impl Display for DryRunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total: u64 = self.operations.iter().map(|(_, count)| count).sum();
+
+        writeln!(f, "Operations:")?;
+        for (op, count) in &self.operations {
+            writeln!(
+                f,
+                "  {}: {} ({:.2}%)",
+                op,
+                count,
+                *count as f64 / total as f64 * 100.0
+            )?;
+        }
+
+        writeln!(f, "Top keys:")?;
+        for (key, count) in &self.top_keys {
+            writeln!(f, "  {}: {}", key, count)?;
+        }
+
+        writeln!(f, "Field length: {}", self.field_length)?;
+        write!(f, "Scan length: {}", self.scan_length)
+    }
+}
+
+/// A `Db` that stores nothing and summarizes the operations it receives.
+/// Reads return no record and scans return no rows.
+pub struct NullDb {
+    operations: [AtomicU64; Operation::ALL.len()],
+    keys: Vec<Mutex<HashMap<String, u64>>>,
+    field_length: LengthCounter,
+    scan_length: LengthCounter,
+}
+
+impl Default for NullDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NullDb {
+    pub fn new() -> Self {
+        Self {
+            operations: Default::default(),
+            keys: (0..KEY_SHARDS)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+            field_length: LengthCounter::new(),
+            scan_length: LengthCounter::new(),
+        }
+    }
+
+    pub fn summary(&self) -> DryRunSummary {
+        let operations = Operation::ALL
+            .iter()
+            .map(|&op| (op, self.operations[op as usize].load(Ordering::Relaxed)))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+
+        let mut top_keys = self
+            .keys
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(key, &count)| (key.clone(), count))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        top_keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_keys.truncate(TOP_KEYS);
+
+        DryRunSummary {
+            operations,
+            top_keys,
+            field_length: self.field_length.stats(),
+            scan_length: self.scan_length.stats(),
+        }
+    }
+
+    fn record(&self, op: Operation, key: &str) {
+        self.operations[op as usize].fetch_add(1, Ordering::Relaxed);
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let shard = &self.keys[hasher.finish() as usize % KEY_SHARDS];
+
+        let mut shard = shard.lock().unwrap();
+        match shard.get_mut(key) {
+            Some(count) => *count += 1,
+            None => {
+                shard.insert(key.to_owned(), 1);
+            }
+        }
+    }
+
+    fn record_values(&self, values: &[(String, String)]) {
+        for (_, value) in values {
+            self.field_length.record(value.len());
+        }
+    }
+}
+
+impl Db for NullDb {
+    type Transaction = ();
+
+    fn start_transaction(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit_transaction(&self, _txn: ()) -> Result<()> {
+        Ok(())
+    }
+
+    fn abort_transaction(&self, _txn: ()) -> Result<()> {
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        _txn: &mut (),
+        _table: &str,
+        key: &str,
+        _fields: Option<Vec<String>>,
+    ) -> Result<Option<Vec<(String, String)>>> {
+        self.record(Operation::Read, key);
+        Ok(None)
+    }
+
+    fn insert(
+        &self,
+        _txn: &mut (),
+        _table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.record(Operation::Insert, &key);
+        self.record_values(&values);
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        _txn: &mut (),
+        _table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.record(Operation::Update, &key);
+        self.record_values(&values);
+        Ok(())
+    }
+
+    fn scan(
+        &self,
+        _txn: &mut (),
+        _table: &str,
+        key: &str,
+        length: usize,
+        _fields: Option<Vec<String>>,
+    ) -> Result<Vec<Vec<(String, String)>>> {
+        self.record(Operation::Scan, key);
+        self.scan_length.record(length);
+        Ok(Vec::new())
+    }
+
+    fn delete(&self, _txn: &mut (), _table: &str, key: &str) -> Result<()> {
+        self.record(Operation::Delete, key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_ycsb_with, RunOptions, WorkloadSpec};
+
+    #[test]
+    fn test_dry_run() {
+        let db = NullDb::new();
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1000)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .scan_proportion(0.5)
+            .field_len_const(20);
+
+        run_ycsb_with(&db, spec, RunOptions::new().threads(2)).unwrap();
+
+        let summary = db.summary();
+        let count = |op| {
+            summary
+                .operations
+                .iter()
+                .find(|&&(o, _)| o == op)
+                .map_or(0, |&(_, count)| count)
+        };
+
+        assert_eq!(count(Operation::Insert), 100);
+        assert_eq!(count(Operation::Read) + count(Operation::Scan), 1000);
+        assert_eq!(summary.top_keys.len(), TOP_KEYS);
+        assert!(summary.top_keys.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(summary.field_length.min, 20);
+        assert_eq!(summary.field_length.max, 20);
+        assert_eq!(summary.scan_length.count, count(Operation::Scan));
+    }
+}
@@ -1,19 +1,164 @@
-use crate::{Error, Result};
+use crate::{Error, Result, RngKind};
 
 use std::{
+    cell::RefCell,
+    collections::BTreeSet,
     marker::PhantomData,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
 use rand::{
     distributions::{Distribution, Uniform},
-    Rng,
+    rngs::SmallRng,
+    Rng, RngCore, SeedableRng,
 };
+use rand_chacha::{ChaCha20Rng, ChaCha8Rng};
 
 const ZIPFIAN_CONSTANT: f64 = 0.99;
 
-pub trait Generator<T> {
-    fn next(&self) -> T;
+thread_local! {
+    static RNG: RefCell<WorkloadRng> = RefCell::new(WorkloadRng::new(RngKind::Small, None, 0));
+}
+
+/// Runs `f` with this thread's random number generator, which
+/// `Generator::next_value` draws from. `f` must not call `with_rng` again.
+pub(crate) fn with_rng<R>(f: impl FnOnce(&mut WorkloadRng) -> R) -> R {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Replaces this thread's random number generator, e.g. with a seeded one
+/// to make the values generated on this thread from now on reproducible.
+pub(crate) fn set_rng(new: WorkloadRng) {
+    RNG.with(|rng| *rng.borrow_mut() = new);
+}
+
+/// A random number generator of any `RngKind`.
+pub(crate) enum WorkloadRng {
+    Small(SmallRng),
+    ChaCha8(ChaCha8Rng),
+    ChaCha20(ChaCha20Rng),
+}
+
+impl WorkloadRng {
+    /// A generator of `kind` for `stream`, e.g. a worker thread, seeded from
+    /// `seed` or else from entropy. ChaCha generators of one seed draw
+    /// independent streams; small ones are seeded with `seed ^ stream`.
+    pub(crate) fn new(kind: RngKind, seed: Option<u64>, stream: usize) -> Self {
+        match (kind, seed) {
+            (RngKind::Small, Some(seed)) => {
+                WorkloadRng::Small(SmallRng::seed_from_u64(seed ^ stream as u64))
+            }
+            (RngKind::Small, None) => WorkloadRng::Small(SmallRng::from_entropy()),
+            (RngKind::ChaCha8, seed) => {
+                let mut rng = seed.map_or_else(ChaCha8Rng::from_entropy, ChaCha8Rng::seed_from_u64);
+                rng.set_stream(stream as u64);
+                WorkloadRng::ChaCha8(rng)
+            }
+            (RngKind::ChaCha20, seed) => {
+                let mut rng =
+                    seed.map_or_else(ChaCha20Rng::from_entropy, ChaCha20Rng::seed_from_u64);
+                rng.set_stream(stream as u64);
+                WorkloadRng::ChaCha20(rng)
+            }
+        }
+    }
+
+    /// A generator of `kind` seeded from `rng`.
+    pub(crate) fn from_rng<R: RngCore>(kind: RngKind, rng: R) -> Self {
+        match kind {
+            RngKind::Small => WorkloadRng::Small(SmallRng::from_rng(rng).unwrap()),
+            RngKind::ChaCha8 => WorkloadRng::ChaCha8(ChaCha8Rng::from_rng(rng).unwrap()),
+            RngKind::ChaCha20 => WorkloadRng::ChaCha20(ChaCha20Rng::from_rng(rng).unwrap()),
+        }
+    }
+}
+
+impl RngCore for WorkloadRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            WorkloadRng::Small(rng) => rng.next_u32(),
+            WorkloadRng::ChaCha8(rng) => rng.next_u32(),
+            WorkloadRng::ChaCha20(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            WorkloadRng::Small(rng) => rng.next_u64(),
+            WorkloadRng::ChaCha8(rng) => rng.next_u64(),
+            WorkloadRng::ChaCha20(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            WorkloadRng::Small(rng) => rng.fill_bytes(dest),
+            WorkloadRng::ChaCha8(rng) => rng.fill_bytes(dest),
+            WorkloadRng::ChaCha20(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        match self {
+            WorkloadRng::Small(rng) => rng.try_fill_bytes(dest),
+            WorkloadRng::ChaCha8(rng) => rng.try_fill_bytes(dest),
+            WorkloadRng::ChaCha20(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+const FNV_OFFSET_BASIS_64: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME_64: u64 = 0x0100_0000_01b3;
+
+/// FNV-1a hash of the 8 bytes of `val`, least significant first, made
+/// non-negative as a Java `long`: the same as upstream YCSB's
+/// `Utils.fnvhash64`. The one hash Java leaves negative, `i64::MIN`, comes
+/// out as 2^63 here.
+pub fn fnv_hash64(mut val: u64) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS_64;
+
+    for _ in 0..8 {
+        hash ^= val & 0xff;
+        hash = hash.wrapping_mul(FNV_PRIME_64);
+        val >>= 8;
+    }
+
+    // Upstream takes the absolute value of the signed hash.
+    (hash as i64).unsigned_abs()
+}
+
+/// A source of values. Generators are shared by the worker threads, so any
+/// state they keep must be thread-safe:
+///
+/// ```compile_fail,E0277
+/// use rand::RngCore;
+/// use std::cell::Cell;
+/// use ycsb_rs::Generator;
+///
+/// struct CellCounter(Cell<u64>);
+///
+/// impl Generator<u64> for CellCounter {
+///     fn next(&self, _rng: &mut dyn RngCore) -> u64 {
+///         self.0.replace(self.0.get() + 1)
+///     }
+/// }
+/// ```
+pub trait Generator<T>: Send + Sync {
+    fn next(&self, rng: &mut dyn RngCore) -> T;
+
+    /// Like `next`, drawing from this thread's random number generator.
+    fn next_value(&self) -> T {
+        with_rng(|rng| self.next(rng))
+    }
+
+    /// Appends `n` values to `out`, the same ones `n` calls of `next` would
+    /// draw, in one call.
+    fn sample_many(&self, rng: &mut dyn RngCore, n: usize, out: &mut Vec<T>) {
+        out.extend((0..n).map(|_| self.next(rng)));
+    }
 }
 
 pub struct ConstGenerator<T> {
@@ -28,9 +173,9 @@ impl<T> ConstGenerator<T> {
 
 impl<T> Generator<T> for ConstGenerator<T>
 where
-    T: Clone,
+    T: Clone + Send + Sync,
 {
-    fn next(&self) -> T {
+    fn next(&self, _rng: &mut dyn RngCore) -> T {
         self.val.clone()
     }
 }
@@ -40,61 +185,88 @@ pub struct DistributionGenerator<T, D: Distribution<T>> {
     value_type: PhantomData<T>,
 }
 
+impl<T, D: Distribution<T>> DistributionGenerator<T, D> {
+    /// Like `Generator::sample_many`, calling `rng` directly rather than
+    /// through a trait object.
+    pub fn sample_many_with<R: Rng + ?Sized>(&self, rng: &mut R, n: usize, out: &mut Vec<T>) {
+        out.extend((&self.dist).sample_iter(rng).take(n));
+    }
+}
+
 impl<D, T> Generator<T> for DistributionGenerator<T, D>
 where
-    T: Clone,
-    D: Distribution<T>,
+    T: Clone + Send + Sync,
+    D: Distribution<T> + Send + Sync,
 {
-    fn next(&self) -> T {
-        let val = self.dist.sample(&mut rand::thread_rng());
-        val
+    fn next(&self, rng: &mut dyn RngCore) -> T {
+        self.dist.sample(rng)
+    }
+
+    fn sample_many(&self, rng: &mut dyn RngCore, n: usize, out: &mut Vec<T>) {
+        self.sample_many_with(rng, n, out)
     }
 }
 
+/// Uniform over `min..=max`.
 pub fn uniform_gen<T>(min: T, max: T) -> DistributionGenerator<T, Uniform<T>>
 where
     T: rand::distributions::uniform::SampleUniform,
 {
     DistributionGenerator {
-        dist: Uniform::new(min, max),
+        dist: Uniform::new_inclusive(min, max),
         value_type: PhantomData,
     }
 }
 
 pub struct DiscreteDistribution<T> {
-    values: Vec<(T, f64)>,
-    sum: f64,
+    values: Vec<T>,
+    /// Normalized running total of the weights up to and including each value.
+    cumulative: Vec<f64>,
 }
 
 impl<T> DiscreteDistribution<T> {
     fn new(values: Vec<(T, f64)>) -> Self {
-        let sum = values.iter().map(|x| x.1).sum();
+        assert!(!values.is_empty(), "discrete distribution needs a value");
+        let sum: f64 = values.iter().map(|x| x.1).sum();
 
-        Self { values, sum }
+        let mut acc = 0.0;
+        let cumulative = values
+            .iter()
+            .map(|(_, weight)| {
+                acc += weight / sum;
+                acc
+            })
+            .collect();
+        let values = values.into_iter().map(|(t, _)| t).collect();
+
+        Self { values, cumulative }
     }
 }
 
 impl<T> Distribution<T> for DiscreteDistribution<T>
 where
-    T: Clone + Default,
+    T: Clone,
 {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
         let val: f64 = rng.gen();
+        let i = self.cumulative.partition_point(|&acc| acc <= val);
 
-        let mut acc = 0.0;
-        for (t, weight) in &self.values {
-            acc += weight / self.sum;
+        // Rounding can leave the weights summing to just below `val`.
+        self.values[i.min(self.values.len() - 1)].clone()
+    }
+}
 
-            if val < acc {
-                return t.clone();
-            }
-        }
+/// Lengths drawn from `(length, weight)` buckets, each picked with
+/// probability proportional to its weight.
+pub type HistogramGenerator = DistributionGenerator<usize, DiscreteDistribution<usize>>;
 
-        Default::default()
-    }
+pub fn histogram_gen(buckets: Vec<(usize, f64)>) -> HistogramGenerator {
+    discrete_gen(buckets)
 }
 
-pub fn discrete_gen<T: Clone + Default>(
+/// Picks from `(value, weight)` pairs with probability proportional to the
+/// weights. Panics if `values` is empty.
+pub fn discrete_gen<T: Clone>(
     values: Vec<(T, f64)>,
 ) -> DistributionGenerator<T, DiscreteDistribution<T>> {
     DistributionGenerator {
@@ -103,17 +275,22 @@ pub fn discrete_gen<T: Clone + Default>(
     }
 }
 
+/// Zipfian over `min..=max`, with `min` the most popular value.
+///
+/// Sampled by rejection-inversion (Hörmann and Derflinger, "Rejection-inversion
+/// to generate variates from monotone discrete distributions"), which is exact
+/// for any `theta > 0` and needs no O(n) setup.
 pub struct ZipfDistribution {
-    base: usize,
-    num_items: usize,
+    base: u64,
+    num_items: u64,
     theta: f64,
-    zeta_n: f64,
-    alpha: f64,
-    eta: f64,
+    h_integral_x1: f64,
+    h_integral_n: f64,
+    s: f64,
 }
 
 impl ZipfDistribution {
-    pub fn new(min: usize, max: usize, theta: f64) -> Result<Self> {
+    pub fn new(min: u64, max: u64, theta: f64) -> Result<Self> {
         if max < min {
             return Err(Error::InvalidArgument("max < min".to_owned()));
         }
@@ -123,54 +300,108 @@ impl ZipfDistribution {
             return Err(Error::InvalidArgument("max - min < 2".to_owned()));
         }
 
-        if theta == 1.0 {
-            return Err(Error::InvalidArgument("theta == 1.0".to_owned()));
+        if !(theta > 0.0 && theta.is_finite()) {
+            return Err(Error::InvalidArgument(format!(
+                "theta must be positive, got {}",
+                theta
+            )));
         }
 
-        let alpha = 1.0 / (1.0 - theta);
-        let zeta_2 = Self::zeta(2, theta);
-        let zeta_n = Self::zeta(num_items, theta);
-
-        let eta = (1.0 - (2.0 / num_items as f64).powf(1.0 - theta)) / (1.0 - zeta_2 / zeta_n);
-
         Ok(Self {
             base: min,
             num_items,
             theta,
-            zeta_n,
-            alpha,
-            eta,
+            h_integral_x1: h_integral(1.5, theta) - 1.0,
+            h_integral_n: h_integral(num_items as f64 + 0.5, theta),
+            s: 2.0 - h_integral_inverse(h_integral(2.5, theta) - h(2.0, theta), theta),
         })
     }
 
-    fn zeta(num: usize, theta: f64) -> f64 {
-        (0..num).map(|i| 1.0 / ((i + 1) as f64).powf(theta)).sum()
+    pub fn num_items(&self) -> u64 {
+        self.num_items
+    }
+
+    /// Grows the range to `min..=new_max`. A smaller `new_max` is ignored.
+    pub fn extend(&mut self, new_max: u64) {
+        let num_items = (new_max + 1).saturating_sub(self.base);
+
+        if num_items > self.num_items {
+            self.num_items = num_items;
+            self.h_integral_n = h_integral(num_items as f64 + 0.5, self.theta);
+        }
+    }
+}
+
+impl Distribution<u64> for ZipfDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        loop {
+            let u = self.h_integral_n + rng.gen::<f64>() * (self.h_integral_x1 - self.h_integral_n);
+            let x = h_integral_inverse(u, self.theta);
+            let k = ((x + 0.5) as u64).clamp(1, self.num_items);
+
+            if k as f64 - x <= self.s
+                || u >= h_integral(k as f64 + 0.5, self.theta) - h(k as f64, self.theta)
+            {
+                return self.base + k - 1;
+            }
+        }
     }
 }
 
+/// For lengths and other values below `usize::MAX`, as the range was built
+/// from `usize` bounds.
 impl Distribution<usize> for ZipfDistribution {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
-        let u: f64 = rng.gen();
-        let uz = u * self.zeta_n;
+        Distribution::<u64>::sample(self, rng) as usize
+    }
+}
 
-        if uz < 1.0 {
-            return self.base;
-        }
+/// `x^-theta`, the unnormalized probability of rank `x`.
+fn h(x: f64, theta: f64) -> f64 {
+    (-theta * x.ln()).exp()
+}
 
-        if uz < 1.0 + 0.5f64.powf(self.theta) {
-            return self.base + 1;
-        }
+/// An antiderivative of `h`, `(x^(1 - theta) - 1) / (1 - theta)`, which is
+/// `ln(x)` for `theta == 1`.
+fn h_integral(x: f64, theta: f64) -> f64 {
+    let log_x = x.ln();
+
+    expm1_over_x((1.0 - theta) * log_x) * log_x
+}
+
+fn h_integral_inverse(x: f64, theta: f64) -> f64 {
+    let t = (x * (1.0 - theta)).max(-1.0);
+
+    (log1p_over_x(t) * x).exp()
+}
+
+/// `ln(1 + x) / x`, continuous at 0.
+fn log1p_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 {
+        x.ln_1p() / x
+    } else {
+        1.0 - x * (0.5 - x * (1.0 / 3.0 - 0.25 * x))
+    }
+}
 
-        self.base
-            + (self.num_items as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as usize
+/// `(e^x - 1) / x`, continuous at 0.
+fn expm1_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 {
+        x.exp_m1() / x
+    } else {
+        1.0 + x * 0.5 * (1.0 + x / 3.0 * (1.0 + 0.25 * x))
     }
 }
 
-pub fn zipfian_gen(
+/// Zipfian over `0..=num_elements`, as `usize` lengths or `u64` key numbers.
+pub fn zipfian_gen<T>(
     num_elements: usize,
     theta: f64,
-) -> Result<DistributionGenerator<usize, ZipfDistribution>> {
-    let dist = ZipfDistribution::new(0, num_elements, theta)?;
+) -> Result<DistributionGenerator<T, ZipfDistribution>>
+where
+    ZipfDistribution: Distribution<T>,
+{
+    let dist = ZipfDistribution::new(0, num_elements as u64, theta)?;
 
     Ok(DistributionGenerator {
         dist,
@@ -178,6 +409,140 @@ pub fn zipfian_gen(
     })
 }
 
+/// Zipfian over `0..num_items`, with each rank hashed to a key so that the
+/// popular keys are spread over the range instead of being the smallest ones.
+pub struct ScrambledZipfianGenerator {
+    zipfian: ZipfDistribution,
+    num_items: u64,
+}
+
+impl ScrambledZipfianGenerator {
+    pub fn new(num_items: usize, theta: f64) -> Result<Self> {
+        if num_items == 0 {
+            return Err(Error::InvalidArgument("num_items == 0".to_owned()));
+        }
+
+        let num_items = num_items as u64;
+
+        Ok(Self {
+            zipfian: ZipfDistribution::new(0, num_items - 1, theta)?,
+            num_items,
+        })
+    }
+}
+
+impl Generator<u64> for ScrambledZipfianGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        let rank = Distribution::<u64>::sample(&self.zipfian, rng);
+
+        fnv_hash64(rank) % self.num_items
+    }
+
+    fn sample_many(&self, rng: &mut dyn RngCore, n: usize, out: &mut Vec<u64>) {
+        out.extend(
+            Distribution::<u64>::sample_iter(&self.zipfian, rng)
+                .take(n)
+                .map(|rank| fnv_hash64(rank) % self.num_items),
+        );
+    }
+}
+
+/// Uniform over `min..=max`, where keys past those `count` has handed out
+/// beyond `start` are left out until they are inserted.
+pub struct GrowingUniformGenerator {
+    count: Arc<AcknowledgedCounterGenerator>,
+    start: u64,
+    min: u64,
+    max: u64,
+}
+
+impl GrowingUniformGenerator {
+    pub fn new(
+        count: Arc<AcknowledgedCounterGenerator>,
+        start: u64,
+        min: usize,
+        max: usize,
+    ) -> Self {
+        Self {
+            count,
+            start,
+            min: min as u64,
+            max: max as u64,
+        }
+    }
+}
+
+impl Generator<u64> for GrowingUniformGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        let num_items = self.count.last_value() - self.start;
+        let max = self.max.min(num_items.saturating_sub(1)).max(self.min);
+
+        rng.gen_range(self.min, max + 1)
+    }
+}
+
+/// Zipfian over the keys `count` has handed out beyond `start`, growing with
+/// the counter so that newly inserted keys are requested too. With `scramble`
+/// the ranks are hashed like `ScrambledZipfianGenerator`, so the hot keys move
+/// as the key space grows.
+pub struct GrowingZipfianGenerator {
+    zipfian: RwLock<ZipfDistribution>,
+    count: Arc<AcknowledgedCounterGenerator>,
+    start: u64,
+    scramble: bool,
+}
+
+impl GrowingZipfianGenerator {
+    pub fn new(
+        count: Arc<AcknowledgedCounterGenerator>,
+        start: u64,
+        theta: f64,
+        scramble: bool,
+    ) -> Result<Self> {
+        // The zipfian needs two items even before anything is inserted.
+        let num_items = (count.last_value() - start).max(2);
+
+        Ok(Self {
+            zipfian: RwLock::new(ZipfDistribution::new(0, num_items - 1, theta)?),
+            count,
+            start,
+            scramble,
+        })
+    }
+}
+
+impl Generator<u64> for GrowingZipfianGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        let live = self.count.last_value() - self.start;
+
+        // Drawn from a single snapshot of the zipfian, only taking the write
+        // lock to extend it.
+        let zipfian = self.zipfian.read().unwrap();
+        let rank = if zipfian.num_items() < live {
+            drop(zipfian);
+
+            // Another thread may have extended it first, which `extend`
+            // tolerates.
+            let mut zipfian = self.zipfian.write().unwrap();
+            zipfian.extend(live - 1);
+            Distribution::<u64>::sample(&*zipfian, rng)
+        } else {
+            Distribution::<u64>::sample(&*zipfian, rng)
+        };
+
+        // The zipfian has at least two items, which may be more than are
+        // live.
+        let live = live.max(1);
+        let rank = rank.min(live - 1);
+
+        if self.scramble {
+            fnv_hash64(rank) % live
+        } else {
+            rank
+        }
+    }
+}
+
 pub struct CounterGenerator {
     counter: AtomicU64,
 }
@@ -189,50 +554,275 @@ impl CounterGenerator {
         }
     }
 
-    pub fn last_value(&self) -> u64 {
-        self.counter.load(std::sync::atomic::Ordering::Relaxed)
+    /// Takes the next `count` values at once, returning the first.
+    pub fn advance(&self, count: u64) -> u64 {
+        self.counter.fetch_add(count, Ordering::Relaxed)
     }
 }
 impl Generator<u64> for CounterGenerator {
-    fn next(&self) -> u64 {
-        self.counter
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    fn next(&self, _rng: &mut dyn RngCore) -> u64 {
+        // Only the values need to be unique; the insert counters publish
+        // what has been inserted through `AcknowledgedCounterGenerator`.
+        self.counter.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// A counter whose values only count towards `last_value` once they are
+/// acknowledged, so that generators based on it don't run ahead of inserts
+/// still in flight. Values acknowledged out of order wait in a window until
+/// every earlier value has been acknowledged too.
+pub struct AcknowledgedCounterGenerator {
+    counter: CounterGenerator,
+    limit: AtomicU64,
+    window: Mutex<BTreeSet<u64>>,
+}
+
+impl AcknowledgedCounterGenerator {
+    pub fn new(val: u64) -> Self {
+        Self {
+            counter: CounterGenerator::new(val),
+            limit: AtomicU64::new(val),
+            window: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// The first value not acknowledged yet.
+    pub fn last_value(&self) -> u64 {
+        self.limit.load(Ordering::Acquire)
+    }
+
+    pub fn acknowledge(&self, value: u64) {
+        let mut window = self.window.lock().unwrap();
+        let mut limit = self.limit.load(Ordering::Relaxed);
+
+        if value != limit {
+            window.insert(value);
+            return;
+        }
+
+        limit += 1;
+        while window.remove(&limit) {
+            limit += 1;
+        }
+
+        self.limit.store(limit, Ordering::Release);
+    }
+}
+
+impl Generator<u64> for AcknowledgedCounterGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        self.counter.next(rng)
+    }
+}
+
+/// `0, 1, ..., n - 1` in turn, starting over after `n - 1`. Concurrent
+/// callers each get the next value, so no value is skipped or repeated
+/// within a round.
+pub struct SequentialGenerator {
+    counter: AtomicU64,
+    n: u64,
+}
+
+impl SequentialGenerator {
+    pub fn new(n: usize) -> Self {
+        Self {
+            counter: AtomicU64::new(0),
+            n: n as u64,
+        }
+    }
+}
+
+impl Generator<u64> for SequentialGenerator {
+    fn next(&self, _rng: &mut dyn RngCore) -> u64 {
+        // A 64-bit counter does not wrap in any realistic run, so taking the
+        // remainder keeps the sequence intact.
+        self.counter.fetch_add(1, Ordering::Relaxed) % self.n
     }
 }
 
+/// Exponentially distributed values with the given mean, such as the gaps
+/// between the arrivals of a Poisson process.
+pub struct ExponentialGenerator {
+    mean: f64,
+}
+
+impl ExponentialGenerator {
+    pub fn new(mean: f64) -> Self {
+        Self { mean }
+    }
+}
+
+impl Generator<f64> for ExponentialGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> f64 {
+        let u: f64 = rng.gen();
+
+        -self.mean * (1.0 - u).ln()
+    }
+}
+
+/// Exponentially distributed lengths with the given mean, rounded up and cut
+/// down to `max`: mostly short, with a few long ones but a finite variance.
+pub struct ExponentialLengthGenerator {
+    exponential: ExponentialGenerator,
+    max: usize,
+}
+
+impl ExponentialLengthGenerator {
+    pub fn new(mean: f64, max: usize) -> Self {
+        Self {
+            exponential: ExponentialGenerator::new(mean),
+            max,
+        }
+    }
+}
+
+impl Generator<usize> for ExponentialLengthGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> usize {
+        let x = self.exponential.next(rng).ceil();
+
+        if x < 1.0 {
+            1
+        } else if x < self.max as f64 {
+            x as usize
+        } else {
+            self.max
+        }
+    }
+}
+
+/// Pareto distributed lengths of at least `scale`, with a heavier tail the
+/// smaller `shape` is. Draws past `max` are cut down to it.
+pub struct ParetoGenerator {
+    scale: f64,
+    shape: f64,
+    max: usize,
+}
+
+impl ParetoGenerator {
+    pub fn new(scale: f64, shape: f64, max: usize) -> Self {
+        Self { scale, shape, max }
+    }
+}
+
+impl Generator<usize> for ParetoGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> usize {
+        let u: f64 = rng.gen();
+        let x = self.scale / (1.0 - u).powf(1.0 / self.shape);
+
+        if x < self.max as f64 {
+            x as usize
+        } else {
+            self.max
+        }
+    }
+}
+
+/// Normally distributed lengths, rounded and kept within `1..=max`.
+pub struct NormalGenerator {
+    normal: rand_distr::Normal<f64>,
+    max: usize,
+}
+
+impl NormalGenerator {
+    pub fn new(mean: f64, std_dev: f64, max: usize) -> Result<Self> {
+        if !(std_dev > 0.0 && std_dev.is_finite()) {
+            return Err(Error::InvalidArgument(format!(
+                "std_dev must be positive, got {}",
+                std_dev
+            )));
+        }
+
+        let normal = rand_distr::Normal::new(mean, std_dev)
+            .map_err(|e| Error::InvalidArgument(format!("{:?}", e)))?;
+
+        Ok(Self { normal, max })
+    }
+}
+
+impl Generator<usize> for NormalGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> usize {
+        let x = self.normal.sample(rng).round();
+
+        if x < 1.0 {
+            1
+        } else if x < self.max as f64 {
+            x as usize
+        } else {
+            self.max
+        }
+    }
+}
+
+/// Favors the most recent keys handed out by `basis` beyond `start`: the last
+/// one is the most popular, and so on back to the first. Keys are counted
+/// from `start`, like those of `GrowingUniformGenerator`.
 pub struct SkewedLatestGenerator {
-    basis: Arc<CounterGenerator>,
-    zipfian: ZipfDistribution,
+    basis: Arc<AcknowledgedCounterGenerator>,
+    start: u64,
+    // Built once `basis` has handed out two keys, then grown along with it.
+    zipfian: RwLock<Option<ZipfDistribution>>,
 }
 
 impl SkewedLatestGenerator {
-    pub fn new(basis: Arc<CounterGenerator>) -> Self {
-        let max = basis.last_value();
-
+    pub fn new(basis: Arc<AcknowledgedCounterGenerator>, start: u64) -> Self {
         Self {
             basis,
-            zipfian: ZipfDistribution::new(0, max as usize, ZIPFIAN_CONSTANT).unwrap(),
+            start,
+            zipfian: RwLock::new(None),
         }
     }
 }
 
-impl Generator<usize> for SkewedLatestGenerator {
-    fn next(&self) -> usize {
-        let max = self.basis.last_value();
+impl Generator<u64> for SkewedLatestGenerator {
+    fn next(&self, rng: &mut dyn RngCore) -> u64 {
+        let count = self.basis.last_value() - self.start;
+        if count < 2 {
+            return 0;
+        }
+
+        let stale = match &*self.zipfian.read().unwrap() {
+            Some(zipfian) => zipfian.num_items() < count,
+            None => true,
+        };
 
-        max as usize - self.zipfian.sample(&mut rand::thread_rng())
+        if stale {
+            let mut zipfian = self.zipfian.write().unwrap();
+
+            match &mut *zipfian {
+                Some(zipfian) => zipfian.extend(count - 1),
+                None => {
+                    *zipfian = Some(ZipfDistribution::new(0, count - 1, ZIPFIAN_CONSTANT).unwrap())
+                }
+            }
+        }
+
+        // Another thread may have grown the zipfian past `count`.
+        let zipfian = self.zipfian.read().unwrap();
+        let rank = Distribution::<u64>::sample(zipfian.as_ref().unwrap(), rng).min(count - 1);
+
+        count - 1 - rank
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{stats, Operation};
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn test_generators_are_thread_safe() {
+        fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+        assert_send_sync::<dyn Generator<usize>>();
+        assert_send_sync::<crate::CoreWorkload>();
+    }
 
     #[test]
     fn test_const_generator() {
         let gen = ConstGenerator::new(100);
 
-        assert_eq!(gen.next(), 100);
+        assert_eq!(gen.next_value(), 100);
     }
 
     #[test]
@@ -240,7 +830,351 @@ mod tests {
         let gen = CounterGenerator::new(0);
 
         for i in 0..10 {
-            assert_eq!(gen.next(), i);
+            assert_eq!(gen.next_value(), i);
+        }
+    }
+
+    #[test]
+    fn test_histogram_generator() {
+        let gen = histogram_gen(vec![(100, 9.0), (10_000, 1.0)]);
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        let long = samples.iter().filter(|&&len| len == 10_000).count();
+
+        assert!(samples.iter().all(|&len| len == 100 || len == 10_000));
+        assert!((800..1200).contains(&long), "{}", long);
+    }
+
+    #[test]
+    fn test_discrete_generator() {
+        let weights = [0.5, 0.0, 0.2, 0.3];
+        let gen = discrete_gen(weights.iter().copied().enumerate().collect());
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let mut counts = [0; 4];
+        for _ in 0..100_000 {
+            counts[gen.next(&mut rng)] += 1;
+        }
+        for (count, weight) in counts.iter().zip(&weights) {
+            let share = *count as f64 / 100_000.0;
+            assert!((share - weight).abs() < 0.01, "{:?}", counts);
+        }
+
+        let observed = stats::frequencies(4, 100_000, || gen.next(&mut rng));
+        let fit = stats::chi_square(&observed, &weights).unwrap();
+        assert!(fit.p_value > 0.001, "{:?}", fit);
+    }
+
+    #[test]
+    fn test_discrete_generator_single_value() {
+        let gen = discrete_gen(vec![(Operation::Read, 1.0)]);
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        assert!((0..5_000_000).all(|_| gen.next(&mut rng) == Operation::Read));
+        assert_eq!(gen.next(&mut StepRng::new(u64::MAX, 0)), Operation::Read);
+    }
+
+    #[test]
+    fn test_acknowledged_counter_generator() {
+        let gen = AcknowledgedCounterGenerator::new(10);
+        let values = (0..4).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert_eq!(values, [10, 11, 12, 13]);
+        assert_eq!(gen.last_value(), 10);
+
+        gen.acknowledge(12);
+        gen.acknowledge(11);
+        assert_eq!(gen.last_value(), 10);
+
+        gen.acknowledge(10);
+        assert_eq!(gen.last_value(), 13);
+
+        gen.acknowledge(13);
+        assert_eq!(gen.last_value(), 14);
+    }
+
+    #[test]
+    fn test_sequential_generator() {
+        let gen = SequentialGenerator::new(3);
+
+        assert_eq!(
+            (0..7).map(|_| gen.next_value()).collect::<Vec<_>>(),
+            [0, 1, 2, 0, 1, 2, 0]
+        );
+
+        let gen = SequentialGenerator::new(1000);
+        let mut seen = std::thread::scope(|s| {
+            let threads = (0..4)
+                .map(|_| s.spawn(|| (0..500).map(|_| gen.next_value()).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+
+            threads
+                .into_iter()
+                .flat_map(|t| t.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        seen.sort_unstable();
+
+        assert_eq!(seen, (0..1000).flat_map(|x| [x, x]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_zipf_distribution() {
+        const N: usize = 100;
+        const SAMPLES: usize = 200_000;
+
+        for &theta in &[0.8, 1.0, 1.2] {
+            let dist = ZipfDistribution::new(0, N as u64 - 1, theta).unwrap();
+            let mut counts = vec![0; N];
+            let mut rng = SmallRng::seed_from_u64(0);
+            for _ in 0..SAMPLES {
+                counts[Distribution::<usize>::sample(&dist, &mut rng)] += 1;
+            }
+
+            let zeta = (1..=N).map(|k| 1.0 / (k as f64).powf(theta)).sum::<f64>();
+            for (rank, &count) in counts.iter().enumerate().take(5) {
+                let expected = 1.0 / ((rank + 1) as f64).powf(theta) / zeta;
+                let actual = count as f64 / SAMPLES as f64;
+
+                assert!(
+                    (actual - expected).abs() < 0.1 * expected,
+                    "theta {} rank {}: {} vs {}",
+                    theta,
+                    rank,
+                    actual,
+                    expected
+                );
+            }
+
+            let fit = stats::chi_square(&counts, &stats::zipfian_probabilities(N, theta)).unwrap();
+            assert!(fit.p_value > 0.001, "theta {}: {:?}", theta, fit);
+        }
+
+        assert!(ZipfDistribution::new(0, 10, 0.0).is_err());
+
+        let mut dist = ZipfDistribution::new(10, 11, 0.99).unwrap();
+        dist.extend(5);
+        assert_eq!(dist.num_items(), 2);
+        dist.extend(1009);
+        assert_eq!(dist.num_items(), 1000);
+
+        let mut rng = rand::thread_rng();
+        let samples = (0..10_000)
+            .map(|_| Distribution::<u64>::sample(&dist, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(samples.iter().all(|x| (10..1010).contains(x)));
+        assert!(samples.iter().any(|&x| x > 500));
+
+        // Extending must give the same distribution as building it at size.
+        let mut rng = SmallRng::seed_from_u64(0);
+        let observed = stats::frequencies(1000, 100_000, || {
+            Distribution::<usize>::sample(&dist, &mut rng) - 10
+        });
+        let fit = stats::chi_square(&observed, &stats::zipfian_probabilities(1000, 0.99)).unwrap();
+        assert!(fit.p_value > 0.001, "{:?}", fit);
+    }
+
+    #[test]
+    fn test_uniform_generator() {
+        let gen = uniform_gen(1, 3);
+        let samples = (0..1000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|x| (1..=3).contains(x)));
+        assert!(samples.contains(&1) && samples.contains(&3));
+
+        let gen = uniform_gen(7, 7);
+        assert!((0..100).all(|_| gen.next_value() == 7));
+
+        let gen = uniform_gen(0, 99);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let observed = stats::frequencies(100, 100_000, || gen.next(&mut rng));
+        let fit = stats::chi_square(&observed, &stats::uniform_probabilities(100)).unwrap();
+        assert!(fit.p_value > 0.001, "{:?}", fit);
+    }
+
+    #[test]
+    fn test_growing_uniform_generator() {
+        let count = Arc::new(AcknowledgedCounterGenerator::new(20));
+        let gen = GrowingUniformGenerator::new(count.clone(), 10, 5, 14);
+        assert!((0..1000).all(|_| (5..10).contains(&gen.next_value())));
+
+        for _ in 0..10 {
+            count.acknowledge(count.next_value());
+        }
+        let samples = (0..1000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|x| (5..15).contains(x)));
+        assert!(samples.contains(&5) && samples.contains(&14));
+    }
+
+    #[test]
+    fn test_growing_zipfian_generator() {
+        let count = Arc::new(AcknowledgedCounterGenerator::new(110));
+        let gen = GrowingZipfianGenerator::new(count.clone(), 10, 0.99, false).unwrap();
+        assert!((0..1000).all(|_| gen.next_value() < 100));
+
+        for _ in 0..900 {
+            count.acknowledge(count.next_value());
+        }
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|&x| x < 1000));
+        assert!(samples.iter().any(|&x| x >= 100));
+
+        for scramble in [false, true] {
+            let count = Arc::new(AcknowledgedCounterGenerator::new(1));
+            let gen = GrowingZipfianGenerator::new(count, 0, 0.99, scramble).unwrap();
+            assert!((0..1000).all(|_| gen.next_value() == 0));
+        }
+    }
+
+    #[test]
+    fn test_scrambled_zipfian_generator() {
+        assert_eq!(fnv_hash64(0), 0x5738_07cd_d7e5_c63b);
+
+        let gen = ScrambledZipfianGenerator::new(1000, 0.99).unwrap();
+        let mut counts = vec![0; 1000];
+        for _ in 0..100_000 {
+            counts[gen.next_value() as usize] += 1;
         }
+
+        // The hottest key is rank 0 hashed, not key 0, and the first tenth of
+        // the keys gets nowhere near the bulk of the requests.
+        let hottest = (0..1000).max_by_key(|&key| counts[key]).unwrap();
+        assert_eq!(hottest, (fnv_hash64(0) % 1000) as usize);
+        let head = counts[..100].iter().sum::<usize>();
+        assert!(head < 40_000, "{}", head);
+    }
+
+    #[test]
+    fn test_skewed_latest_generator() {
+        let basis = Arc::new(AcknowledgedCounterGenerator::new(0));
+        let gen = SkewedLatestGenerator::new(basis.clone(), 0);
+        assert_eq!(gen.next_value(), 0);
+
+        basis.acknowledge(basis.next_value());
+        assert_eq!(gen.next_value(), 0);
+
+        for _ in 0..99 {
+            basis.acknowledge(basis.next_value());
+        }
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|&x| x < 100));
+        assert!(samples.iter().filter(|&&x| x == 99).count() > 1000);
+    }
+
+    #[test]
+    fn test_pareto_generator() {
+        let gen = ParetoGenerator::new(100.0, 3.0, usize::MAX);
+        let samples = (0..100_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        let mean = samples.iter().sum::<usize>() as f64 / samples.len() as f64;
+
+        // scale * shape / (shape - 1), less about 0.5 for rounding down.
+        assert!(samples.iter().all(|&x| x >= 100));
+        assert!((mean - 149.5).abs() < 3.0, "{}", mean);
+
+        let gen = ParetoGenerator::new(100.0, 0.5, 1000);
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+
+        // P(x >= 1000) = (100 / 1000)^0.5, so about a third hit the cap.
+        let capped = samples.iter().filter(|&&x| x == 1000).count();
+        assert!(samples.iter().all(|&x| (100..=1000).contains(&x)));
+        assert!(capped > 2800 && capped < 3500, "{}", capped);
+
+        // A draw of almost 1 would be infinite without the cap.
+        let gen = ParetoGenerator::new(1.0, 1.0, 1 << 20);
+        assert_eq!(gen.next(&mut StepRng::new(u64::MAX, 0)), 1 << 20);
+    }
+
+    #[test]
+    fn test_normal_generator() {
+        let gen = NormalGenerator::new(100.0, 10.0, usize::MAX).unwrap();
+        let samples = (0..100_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        let mean = samples.iter().sum::<usize>() as f64 / samples.len() as f64;
+        assert!((mean - 100.0).abs() < 0.5, "{}", mean);
+
+        let gen = NormalGenerator::new(5.0, 10.0, 20).unwrap();
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|&x| (1..=20).contains(&x)));
+        assert!(samples.contains(&1) && samples.contains(&20));
+
+        assert!(NormalGenerator::new(5.0, -1.0, 20).is_err());
+    }
+
+    #[test]
+    fn test_exponential_generator() {
+        let gen = ExponentialGenerator::new(2.0);
+        let samples = (0..100_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+
+        assert!(samples.iter().all(|&x| x >= 0.0));
+        assert!((mean - 2.0).abs() < 0.05, "{}", mean);
+    }
+
+    #[test]
+    fn test_exponential_length_generator() {
+        let gen = ExponentialLengthGenerator::new(100.0, usize::MAX);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let samples = (0..1_000_000)
+            .map(|_| gen.next(&mut rng))
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<usize>() as f64 / samples.len() as f64;
+
+        // Rounding up adds about half a byte.
+        assert!((mean - 100.0).abs() < 2.0, "{}", mean);
+        assert!(samples.iter().all(|&x| x >= 1));
+
+        let gen = ExponentialLengthGenerator::new(100.0, 150);
+        let samples = (0..10_000).map(|_| gen.next_value()).collect::<Vec<_>>();
+        assert!(samples.iter().all(|&x| (1..=150).contains(&x)));
+        assert!(samples.contains(&150));
+        assert_eq!(gen.next(&mut StepRng::new(0, 0)), 1);
+    }
+
+    #[test]
+    fn test_explicit_rng() {
+        let mut zeros = StepRng::new(0, 0);
+        assert_eq!(uniform_gen(5, 10).next(&mut zeros), 5);
+        assert_eq!(
+            discrete_gen(vec![('a', 1.0), ('b', 1.0)]).next(&mut zeros),
+            'a'
+        );
+        assert_eq!(ExponentialGenerator::new(2.0).next(&mut zeros), 0.0);
+
+        let mut ones = StepRng::new(u64::MAX, 0);
+        assert_eq!(uniform_gen(5, 10).next(&mut ones), 10);
+        assert_eq!(
+            discrete_gen(vec![('a', 1.0), ('b', 1.0)]).next(&mut ones),
+            'b'
+        );
+
+        let gen = ScrambledZipfianGenerator::new(1000, 0.99).unwrap();
+        let sample = |seed| {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            (0..100).map(|_| gen.next(&mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+    }
+
+    #[test]
+    fn test_sample_many() {
+        fn check<T: From<u8> + PartialEq + std::fmt::Debug>(gen: &dyn Generator<T>) {
+            let mut rng = SmallRng::seed_from_u64(1);
+            let expected = (0..50).map(|_| gen.next(&mut rng)).collect::<Vec<_>>();
+
+            let mut rng = SmallRng::seed_from_u64(1);
+            let mut out = vec![T::from(7)];
+            gen.sample_many(&mut rng, 50, &mut out);
+            assert_eq!(out[0], T::from(7));
+            assert_eq!(out[1..], expected[..]);
+        }
+
+        let generators: Vec<Box<dyn Generator<usize>>> = vec![
+            Box::new(uniform_gen(0, 100)),
+            Box::new(discrete_gen(vec![(1, 1.0), (2, 3.0)])),
+            Box::new(zipfian_gen(100, 0.99).unwrap()),
+            Box::new(ExponentialLengthGenerator::new(10.0, 100)),
+        ];
+        for gen in generators {
+            check(&*gen);
+        }
+
+        check::<u64>(&ScrambledZipfianGenerator::new(100, 0.99).unwrap());
     }
 }
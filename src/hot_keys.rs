@@ -0,0 +1,161 @@
+//! Counts of the most frequently drawn keys in bounded memory, with the
+//! Space-Saving algorithm (Metwally, Agrawal and El Abbadi, 2005).
+
+use serde::Serialize;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::Hash,
+};
+
+/// How often a key was drawn during a run phase; see
+/// `RunOptions::key_frequency`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct KeyFrequency {
+    pub table: String,
+    pub key_num: u64,
+    pub key: String,
+    /// Draws of the key, overestimated by at most `error`.
+    pub count: u64,
+    pub error: u64,
+}
+
+/// The `capacity` most frequent items seen so far with their counts. Any
+/// item making up more than 1 / `capacity` of the items seen is among them.
+#[derive(Clone, Debug)]
+pub(crate) struct SpaceSaving<K> {
+    capacity: usize,
+    /// Count and maximum overestimate of each tracked item.
+    counts: HashMap<K, (u64, u64)>,
+    by_count: BTreeSet<(u64, K)>,
+}
+
+impl<K: Copy + Hash + Ord> SpaceSaving<K> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            counts: HashMap::with_capacity(capacity),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn record(&mut self, item: K) {
+        if let Some(&(count, error)) = self.counts.get(&item) {
+            self.set(item, count + 1, error);
+        } else if self.counts.len() < self.capacity {
+            self.set(item, 1, 0);
+        } else if let Some(&(min, evicted)) = self.by_count.iter().next() {
+            // The new item takes the place of the least frequent one, which
+            // it may have been all along.
+            self.by_count.remove(&(min, evicted));
+            self.counts.remove(&evicted);
+            self.set(item, min + 1, min);
+        }
+    }
+
+    fn set(&mut self, item: K, count: u64, error: u64) {
+        if let Some((old, _)) = self.counts.insert(item, (count, error)) {
+            self.by_count.remove(&(old, item));
+        }
+        self.by_count.insert((count, item));
+    }
+
+    /// Count that any untracked item may have reached.
+    fn untracked_count(&self) -> u64 {
+        match self.by_count.iter().next() {
+            Some(&(min, _)) if self.counts.len() >= self.capacity => min,
+            _ => 0,
+        }
+    }
+
+    /// Adds the items counted by `other`, as if they had been recorded here.
+    pub(crate) fn merge(&mut self, other: &SpaceSaving<K>) {
+        let (own_min, other_min) = (self.untracked_count(), other.untracked_count());
+
+        let mut merged = self
+            .counts
+            .iter()
+            .map(|(&item, &(count, error))| {
+                let (c, e) = other
+                    .counts
+                    .get(&item)
+                    .copied()
+                    .unwrap_or((other_min, other_min));
+                (item, count + c, error + e)
+            })
+            .collect::<Vec<_>>();
+        merged.extend(
+            other
+                .counts
+                .iter()
+                .filter(|(item, _)| !self.counts.contains_key(item))
+                .map(|(&item, &(count, error))| (item, count + own_min, error + own_min)),
+        );
+
+        merged.sort_by_key(|&(item, count, _)| std::cmp::Reverse((count, item)));
+        merged.truncate(self.capacity);
+
+        self.counts.clear();
+        self.by_count.clear();
+        for (item, count, error) in merged {
+            self.set(item, count, error);
+        }
+    }
+
+    /// The `n` most frequent items with their counts and maximum
+    /// overestimates, most frequent first.
+    pub(crate) fn top(&self, n: usize) -> Vec<(K, u64, u64)> {
+        self.by_count
+            .iter()
+            .rev()
+            .take(n)
+            .map(|&(count, item)| (item, count, self.counts[&item].1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats;
+    use rand::{distributions::Distribution, rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_space_saving() {
+        let mut exact = SpaceSaving::new(10);
+        for item in &[1, 2, 2, 3, 3, 3] {
+            exact.record(*item);
+        }
+        assert_eq!(exact.top(2), vec![(3, 3, 0), (2, 2, 0)]);
+
+        // Draws of 0..1000 weighted by a zipfian distribution, in two halves
+        // counted separately.
+        let weights = stats::zipfian_probabilities(1000, 0.99);
+        let dist = rand::distributions::WeightedIndex::new(&weights).unwrap();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut counts = vec![0; 1000];
+        let mut halves = vec![SpaceSaving::new(50), SpaceSaving::new(50)];
+
+        for i in 0..100_000 {
+            let item = dist.sample(&mut rng);
+            counts[item] += 1;
+            halves[i % 2].record(item);
+        }
+
+        let mut summary = halves.pop().unwrap();
+        summary.merge(&halves[0]);
+
+        let top = summary.top(5);
+        assert_eq!(
+            top.iter().map(|(item, _, _)| *item).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+        for (item, count, error) in top {
+            assert!(count >= counts[item] && count - error <= counts[item]);
+        }
+    }
+}
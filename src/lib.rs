@@ -1,109 +1,243 @@
-extern crate fasthash;
+#[cfg(feature = "async")]
+extern crate async_trait;
+#[cfg(feature = "core_affinity")]
+extern crate core_affinity;
 extern crate indicatif;
 extern crate rand;
 extern crate serde;
 extern crate serde_json;
+#[cfg(feature = "async")]
+extern crate tokio;
+extern crate toml;
 
+#[cfg(feature = "async")]
+mod async_db;
+mod checkpoint;
 mod db;
+mod dry_run;
 mod generator;
+mod hot_keys;
+mod measurement;
+#[cfg(feature = "prometheus")]
+mod metrics;
+mod options;
 mod result;
+mod sla;
+pub mod stats;
+mod trace;
 mod workload;
+mod xxh64;
 
+#[cfg(feature = "async")]
+pub use crate::async_db::{run_phases_async_with, run_ycsb_async, run_ycsb_async_with, AsyncDb};
+// Only for the doc test showing that `Generator` cannot be implemented by
+// types that are not thread-safe.
+#[doc(hidden)]
+pub use crate::generator::Generator;
 pub use crate::{
     db::{Db, MockDb},
+    dry_run::{DryRunSummary, LengthStats, NullDb},
+    hot_keys::KeyFrequency,
+    measurement::{
+        ErrorCount, Fairness, LatencyHistogram, LatencySummary, OpResult, PhaseResult, RunResult,
+        StageLatency, ThreadResult, VerificationReport,
+    },
+    options::{ErrorPolicy, Hook, Phase, RunOptions},
     result::{Error, Result},
-    workload::{CoreWorkload, Operation, WorkloadSpec},
+    sla::SlaSpec,
+    trace::TraceDb,
+    workload::{
+        CoreWorkload, DistributionSpec, KeyHashKind, KeyMapper, Operation, RngKind, Table,
+        ValueKind, WorkloadSession, WorkloadSource, WorkloadSpec,
+    },
 };
 
-use std::{fs::File, io::prelude::*, path::Path, sync::Arc, thread, time::Instant};
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    path::Path,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    checkpoint::LoadCheckpoint,
+    generator::ExponentialGenerator,
+    measurement::{Measurements, RawLatencyBuffer, Stage, ThreadStats, ThroughputSeries},
+};
 
 use indicatif::{ProgressBar, ProgressStyle};
+use rand::RngCore;
 
+/// Issues single operations drawn from a workload, for callers that want to
+/// drive the database with their own loops. Reads return what the database
+/// returned, writes the number of value bytes they sent. Committing or
+/// aborting `txn` is left to the caller.
+///
+/// When the workload checks data integrity, the values returned by reads,
+/// scans and read-modify-writes are checked too; see `verification_errors`.
 pub struct Client<'a, T> {
     db: &'a dyn Db<Transaction = T>,
     workload: &'a CoreWorkload,
+    session: RefCell<WorkloadSession<'a>>,
+    verification_errors: Cell<u64>,
 }
 
 impl<'a, T> Client<'a, T> {
     pub fn new(db: &'a dyn Db<Transaction = T>, workload: &'a CoreWorkload) -> Self {
-        Self { db, workload }
+        Self::with_session(db, workload.thread_session())
     }
 
-    pub fn read_txn(&self, txn: &mut T) -> Result<()> {
-        let table = self.workload.next_table();
-        let key = self.workload.next_transaction_key();
+    /// A client drawing its operations and keys from `session`.
+    pub fn with_session(db: &'a dyn Db<Transaction = T>, session: WorkloadSession<'a>) -> Self {
+        Self {
+            db,
+            workload: session.workload(),
+            session: RefCell::new(session),
+            verification_errors: Cell::new(0),
+        }
+    }
 
-        let fields = if self.workload.read_all_fields() {
-            None
-        } else {
-            Some(vec![self.workload.next_field_name()])
-        };
+    pub fn next_operation(&self) -> Operation {
+        self.session.borrow_mut().next_operation()
+    }
 
-        self.db.read(txn, &table, &key, fields).map(|_| ())
+    /// Records read so far whose values were not the ones written.
+    pub fn verification_errors(&self) -> u64 {
+        self.verification_errors.get()
     }
 
-    pub fn update_txn(&self, txn: &mut T) -> Result<()> {
-        let table = self.workload.next_table();
-        let key = self.workload.next_transaction_key();
+    fn check_values(&self, key: &str, record: Option<&[(String, String)]>) {
+        if let Some(values) = record {
+            if !self.workload.check_values(key, values) {
+                self.verification_errors
+                    .set(self.verification_errors.get() + 1);
+            }
+        }
+    }
 
-        let values = if self.workload.write_all_fields() {
-            vec![self.workload.build_update()]
-        } else {
-            self.workload.build_values()
+    /// Reads a record, returning it and whether its key was never inserted;
+    /// see `WorkloadSpec::read_miss_proportion`.
+    #[allow(clippy::type_complexity)]
+    pub fn read_txn(&self, txn: &mut T) -> Result<(Option<Vec<(String, String)>>, bool)> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let (key, missing) = match session.next_missing_key() {
+            Some(key) => (key, true),
+            None => (
+                session.next_transaction_key_for(Operation::Read, table),
+                false,
+            ),
         };
 
-        self.db.update(txn, &table, key, values).map(|_| ())
+        let fields = session.next_read_fields();
+
+        let record = self.db.read(txn, table.name(), &key, fields)?;
+        if !missing {
+            self.check_values(&key, record.as_deref());
+        }
+
+        Ok((record, missing))
+    }
+
+    pub fn update_txn(&self, txn: &mut T) -> Result<u64> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let key = session.next_transaction_key_for(Operation::Update, table);
+
+        let values = session.next_update_values(&key);
+        let bytes = payload_bytes(&values);
+
+        self.db.update(txn, table.name(), key, values)?;
+
+        Ok(bytes)
     }
 
-    pub fn insert_txn(&self, txn: &mut T) -> Result<()> {
-        let table = self.workload.next_table();
-        let key = self.workload.next_insert_sequence();
-        let values = self.workload.build_values();
+    /// Inserts the next key of the insert sequence of a table. Returns the
+    /// table and key number, to pass to `CoreWorkload::acknowledge_insert`
+    /// once `txn` has been committed or aborted, and the number of value
+    /// bytes written.
+    pub fn insert_txn(&self, txn: &mut T) -> Result<(&'a Table, u64, u64)> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let key_num = self.workload.next_insert_key_num(table);
+        let key = self.workload.get_key_name(key_num);
+        let mut values = Vec::new();
+        session.build_values_into(&key, &mut values);
+        let bytes = payload_bytes(&values);
 
-        self.db.insert(txn, &table, key, values).map(|_| ())
+        if let Err(err) = self.db.insert(txn, table.name(), key, values) {
+            self.workload.acknowledge_insert(table, key_num);
+            return Err(err);
+        }
+
+        Ok((table, key_num, bytes))
     }
 
-    pub fn scan_txn(&self, txn: &mut T) -> Result<()> {
-        let table = self.workload.next_table();
-        let key = self.workload.next_transaction_key();
-        let length = self.workload.next_scan_length();
+    /// Deletes a key drawn like the keys of the other transactions.
+    pub fn delete_txn(&self, txn: &mut T) -> Result<()> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let key = session.next_delete_key(table);
 
-        let fields = if self.workload.read_all_fields() {
-            None
-        } else {
-            Some(vec![self.workload.next_field_name()])
-        };
+        self.db.delete(txn, table.name(), &key)
+    }
+
+    pub fn scan_txn(&self, txn: &mut T) -> Result<Vec<Vec<(String, String)>>> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let (key, length) = session.next_scan(table);
+
+        let fields = session.next_read_fields();
+
+        let rows = self.db.scan(txn, table.name(), &key, length, fields)?;
+        let corrupted = rows
+            .iter()
+            .filter(|row| !self.workload.check_row(row))
+            .count();
+        self.verification_errors
+            .set(self.verification_errors.get() + corrupted as u64);
 
-        self.db.scan(txn, &table, &key, length, fields).map(|_| ())
+        Ok(rows)
     }
 
-    pub fn rmw_txn(&self, txn: &mut T) -> Result<()> {
-        let table = self.workload.next_table();
-        let key = self.workload.next_transaction_key();
+    /// Reads a record and writes new values to the fields read, in the same
+    /// transaction. Records that are not found are not written. Returns the
+    /// values read and the number of value bytes written.
+    #[allow(clippy::type_complexity)]
+    pub fn rmw_txn(&self, txn: &mut T) -> Result<(Option<Vec<(String, String)>>, u64)> {
+        let mut session = self.session.borrow_mut();
+        let table = session.next_table();
+        let key = session.next_transaction_key_for(Operation::ReadModifyWrite, table);
 
-        let fields = if self.workload.read_all_fields() {
-            None
-        } else {
-            Some(vec![self.workload.next_field_name()])
-        };
+        let fields = session.next_read_fields();
 
-        self.db.read(txn, &table, &key, fields)?;
+        let record = self.db.read(txn, table.name(), &key, fields.clone())?;
+        self.check_values(&key, record.as_deref());
 
-        let values = if self.workload.write_all_fields() {
-            vec![self.workload.build_update()]
-        } else {
-            self.workload.build_values()
-        };
+        if record.is_none() {
+            return Ok((None, 0));
+        }
+
+        let values = session.next_rmw_values(&key, fields.as_deref());
+        let bytes = payload_bytes(&values);
 
-        self.db.update(txn, &table, key, values).map(|_| ())
+        self.db.update(txn, table.name(), key, values)?;
+
+        Ok((record, bytes))
     }
 }
 
-fn load_batch<T>(
-    db: &dyn Db<Transaction = T>,
-    txn: &mut T,
-    batch: &[(String, String, Vec<(String, String)>)],
-) -> Result<usize> {
+pub(crate) type Record<'a> = (&'a str, String, Vec<(String, String)>);
+
+/// Size of the field values of a record, the payload counted as bytes read
+/// or written. Field names are not counted.
+pub(crate) fn payload_bytes(values: &[(String, String)]) -> u64 {
+    values.iter().map(|(_, value)| value.len() as u64).sum()
+}
+
+fn load_batch<T>(db: &dyn Db<Transaction = T>, txn: &mut T, batch: &[Record]) -> Result<usize> {
     let batch_size = batch.len();
 
     for (table, key, values) in batch {
@@ -113,198 +247,2896 @@ fn load_batch<T>(
     Ok(batch_size)
 }
 
+/// Where a load worker takes its key numbers from.
+pub(crate) enum LoadKeys<'a> {
+    /// The next `n` numbers of the workload's shared load sequence.
+    Shared(usize),
+    /// A range of key numbers owned by this worker alone, with the checkpoint
+    /// that its progress is saved to, if any.
+    Partition(Range<usize>, Option<&'a LoadCheckpoint>),
+}
+
+impl LoadKeys<'_> {
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            LoadKeys::Shared(n) => *n,
+            LoadKeys::Partition(key_nums, _) => key_nums.len(),
+        }
+    }
+
+    /// Fills `batch` with the `count` records starting at the `b`-th of
+    /// these keys, generating the values into the buffers already there, and
+    /// returns the number of value bytes.
+    pub(crate) fn next_batch<'w>(
+        &self,
+        session: &mut WorkloadSession<'w>,
+        b: usize,
+        count: usize,
+        batch: &mut Vec<Record<'w>>,
+    ) -> u64 {
+        let workload = session.workload();
+        batch.truncate(count);
+        batch.resize_with(count, Default::default);
+
+        let positions = match self {
+            LoadKeys::Shared(_) => workload.next_load_positions(count),
+            LoadKeys::Partition(key_nums, _) => key_nums.start + b..key_nums.start + b + count,
+        };
+
+        for (pos, (table, key, values)) in positions.zip(batch.iter_mut()) {
+            let (t, key_num) = workload.load_key_num(pos);
+            *table = t.name();
+            *key = workload.get_key_name(key_num);
+            session.build_values_into(key, values);
+        }
+
+        batch
+            .iter()
+            .map(|(_, _, values)| payload_bytes(values))
+            .sum()
+    }
+
+    /// Moves the checkpoint of worker `stream`, if any, past the batch of
+    /// `count` records at `b`, its `batches`-th committed one.
+    pub(crate) fn batch_committed(
+        &self,
+        stream: usize,
+        b: usize,
+        count: usize,
+        batches: usize,
+    ) -> Result<()> {
+        match self {
+            LoadKeys::Partition(key_nums, Some(checkpoint)) => {
+                checkpoint.advance(stream, key_nums.start + b + count, batches)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 fn load_db<T>(
     db: &dyn Db<Transaction = T>,
     workload: &CoreWorkload,
-    num_ops: usize,
+    stream: usize,
+    keys: LoadKeys,
     batch_size: usize,
     pb: &ProgressBar,
-) -> Result<usize> {
+    measurements: &Measurements,
+) -> Result<(usize, u64)> {
     let mut total_count = 0;
+    let mut total_bytes = 0;
+    let mut committed_batches = 0;
+    let num_ops = keys.len();
+
+    // Reused from batch to batch, so that the values of each slot are
+    // generated into the same buffers.
+    let mut batch: Vec<Record> = Vec::with_capacity(batch_size);
+    let mut session = workload.session(stream);
+    let mut progress = BatchedProgress::new(pb);
 
     for b in (0..num_ops).step_by(batch_size) {
         let count = std::cmp::min(batch_size, num_ops - b);
-
-        let batch = (0..count)
-            .map(|_| {
-                (
-                    workload.next_table(),
-                    workload.next_sequence_key(),
-                    workload.build_values(),
-                )
-            })
-            .collect::<Vec<_>>();
+        let bytes = keys.next_batch(&mut session, b, count, &mut batch);
 
         loop {
             let mut txn = db.start_transaction()?;
 
             match load_batch(db, &mut txn, &batch) {
                 Ok(count) => {
-                    total_count += count;
-                    pb.inc(count as u64);
                     db.commit_transaction(txn)?;
+                    total_count += count;
+                    total_bytes += bytes;
+                    progress.add(count as u64);
+                    measurements.add_completed(count as u64);
+
+                    committed_batches += 1;
+                    keys.batch_committed(stream, b, count, committed_batches)?;
                     break;
                 }
                 Err(Error::TransactionAborted) => {
                     db.abort_transaction(txn)?;
                     continue;
                 }
-                err => {
+                Err(err) => {
                     db.abort_transaction(txn)?;
-                    return err;
+                    return Err(err);
                 }
             }
         }
     }
 
-    Ok(total_count)
+    Ok((total_count, total_bytes))
+}
+
+/// An operation of the run phase, with the time it was issued at, which its
+/// latency is measured from, and whether that falls outside the measured
+/// window.
+pub(crate) struct IssuedOp {
+    pub(crate) op: Operation,
+    pub(crate) issued: Instant,
+    pub(crate) ramp: bool,
+}
+
+/// One attempt at an operation: its outcome, the bytes read and written if
+/// it succeeded, and when it started and began to commit.
+pub(crate) struct Attempt {
+    pub(crate) res: Result<(u64, u64)>,
+    pub(crate) started: Instant,
+    pub(crate) committing: Option<Instant>,
+    /// Whether a read was of a key that was never inserted.
+    pub(crate) missed: bool,
+}
+
+/// Statistics and progress of a run phase worker, kept the same way whether
+/// it runs on a thread or as a task.
+pub(crate) struct TxnRecorder<'a, T> {
+    options: &'a RunOptions<T>,
+    measurements: &'a Measurements,
+    raw_latency: Option<RawLatencyBuffer<'a>>,
+    progress: BatchedProgress<'a>,
+    stats: ThreadStats,
+    started: Instant,
+    arrival_gaps: Option<ExponentialGenerator>,
+    next_arrival: Instant,
+}
+
+impl<'a, T> TxnRecorder<'a, T> {
+    pub(crate) fn new(
+        options: &'a RunOptions<T>,
+        pb: &'a ProgressBar,
+        measurements: &'a Measurements,
+    ) -> Self {
+        let started = Instant::now();
+
+        Self {
+            options,
+            measurements,
+            raw_latency: measurements.raw_latency().map(|log| log.buffer()),
+            progress: BatchedProgress::new(pb),
+            stats: ThreadStats::default(),
+            started,
+            arrival_gaps: options
+                .arrival_rate
+                .map(|rate| ExponentialGenerator::new(options.threads as f64 / rate)),
+            next_arrival: started,
+        }
+    }
+
+    /// With an arrival rate, the arrival time of the next operation, which
+    /// the worker waits for before issuing it.
+    pub(crate) fn next_arrival(&mut self, rng: &mut dyn RngCore) -> Option<Instant> {
+        let gap = self.arrival_gaps.as_ref()?.next(rng);
+        self.next_arrival += Duration::from_secs_f64(gap);
+
+        Some(self.next_arrival)
+    }
+
+    /// `op` issued at `issued`, or now.
+    pub(crate) fn issue(&self, op: Operation, issued: Option<Instant>) -> IssuedOp {
+        let issued = issued.unwrap_or_else(Instant::now);
+
+        IssuedOp {
+            op,
+            issued,
+            ramp: self.measurements.in_ramp(issued),
+        }
+    }
+
+    /// Records `attempt` at `op`, finished just now. Returns whether the
+    /// operation is done, or has been aborted and should be tried again, or
+    /// the error that stops the worker.
+    pub(crate) fn record(&mut self, op: &IssuedOp, attempt: Attempt) -> Result<bool> {
+        let IssuedOp { op, issued, ramp } = *op;
+        let Attempt {
+            res,
+            started,
+            committing,
+            missed,
+        } = attempt;
+
+        let finished = Instant::now();
+        let latency = finished - started;
+        if !ramp {
+            self.stats.record_busy(latency);
+
+            let (stats, measurements) = (&mut self.stats, self.measurements);
+            let mut record_stage = |stage, latency| {
+                stats.record_stage(op, stage, latency);
+                measurements.record_stage(op, stage, latency);
+            };
+
+            match (&res, committing) {
+                (Ok(_), Some(committing)) => {
+                    record_stage(Stage::Execute, committing - started);
+                    record_stage(Stage::Commit, finished - committing);
+                }
+                (Err(Error::TransactionAborted), _) if self.options.latency_breakdown => {
+                    record_stage(Stage::Wasted, latency);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(raw_latency) = self.raw_latency.as_mut() {
+            raw_latency.record(op, started, latency, res.is_ok())?;
+        }
+
+        match res {
+            Ok((read, written)) => {
+                let latency = issued.elapsed();
+
+                if ramp {
+                    self.stats.record_ramp_op();
+                } else {
+                    self.stats.record_completed(op, latency);
+                    self.stats.record_bytes(op, read, written);
+                    if missed {
+                        self.stats.record_read_miss(latency);
+                    }
+                }
+                self.progress.inc();
+                self.measurements.record_completed(op, latency);
+                Ok(true)
+            }
+            Err(Error::TransactionAborted) => {
+                self.measurements.record_abort(op);
+                Ok(false)
+            }
+            Err(err) if self.options.error_policy == ErrorPolicy::CountAndContinue => {
+                self.measurements.record_error(op);
+                self.stats.record_error(op, &err);
+                self.progress.inc();
+                Ok(true)
+            }
+            Err(err) => {
+                if let Some(raw_latency) = self.raw_latency.as_mut() {
+                    raw_latency.flush()?;
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Called once the worker has run all its operations.
+    pub(crate) fn finish(mut self, verification_errors: u64) -> Result<ThreadStats> {
+        if let Some(raw_latency) = self.raw_latency.as_mut() {
+            raw_latency.flush()?;
+        }
+
+        self.measurements.worker_finished();
+        self.stats.record_verification_errors(verification_errors);
+        self.stats.set_elapsed(
+            self.measurements
+                .measured_time(self.started, Instant::now()),
+        );
+
+        Ok(self.stats)
+    }
 }
 
 fn bench_txn<T>(
     db: &dyn Db<Transaction = T>,
     workload: &CoreWorkload,
+    stream: usize,
     num_ops: usize,
+    options: &RunOptions<T>,
     pb: &ProgressBar,
-) -> Result<usize> {
-    let client = Client::new(db, workload);
-    let mut total_count = 0;
+    measurements: &Measurements,
+) -> Result<ThreadStats> {
+    let client = Client::with_session(db, workload.session(stream));
+    let mut recorder = TxnRecorder::new(options, pb, measurements);
 
     for _ in 0..num_ops {
-        let op = workload.next_operation();
+        let op = client.next_operation();
+        let arrival = recorder.next_arrival(client.session.borrow_mut().rng());
+        if let Some(arrival) = arrival {
+            let now = Instant::now();
+            if arrival > now {
+                thread::sleep(arrival - now);
+            }
+        }
+        let op = recorder.issue(op, arrival);
 
         loop {
-            let mut txn = db.start_transaction()?;
+            let started = Instant::now();
+            let mut committing = None;
+            let mut inserted = None;
+            let mut missed = false;
+
+            let res = db.start_transaction().and_then(|mut txn| {
+                // Bytes read and written.
+                let res = match op.op {
+                    Operation::Read => client.read_txn(&mut txn).map(|(record, missing)| {
+                        missed = missing;
+                        (record.as_deref().map_or(0, payload_bytes), 0)
+                    }),
+                    Operation::Update => client.update_txn(&mut txn).map(|bytes| (0, bytes)),
+                    Operation::Insert => {
+                        client.insert_txn(&mut txn).map(|(table, key_num, bytes)| {
+                            inserted = Some((table, key_num));
+                            (0, bytes)
+                        })
+                    }
+                    Operation::Scan => client
+                        .scan_txn(&mut txn)
+                        .map(|rows| (rows.iter().map(|row| payload_bytes(row)).sum(), 0)),
+                    Operation::Delete => client.delete_txn(&mut txn).map(|_| (0, 0)),
+                    Operation::ReadModifyWrite => client
+                        .rmw_txn(&mut txn)
+                        .map(|(record, bytes)| (record.as_deref().map_or(0, payload_bytes), bytes)),
+                };
+
+                if options.latency_breakdown {
+                    committing = Some(Instant::now());
+                }
+
+                match res {
+                    Ok(bytes) => db.commit_transaction(txn).map(|_| bytes),
+                    Err(err) => {
+                        db.abort_transaction(txn)?;
+                        Err(err)
+                    }
+                }
+            });
+
+            // Only now is a committed insert visible to the other workers. A
+            // failed one is released as well so that later inserts become
+            // visible.
+            if let Some((table, key_num)) = inserted {
+                workload.acknowledge_insert(table, key_num);
+            }
 
-            let res = match op {
-                Operation::Read => client.read_txn(&mut txn),
-                Operation::Update => client.update_txn(&mut txn),
-                Operation::Insert => client.insert_txn(&mut txn),
-                Operation::Scan => client.scan_txn(&mut txn),
-                Operation::ReadModifyWrite => client.rmw_txn(&mut txn),
+            let attempt = Attempt {
+                res,
+                started,
+                committing,
+                missed,
             };
+            if recorder.record(&op, attempt)? {
+                break;
+            }
+        }
+
+        if let Some(think_time) = client.session.borrow_mut().next_think_time() {
+            thread::sleep(think_time);
+        }
+    }
+
+    recorder.finish(client.verification_errors())
+}
+
+/// Counts a record read back by verification in `report`, given the sorted
+/// field names it should have.
+pub(crate) fn check_record(
+    workload: &CoreWorkload,
+    key: &str,
+    record: Option<Vec<(String, String)>>,
+    expected: &[String],
+    report: &mut VerificationReport,
+) {
+    report.checked += 1;
+
+    match record {
+        Some(fields) if !fields.is_empty() => {
+            let mut names = fields
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            names.sort();
+
+            if names != expected || !workload.check_values(key, &fields) {
+                report.corrupted += 1;
+            }
+        }
+        _ => report.missing += 1,
+    }
+}
+
+fn verify_keys<T>(
+    db: &dyn Db<Transaction = T>,
+    workload: &CoreWorkload,
+    key_nums: Range<usize>,
+    pb: &ProgressBar,
+) -> Result<VerificationReport> {
+    let mut expected = workload.field_names();
+    expected.sort();
+
+    let mut report = VerificationReport::default();
+    let mut progress = BatchedProgress::new(pb);
+
+    for i in key_nums {
+        let (table, key_num) = workload.load_key_num(i);
+
+        if workload.is_deleted(table, key_num) {
+            progress.inc();
+            continue;
+        }
+
+        let key = workload.get_key_name(key_num);
 
-            match res {
-                Ok(_) => {
-                    total_count += 1;
-                    pb.inc(1);
+        let record = loop {
+            let mut txn = db.start_transaction()?;
+
+            match db.read(&mut txn, table.name(), &key, None) {
+                Ok(record) => {
                     db.commit_transaction(txn)?;
-                    break;
+                    break record;
                 }
                 Err(Error::TransactionAborted) => {
                     db.abort_transaction(txn)?;
                     continue;
                 }
-                err => {
+                Err(err) => {
                     db.abort_transaction(txn)?;
-                    return err.map(|_| 0);
+                    return Err(err);
                 }
             }
-        }
+        };
+
+        check_record(workload, &key, record, &expected, &mut report);
+        progress.inc();
     }
 
-    Ok(total_count)
+    Ok(report)
 }
 
-pub fn run_ycsb<P: AsRef<Path>, T: 'static>(
-    db: Arc<dyn Db<Transaction = T>>,
-    workload_path: P,
-    nr_threads: usize,
-) -> Result<()> {
-    let mut file = File::open(workload_path)?;
-    let mut json_data = String::new();
-    file.read_to_string(&mut json_data)?;
-
-    let workload_spec =
-        serde_json::from_str::<WorkloadSpec>(&json_data).map_err(|_| Error::UnknownSpecFormat)?;
-    let record_count = workload_spec.get_record_count();
-    let op_count = workload_spec.get_operation_count();
-    let workload = Arc::new(CoreWorkload::new(workload_spec)?);
-
-    let sty = ProgressStyle::default_bar()
-        .template("[{elapsed_precise}] {bar:60.cyan/blue} {pos:>7}/{len:7} {per_sec}")
-        .progress_chars("##-");
-
-    {
-        let pb = Arc::new(ProgressBar::new(
-            (record_count / nr_threads * nr_threads) as u64,
-        ));
-        pb.set_style(sty.clone());
-        pb.set_draw_delta(record_count as u64 / 1000);
+/// Splits `range` into `parts` contiguous ranges whose lengths differ by at
+/// most one and returns the `i`-th of them.
+fn partition(range: Range<usize>, parts: usize, i: usize) -> Range<usize> {
+    let len = range.end - range.start;
+    let (chunk, rem) = (len / parts, len % parts);
+    let start = range.start + i * chunk + std::cmp::min(i, rem);
+    let end = start + chunk + if i < rem { 1 } else { 0 };
+
+    start..end
+}
+
+/// Pins the calling thread to `cpu`. Without the `core_affinity` feature, or
+/// on platforms where affinity cannot be set, this silently does nothing.
+#[cfg(feature = "core_affinity")]
+fn pin_current_thread(cpu: usize) {
+    core_affinity::set_for_current(core_affinity::CoreId { id: cpu });
+}
+
+#[cfg(not(feature = "core_affinity"))]
+fn pin_current_thread(_cpu: usize) {}
 
-        let mut threads = Vec::new();
+fn spawn_cpu(cpu_set: &[usize], thread_id: usize) -> Option<usize> {
+    if cpu_set.is_empty() {
+        None
+    } else {
+        Some(cpu_set[thread_id % cpu_set.len()])
+    }
+}
+
+/// Runs `worker` on `options.threads` scoped threads and collects what they
+/// return.
+fn run_workers<T, R, F>(options: &RunOptions<T>, worker: F) -> Result<Vec<R>>
+where
+    R: Send,
+    F: Fn(usize) -> Result<R> + Sync,
+{
+    thread::scope(|s| {
+        let threads = (0..options.threads)
+            .map(|i| {
+                let worker = &worker;
+                let cpu = spawn_cpu(&options.cpu_set, i);
+
+                s.spawn(move || {
+                    if let Some(cpu) = cpu {
+                        pin_current_thread(cpu);
+                    }
+
+                    worker(i)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    })
+}
+
+/// Progress bar style used for phases without a style set in `RunOptions`.
+/// The prefix is the phase name and the message the current throughput and
+/// p99 latency.
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{prefix:>6} [{elapsed_precise}] {bar:60.cyan/blue} {pos:>7}/{len:7} {msg}")
+        .progress_chars("##-")
+}
+
+fn phase_progress_bar(len: usize, phase: &str, style: Option<&ProgressStyle>) -> ProgressBar {
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(style.cloned().unwrap_or_else(progress_style));
+    pb.set_draw_delta(len as u64 / 1000);
+    pb.set_prefix(phase);
+
+    pb
+}
+
+/// Operations a worker completes before it advances the shared progress bar.
+const PROGRESS_BATCH: u64 = 1024;
+
+/// Longest a worker holds back completed operations from the progress bar,
+/// so that the bar keeps moving when operations are slow.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
-        for _ in 0..nr_threads {
-            let db = db.clone();
-            let workload = workload.clone();
-            let pb = pb.clone();
+/// Advances a progress bar in batches of `PROGRESS_BATCH`, or every
+/// `PROGRESS_INTERVAL` if that comes first, so workers do not contend on the
+/// bar for every operation. The remainder is added on drop.
+pub(crate) struct BatchedProgress<'a> {
+    pb: &'a ProgressBar,
+    pending: u64,
+    flushed: Instant,
+}
 
-            threads.push(thread::spawn(move || {
-                load_db(&*db, &workload, record_count / nr_threads, 32, &*pb)
-            }));
+impl<'a> BatchedProgress<'a> {
+    pub(crate) fn new(pb: &'a ProgressBar) -> Self {
+        Self {
+            pb,
+            pending: 0,
+            flushed: Instant::now(),
         }
+    }
 
-        let loaded: usize = threads
-            .into_iter()
-            .map(|t| t.join().unwrap())
-            .collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .sum();
+    pub(crate) fn inc(&mut self) {
+        self.add(1);
+    }
 
-        pb.finish();
+    pub(crate) fn add(&mut self, count: u64) {
+        self.pending += count;
 
-        eprintln!("{} records loaded", loaded);
+        // A clock read is far cheaper than advancing the bar, which takes a
+        // lock.
+        if self.pending >= PROGRESS_BATCH || self.flushed.elapsed() >= PROGRESS_INTERVAL {
+            self.pb.inc(self.pending);
+            self.pending = 0;
+            self.flushed = Instant::now();
+        }
     }
+}
 
-    {
-        let pb = Arc::new(ProgressBar::new(
-            (op_count / nr_threads * nr_threads) as u64,
-        ));
-        pb.set_style(sty);
-        pb.set_draw_delta(op_count as u64 / 1000);
+impl<'a> Drop for BatchedProgress<'a> {
+    fn drop(&mut self) {
+        self.pb.inc(self.pending);
+    }
+}
+
+/// Records the phase's throughput once per second until `stop` is signalled
+/// or disconnected, and shows it as the message of `pb`.
+pub(crate) fn sample_progress(
+    measurements: &Measurements,
+    pb: &ProgressBar,
+    stop: mpsc::Receiver<()>,
+) -> ThroughputSeries {
+    measurements.sample_throughput(Duration::from_secs(1), stop, |interval, ops, p99| {
+        let ktps = ops as f64 / interval.as_secs_f64() / 1000.0;
+        let now = Instant::now();
+        let ramp = if measurements.overlaps_ramp(now - interval, now) {
+            "  (ramp)"
+        } else {
+            ""
+        };
+
+        match p99 {
+            Some(p99) => pb.set_message(&format!("{:.2} KTPS  p99={:.1?}{}", ktps, p99, ramp)),
+            None => pb.set_message(&format!("{:.2} KTPS{}", ktps, ramp)),
+        }
+    })
+}
+
+/// Runs `worker` on all threads while a sampler thread records the phase's
+/// throughput once per second and shows it as the message of `pb`.
+fn run_measured<T, R, F>(
+    options: &RunOptions<T>,
+    measurements: &Measurements,
+    pb: &ProgressBar,
+    worker: F,
+) -> Result<(Vec<R>, ThroughputSeries)>
+where
+    R: Send,
+    F: Fn(usize) -> Result<R> + Sync,
+{
+    let (stop, stopped) = mpsc::channel();
+
+    thread::scope(|s| {
+        let sampler = s.spawn(move || sample_progress(measurements, pb, stopped));
+
+        let results = run_workers(options, worker);
+        drop(stop);
+        let series = sampler.join().unwrap();
+
+        Ok((results?, series))
+    })
+}
+
+/// How the load phase's key numbers are split over the workers, and where
+/// each of them starts.
+pub(crate) struct LoadPlan {
+    record_count: usize,
+    threads: usize,
+    partitioned: bool,
+    partitions: Vec<Range<usize>>,
+    checkpoint: Option<LoadCheckpoint>,
+}
 
-        let start = Instant::now();
+impl LoadPlan {
+    fn new<T>(
+        workload: &CoreWorkload,
+        record_count: usize,
+        options: &RunOptions<T>,
+    ) -> Result<Self> {
+        let threads = options.threads;
+        let partitions = (0..threads)
+            .map(|i| partition(workload.load_key_range(record_count), threads, i))
+            .collect::<Vec<_>>();
 
-        let mut threads = Vec::new();
+        let checkpoint = match &options.load_checkpoint {
+            Some(path) => Some(LoadCheckpoint::open(
+                path,
+                options.checkpoint_interval,
+                workload.load_spec_hash(),
+                &partitions,
+            )?),
+            None => None,
+        };
 
-        for _ in 0..nr_threads {
-            let db = db.clone();
-            let workload = workload.clone();
-            let pb = pb.clone();
+        Ok(Self {
+            record_count,
+            threads,
+            partitioned: options.partitioned_load || checkpoint.is_some(),
+            partitions,
+            checkpoint,
+        })
+    }
 
-            threads.push(thread::spawn(move || {
-                bench_txn(&*db, &workload, op_count / nr_threads, &*pb)
-            }));
+    fn resume_from(&self, i: usize) -> usize {
+        match &self.checkpoint {
+            Some(checkpoint) => checkpoint.position(i),
+            None => self.partitions[i].start,
         }
+    }
 
-        let nr_txns: usize = threads
-            .into_iter()
-            .map(|t| t.join().unwrap())
-            .collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .sum();
+    /// Records the workers are going to load.
+    fn total(&self) -> usize {
+        if self.partitioned {
+            (0..self.threads)
+                .map(|i| self.partitions[i].end - self.resume_from(i))
+                .sum()
+        } else {
+            self.record_count / self.threads * self.threads
+        }
+    }
 
-        let elapsed = start.elapsed();
+    /// Key numbers of load worker `i`.
+    pub(crate) fn keys(&self, i: usize) -> LoadKeys<'_> {
+        if self.partitioned {
+            LoadKeys::Partition(
+                self.resume_from(i)..self.partitions[i].end,
+                self.checkpoint.as_ref(),
+            )
+        } else {
+            LoadKeys::Shared(self.record_count / self.threads)
+        }
+    }
+}
 
-        pb.finish();
+/// Sets up the load phase of `record_count` records: how they are split
+/// over the workers, the progress bar and the measurements.
+pub(crate) fn start_load_phase<T>(
+    workload: &CoreWorkload,
+    record_count: usize,
+    options: &RunOptions<T>,
+) -> Result<(LoadPlan, ProgressBar, Measurements)> {
+    let plan = LoadPlan::new(workload, record_count, options)?;
+    let total = plan.total();
 
-        eprintln!("{} transactions in {:?}", nr_txns, elapsed);
+    if plan.partitioned && total < record_count {
         eprintln!(
-            "Throughput: {:.2} KTPS",
-            nr_txns as f64 / elapsed.as_secs_f64() / 1000.0
+            "Resuming load: {} of {} records already loaded",
+            record_count - total,
+            record_count
+        );
+    }
+
+    let pb = phase_progress_bar(total, "load", options.load_progress_style.as_ref());
+
+    let measurements = Measurements::new(None)?;
+    #[cfg(feature = "prometheus")]
+    let measurements = measurements.with_metrics(options.metrics.clone());
+
+    Ok((plan, pb, measurements))
+}
+
+/// Sums up what the load workers returned, the records and value bytes each
+/// of them wrote.
+pub(crate) fn finish_load_phase(
+    plan: &LoadPlan,
+    res: Result<(Vec<(usize, u64)>, ThroughputSeries)>,
+    measurements: Measurements,
+    pb: &ProgressBar,
+) -> Result<PhaseResult> {
+    // Whatever committed before a failure counts as loaded as well.
+    if let Some(checkpoint) = &plan.checkpoint {
+        checkpoint.save()?;
+    }
+
+    let (loaded, series) = res?;
+    let (loaded, bytes_written) = loaded
+        .into_iter()
+        .fold((0, 0), |(count, bytes), (c, b)| (count + c, bytes + b));
+
+    let elapsed = measurements.elapsed();
+    measurements.finish()?;
+
+    pb.finish();
+
+    let mut result = PhaseResult::new(loaded, elapsed);
+    result.bytes_written = bytes_written;
+    result.throughput_series = series;
+
+    eprintln!(
+        "{} records loaded, {:.2} MB written ({:.2} MB/s)",
+        loaded,
+        bytes_written as f64 / 1e6,
+        result.mb_per_sec(bytes_written)
+    );
+
+    Ok(result)
+}
+
+fn load_phase<T>(
+    db: &dyn Db<Transaction = T>,
+    workload: &CoreWorkload,
+    record_count: usize,
+    options: &RunOptions<T>,
+) -> Result<PhaseResult> {
+    let (plan, pb, measurements) = start_load_phase(workload, record_count, options)?;
+
+    let res = run_measured(options, &measurements, &pb, |i| {
+        load_db(
+            db,
+            workload,
+            i,
+            plan.keys(i),
+            options.load_batch_size,
+            &pb,
+            &measurements,
         )
+    });
+
+    finish_load_phase(&plan, res, measurements, &pb)
+}
+
+/// Keys counted per hot key reported, so that the reported keys and their
+/// counts are accurate unless the hottest keys are barely hotter than the
+/// rest.
+const KEY_FREQUENCY_SLACK: usize = 10;
+
+/// Worker threads of the run phase following one workload; see
+/// `RunOptions::thread_groups`.
+pub(crate) struct ThreadGroup {
+    workload: Arc<CoreWorkload>,
+    threads: usize,
+    op_count: usize,
+}
+
+impl ThreadGroup {
+    /// The group of worker `i`, with `i` made relative to the group.
+    fn of_worker(groups: &[ThreadGroup], mut i: usize) -> (usize, &Self, usize) {
+        for (index, group) in groups.iter().enumerate() {
+            if i < group.threads {
+                return (index, group, i);
+            }
+            i -= group.threads;
+        }
+
+        unreachable!("worker outside of every thread group")
     }
 
-    Ok(())
+    /// Workload and operations of worker `i` in the warmup and in the
+    /// measured run, as split by `group_ops`.
+    pub(crate) fn worker_ops<'g>(
+        groups: &'g [ThreadGroup],
+        ops: &[(usize, usize)],
+        i: usize,
+    ) -> (&'g Arc<CoreWorkload>, (usize, usize)) {
+        let (group, ThreadGroup { workload, .. }, _) = Self::of_worker(groups, i);
+
+        (workload, ops[group])
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Operations per thread of each group in the warmup and in the measured
+/// run. Every thread runs the same warmup, taken out of its own group's
+/// `op_count`.
+pub(crate) fn group_ops<T>(
+    groups: &[ThreadGroup],
+    options: &RunOptions<T>,
+) -> Result<Vec<(usize, usize)>> {
+    let warmup = options.warmup_ops / options.threads;
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, g)| {
+            if warmup * g.threads > g.op_count {
+                return Err(Error::InvalidArgument(format!(
+                    "thread group {} has {} operations, fewer than its {} warmup operations",
+                    i,
+                    g.op_count,
+                    warmup * g.threads
+                )));
+            }
 
-    #[test]
-    fn test_run() {
-        let db = Arc::new(MockDb::new(true));
+            Ok((warmup, (g.op_count - warmup * g.threads) / g.threads))
+        })
+        .collect()
+}
 
-        run_ycsb(db, "workloads/workload_a.json", 8).unwrap();
+/// Sets up the measured part of the run phase: the progress bar for the
+/// operations in `ops` and the measurements.
+pub(crate) fn start_run_phase<T>(
+    groups: &[ThreadGroup],
+    ops: &[(usize, usize)],
+    options: &RunOptions<T>,
+) -> Result<(ProgressBar, Measurements)> {
+    if let Some(top_n) = options.key_frequency {
+        groups[0]
+            .workload
+            .track_key_frequency(top_n * KEY_FREQUENCY_SLACK);
+    }
+
+    let pb = phase_progress_bar(
+        groups
+            .iter()
+            .zip(ops)
+            .map(|(g, (_, ops))| ops * g.threads)
+            .sum(),
+        "run",
+        options.run_progress_style.as_ref(),
+    );
+
+    let mut measurements = Measurements::new(options.raw_latency_output.as_deref())?;
+    if let Some(ramp_up) = options.ramp_up {
+        measurements = measurements.with_ramp_up(ramp_up);
+    }
+    #[cfg(feature = "prometheus")]
+    let measurements = measurements.with_metrics(options.metrics.clone());
+
+    Ok((pb, measurements))
+}
+
+/// How long worker `i` waits before its first operation, to spread the
+/// workers' starts over the ramp-up.
+pub(crate) fn ramp_up_delay<T>(options: &RunOptions<T>, i: usize) -> Option<Duration> {
+    options
+        .ramp_up
+        .map(|ramp_up| ramp_up.mul_f64(i as f64 / options.threads as f64))
+}
+
+fn run_phase<T>(
+    db: &dyn Db<Transaction = T>,
+    groups: &[ThreadGroup],
+    options: &RunOptions<T>,
+) -> Result<PhaseResult> {
+    let nr_threads = options.threads;
+    let ops = group_ops(groups, options)?;
+
+    if options.warmup_ops > 0 {
+        let pb = ProgressBar::hidden();
+        let measurements = Measurements::new(None)?;
+
+        run_workers(options, |i| {
+            let (workload, (warmup_ops, _)) = ThreadGroup::worker_ops(groups, &ops, i);
+
+            // Apart from the run phase's streams, so the warmup doesn't touch
+            // exactly the keys the run is about to.
+            bench_txn(
+                db,
+                workload,
+                nr_threads + i,
+                warmup_ops,
+                options,
+                &pb,
+                &measurements,
+            )
+        })?;
+    }
+
+    let (pb, measurements) = start_run_phase(groups, &ops, options)?;
+
+    let (thread_stats, series) = run_measured(options, &measurements, &pb, |i| {
+        if let Some(delay) = ramp_up_delay(options, i) {
+            thread::sleep(delay);
+        }
+
+        let (workload, (_, run_ops)) = ThreadGroup::worker_ops(groups, &ops, i);
+
+        bench_txn(db, workload, i, run_ops, options, &pb, &measurements)
+    })?;
+
+    finish_run_phase(groups, thread_stats, series, measurements, &pb, options)
+}
+
+/// Sums up and reports the statistics the run phase's workers returned.
+pub(crate) fn finish_run_phase<T>(
+    groups: &[ThreadGroup],
+    thread_stats: Vec<ThreadStats>,
+    series: ThroughputSeries,
+    measurements: Measurements,
+    pb: &ProgressBar,
+    options: &RunOptions<T>,
+) -> Result<PhaseResult> {
+    let nr_threads = options.threads;
+    let workload = &groups[0].workload;
+
+    let elapsed = measurements.elapsed();
+    let ramp_intervals = measurements.ramp_intervals(&series);
+    measurements.finish()?;
+
+    pb.finish();
+    let threads = thread_stats
+        .iter()
+        .map(ThreadStats::thread_result)
+        .collect::<Vec<_>>();
+
+    let mut group_stats = vec![ThreadStats::default(); groups.len()];
+    for (i, s) in thread_stats.into_iter().enumerate() {
+        group_stats[ThreadGroup::of_worker(groups, i).0].merge(s);
+    }
+
+    let mut stats = ThreadStats::default();
+    for s in &group_stats {
+        stats.merge(s.clone());
+    }
+
+    let mut result = stats.into_result(elapsed);
+    result.threads = threads;
+    if groups.len() > 1 {
+        let mut first_thread = 0;
+
+        for (group, s) in groups.iter().zip(group_stats) {
+            let mut group_result = s.into_result(elapsed);
+            group_result.threads =
+                result.threads[first_thread..first_thread + group.threads].to_vec();
+            first_thread += group.threads;
+            result.groups.push(group_result);
+        }
+    }
+    result.ramp_intervals = ramp_intervals;
+    result.throughput_series = series;
+
+    if let Some(top_n) = options.key_frequency {
+        result.hot_keys = workload.key_frequency_report(top_n);
+        workload.track_key_frequency(0);
+    }
+
+    eprintln!("{} transactions in {:?}", result.operations, elapsed);
+    if result.ramp_ops > 0 {
+        eprintln!(
+            "{} transactions during ramp-up and ramp-down",
+            result.ramp_ops
+        );
+    }
+    eprintln!(
+        "Throughput: {:.2} KTPS",
+        result.operations as f64 / elapsed.as_secs_f64() / 1000.0
+    );
+
+    eprintln!(
+        "Data: {:.2} MB read ({:.2} MB/s), {:.2} MB written ({:.2} MB/s)",
+        result.bytes_read as f64 / 1e6,
+        result.mb_per_sec(result.bytes_read),
+        result.bytes_written as f64 / 1e6,
+        result.mb_per_sec(result.bytes_written)
+    );
+
+    for (i, group) in result.groups.iter().enumerate() {
+        eprintln!(
+            "Group {}: {} threads, {} transactions ({:.2} KTPS)",
+            i,
+            group.threads.len(),
+            group.operations,
+            group.throughput() / 1000.0
+        );
+    }
+
+    if groups.iter().any(|g| g.workload.data_integrity()) {
+        eprintln!(
+            "Data integrity: {} records read back with wrong values",
+            result.verification_errors
+        );
+    }
+
+    if let Some(latency) = &result.latency {
+        eprintln!(
+            "Latency (us): mean {:.1}, min {:.1}, max {:.1}, stddev {:.1}",
+            latency.mean_us, latency.min_us, latency.max_us, latency.stddev_us
+        );
+    }
+
+    for r in &result.per_op {
+        eprintln!(
+            "{}: {} ops ({:.2} KTPS), latency (us): mean {:.1}, min {:.1}, max {:.1}, stddev {:.1}, {:.2} MB/s read, {:.2} MB/s written",
+            r.op,
+            r.operations,
+            result.op_throughput(r.op) / 1000.0,
+            r.latency.mean_us,
+            r.latency.min_us,
+            r.latency.max_us,
+            r.latency.stddev_us,
+            result.mb_per_sec(r.bytes_read),
+            result.mb_per_sec(r.bytes_written)
+        );
+
+        if let (Some(execute), Some(commit)) = (&r.execute, &r.commit) {
+            eprintln!(
+                "  execute mean {:.1} us, commit mean {:.1} us",
+                execute.latency.mean_us, commit.latency.mean_us
+            );
+        }
+
+        if let Some(wasted) = &r.wasted {
+            eprintln!(
+                "  {} aborted attempts, mean {:.1} us",
+                wasted.latency.count, wasted.latency.mean_us
+            );
+        }
+    }
+
+    if let Some(r) = &result.read_misses {
+        eprintln!(
+            "{} misses: {} ops, latency (us): mean {:.1}, min {:.1}, max {:.1}, stddev {:.1}",
+            r.op,
+            r.operations,
+            r.latency.mean_us,
+            r.latency.min_us,
+            r.latency.max_us,
+            r.latency.stddev_us
+        );
+    }
+
+    if let (Some(fairness), true) = (result.fairness(), nr_threads > 1) {
+        eprintln!(
+            "Thread fairness: max/min throughput {:.2}, CV {:.3}",
+            fairness.max_min_ratio, fairness.cv
+        );
+
+        if fairness.max_min_ratio > options.fairness_threshold {
+            eprintln!(
+                "Warning: thread throughput differs by more than {:.2}x, some threads may be starved",
+                options.fairness_threshold
+            );
+        }
+
+        for (i, t) in result.threads.iter().enumerate() {
+            eprintln!(
+                "  thread {}: {} ops ({:.2} KTPS), busy {:?} of {:?} ({:.1}%)",
+                i,
+                t.operations,
+                t.throughput() / 1000.0,
+                t.busy,
+                t.elapsed,
+                t.busy.as_secs_f64() / t.elapsed.as_secs_f64() * 100.0
+            );
+        }
+    }
+
+    if !result.hot_keys.is_empty() {
+        eprintln!("Hottest keys:");
+
+        for k in result.hot_keys.iter().take(10) {
+            eprintln!(
+                "  {} {}: {} draws ({:.2}%)",
+                k.table,
+                k.key,
+                k.count,
+                k.count as f64 / result.operations as f64 * 100.0
+            );
+        }
+    }
+
+    if result.failed > 0 {
+        eprintln!(
+            "{} operations failed ({:.2}% failure rate)",
+            result.failed,
+            result.failure_rate() * 100.0
+        );
+
+        for e in &result.errors {
+            eprintln!("  {} {}: {}", e.op, e.kind, e.count);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Merges and reports what the verification workers found.
+pub(crate) fn finish_verify_phase(
+    reports: Vec<VerificationReport>,
+    pb: &ProgressBar,
+) -> VerificationReport {
+    pb.finish();
+
+    let mut report = VerificationReport::default();
+    for r in reports {
+        report.merge(r);
+    }
+
+    eprintln!(
+        "{} records verified: {} missing, {} corrupted",
+        report.checked, report.missing, report.corrupted
+    );
+
+    report
+}
+
+fn verify_phase<T>(
+    db: &dyn Db<Transaction = T>,
+    workload: &CoreWorkload,
+    loaded: usize,
+    options: &RunOptions<T>,
+) -> Result<VerificationReport> {
+    let key_nums = workload.load_key_range(loaded);
+
+    let pb = phase_progress_bar(key_nums.len(), "verify", None);
+
+    let reports = run_workers(options, |i| {
+        verify_keys(
+            db,
+            workload,
+            partition(key_nums.clone(), options.threads, i),
+            &pb,
+        )
+    })?;
+
+    Ok(finish_verify_phase(reports, &pb))
+}
+
+/// Checks `options` against every phase and takes out its thread groups,
+/// setting the thread count to theirs.
+pub(crate) fn prepare_run<T>(
+    phases: &mut [WorkloadSource],
+    options: &mut RunOptions<T>,
+) -> Result<Vec<(WorkloadSpec, usize)>> {
+    if phases.is_empty() {
+        return Err(Error::InvalidArgument("no workload phases".to_owned()));
+    }
+
+    if let Some(overrides) = &options.overrides {
+        for source in phases.iter_mut() {
+            match source {
+                WorkloadSource::Spec(spec) => spec.apply_overrides(overrides)?,
+                WorkloadSource::Workload { .. } => {
+                    return Err(Error::InvalidArgument(
+                        "overrides cannot be applied to a pre-built workload".to_owned(),
+                    ))
+                }
+            }
+        }
+    }
+
+    let thread_groups = std::mem::take(&mut options.thread_groups);
+
+    for source in phases.iter() {
+        let (op_count, think_time) = match thread_groups.is_empty() {
+            true => (source.operation_count(), source.has_think_time()),
+            false => (
+                thread_groups
+                    .iter()
+                    .map(|(spec, _)| spec.get_operation_count())
+                    .sum(),
+                thread_groups
+                    .iter()
+                    .any(|(spec, _)| spec.get_think_time_dist().is_some()),
+            ),
+        };
+        options.validate(op_count)?;
+
+        if options.arrival_rate.is_some() && think_time {
+            return Err(Error::InvalidArgument(
+                "think time cannot be combined with an arrival rate".to_owned(),
+            ));
+        }
+    }
+
+    if !thread_groups.is_empty() {
+        if phases.len() > 1 {
+            return Err(Error::InvalidArgument(
+                "thread groups need a single workload phase".to_owned(),
+            ));
+        }
+
+        if thread_groups.iter().any(|&(_, threads)| threads == 0) {
+            return Err(Error::InvalidArgument(
+                "thread group must have at least 1 thread".to_owned(),
+            ));
+        }
+
+        if options.key_frequency.is_some() {
+            return Err(Error::InvalidArgument(
+                "key frequency cannot be counted across thread groups".to_owned(),
+            ));
+        }
+
+        options.threads = thread_groups.iter().map(|&(_, threads)| threads).sum();
+    }
+
+    Ok(thread_groups)
+}
+
+/// Starts the Prometheus exporter if `RunOptions::metrics_addr` is set. The
+/// exporter stops when the returned server is dropped.
+#[cfg(feature = "prometheus")]
+pub(crate) fn serve_metrics<T>(
+    options: &mut RunOptions<T>,
+) -> Result<Option<metrics::MetricsServer>> {
+    match options.metrics_addr {
+        Some(addr) => {
+            let server = metrics::MetricsServer::start(addr)?;
+            eprintln!("Serving metrics on http://{}/metrics", server.local_addr());
+
+            options.metrics = Some(server.metrics().clone());
+            Ok(Some(server))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Builds the workload of a phase, with the key counts the phases before it
+/// left behind, and its thread groups. Returns the groups, the records to
+/// load and the workload.
+pub(crate) fn phase_workload<T>(
+    source: WorkloadSource,
+    key_counts: Option<&Vec<usize>>,
+    thread_groups: &[(WorkloadSpec, usize)],
+    options: &RunOptions<T>,
+) -> Result<(Vec<ThreadGroup>, usize, Arc<CoreWorkload>)> {
+    let (workload, record_count, op_count) = match source {
+        WorkloadSource::Spec(spec) => {
+            let spec = match key_counts {
+                Some(counts) => spec.table_record_counts(counts.clone()),
+                None => *spec,
+            };
+
+            let record_count = spec.load_count();
+            let op_count = spec.get_operation_count();
+
+            let workload = match options.phase {
+                Phase::LoadOnly => CoreWorkload::for_load(spec)?,
+                _ => CoreWorkload::new(spec)?,
+            };
+
+            (Arc::new(workload), record_count, op_count)
+        }
+        WorkloadSource::Workload {
+            workload,
+            record_count,
+            operation_count,
+        } => (workload, record_count, operation_count),
+    };
+
+    let groups = match thread_groups.is_empty() {
+        true => vec![ThreadGroup {
+            workload: workload.clone(),
+            threads: options.threads,
+            op_count,
+        }],
+        false => thread_groups
+            .iter()
+            .map(|(spec, threads)| {
+                Ok(ThreadGroup {
+                    workload: Arc::new(CoreWorkload::sharing_key_space(spec.clone(), &workload)?),
+                    threads: *threads,
+                    op_count: spec.get_operation_count(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    if options.verbose {
+        eprint!("{}", workload.describe());
+
+        if !thread_groups.is_empty() {
+            for (i, group) in groups.iter().enumerate() {
+                eprint!(
+                    "Group {} ({} threads):\n{}",
+                    i,
+                    group.threads,
+                    group.workload.describe()
+                );
+            }
+        }
+    }
+
+    Ok((groups, record_count, workload))
+}
+
+/// Fails the run with `Error::SlaViolated` if the run of any phase violates
+/// `RunOptions::sla`.
+pub(crate) fn check_sla<T>(
+    results: Vec<RunResult>,
+    options: &RunOptions<T>,
+) -> Result<Vec<RunResult>> {
+    if let Some(sla) = &options.sla {
+        let multi_phase = results.len() > 1;
+        let violations = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, result)| result.run.as_ref().map(|run| (i, sla.check(run))))
+            .flat_map(|(i, violations)| {
+                violations.into_iter().map(move |v| match multi_phase {
+                    true => format!("phase {}: {}", i + 1, v),
+                    false => v,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !violations.is_empty() {
+            return Err(Error::SlaViolated {
+                violations,
+                results,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// Loads the database once with the first phase's `record_count`, or its
+/// `insert_count` when set, then runs the transaction mix of every phase in
+/// order.
+///
+/// Keys inserted by a phase stay visible to the phases after it: each later
+/// phase starts with its record count set to the number of keys generated so
+/// far, so its insert sequence continues where the previous one stopped.
+///
+/// `RunOptions::phase` can restrict the run to the load, in which case only
+/// the first phase's records are loaded and a single result is returned, or to
+/// the transactions, in which case the database must already hold the first
+/// phase's records.
+///
+/// With `RunOptions::sla` set, every phase's run is checked after the whole
+/// run has finished, and any violation fails it with `Error::SlaViolated`,
+/// which carries the results.
+///
+/// Phases given as a pre-built `CoreWorkload` keep the record count they
+/// were given instead, so their key sequences are up to the caller.
+///
+/// The `before_load` and `after_load` hooks run around the first phase's
+/// load, if there is one, and `before_run` and `after_run` before the first
+/// run phase and after the last one.
+///
+/// With `RunOptions::verify`, the records written by the load are read back
+/// after the last phase and the report is attached to the last result.
+///
+/// The workers run on scoped threads, so neither the database nor its
+/// transaction type need to be `'static`.
+pub fn run_phases_with<T, W: Into<WorkloadSource>>(
+    db: &dyn Db<Transaction = T>,
+    phases: Vec<W>,
+    mut options: RunOptions<T>,
+) -> Result<Vec<RunResult>> {
+    let mut phases = phases.into_iter().map(Into::into).collect::<Vec<_>>();
+    let thread_groups = prepare_run(&mut phases, &mut options)?;
+
+    let mut hooks = std::mem::take(&mut options.hooks);
+
+    #[cfg(feature = "prometheus")]
+    let _metrics_server = serve_metrics(&mut options)?;
+
+    let mut results = Vec::new();
+    let mut key_counts: Option<Vec<usize>> = None;
+    let mut loaded = None;
+
+    for source in phases {
+        let (groups, record_count, workload) =
+            phase_workload(source, key_counts.as_ref(), &thread_groups, &options)?;
+
+        let load = match (&key_counts, options.phase) {
+            (Some(_), _) => None,
+            (None, Phase::RunOnly) => {
+                workload.skip_load(record_count);
+                loaded = Some((workload.clone(), record_count));
+                None
+            }
+            (None, _) => {
+                if let Some(hook) = hooks.before_load.take() {
+                    hook(db)?;
+                }
+
+                let load = load_phase(db, &workload, record_count, &options)?;
+
+                if let Some(hook) = hooks.after_load.take() {
+                    hook(db)?;
+                }
+
+                // A resumed load only counts the records it wrote itself.
+                let count = match options.load_checkpoint {
+                    Some(_) => record_count,
+                    None => load.operations,
+                };
+                loaded = Some((workload.clone(), count));
+                Some(load)
+            }
+        };
+
+        if options.phase == Phase::LoadOnly {
+            results.push(RunResult {
+                load,
+                run: None,
+                verification: None,
+                workload: Some(workload.effective_spec()),
+            });
+            break;
+        }
+
+        if let Some(hook) = hooks.before_run.take() {
+            hook(db)?;
+        }
+
+        let run = run_phase(db, &groups, &options)?;
+
+        key_counts = Some(workload.table_key_counts());
+        results.push(RunResult {
+            load,
+            run: Some(run),
+            verification: None,
+            workload: Some(workload.effective_spec()),
+        });
+    }
+
+    if let (Some(hook), true) = (
+        hooks.after_run.take(),
+        results.iter().any(|r| r.run.is_some()),
+    ) {
+        hook(db)?;
+    }
+
+    if options.verify {
+        if let (Some((workload, count)), Some(last)) = (&loaded, results.last_mut()) {
+            last.verification = Some(verify_phase(db, workload, *count, &options)?);
+        }
+    }
+
+    check_sla(results, &options)
+}
+
+/// Loads and runs a single workload, given as a `WorkloadSpec` or a
+/// `WorkloadSource`. See `run_phases_with` for details.
+pub fn run_ycsb_with<T, W: Into<WorkloadSource>>(
+    db: &dyn Db<Transaction = T>,
+    workload: W,
+    options: RunOptions<T>,
+) -> Result<RunResult> {
+    let mut results = run_phases_with(db, vec![workload], options)?;
+
+    Ok(results.remove(0))
+}
+
+/// Loads the database once with the first phase's `record_count`, then runs
+/// the transaction mix of every phase in order.
+///
+/// Keys inserted by a phase stay visible to the phases after it: each later
+/// phase starts with its record count set to the number of keys generated so
+/// far, so its insert sequence continues where the previous one stopped.
+///
+/// With a `seed`, phase `i` is seeded with `seed + i` in place of its own
+/// seed, so that the whole run is reproducible.
+pub fn run_phases<T: 'static>(
+    db: Arc<dyn Db<Transaction = T>>,
+    phases: Vec<WorkloadSpec>,
+    nr_threads: usize,
+    seed: Option<u64>,
+) -> Result<Vec<RunResult>> {
+    run_phases_scoped(&*db, phases, nr_threads, seed)
+}
+
+/// Like `run_phases`, but borrows the database instead of requiring an `Arc`.
+///
+/// The workers run on scoped threads, so neither the database nor its
+/// transaction type need to be `'static`.
+pub fn run_phases_scoped<T>(
+    db: &dyn Db<Transaction = T>,
+    phases: Vec<WorkloadSpec>,
+    nr_threads: usize,
+    seed: Option<u64>,
+) -> Result<Vec<RunResult>> {
+    let phases = match seed {
+        Some(seed) => phases
+            .into_iter()
+            .enumerate()
+            .map(|(i, spec)| spec.seed(seed.wrapping_add(i as u64)))
+            .collect(),
+        None => phases,
+    };
+
+    run_phases_with(db, phases, RunOptions::new().threads(nr_threads))
+}
+
+pub fn run_ycsb<P: AsRef<Path>, T: 'static>(
+    db: Arc<dyn Db<Transaction = T>>,
+    workload_path: P,
+    nr_threads: usize,
+) -> Result<RunResult> {
+    run_ycsb_scoped(&*db, workload_path, nr_threads)
+}
+
+/// Like `run_ycsb`, but borrows the database instead of requiring an `Arc`.
+/// See `run_phases_scoped` for details.
+pub fn run_ycsb_scoped<P: AsRef<Path>, T>(
+    db: &dyn Db<Transaction = T>,
+    workload_path: P,
+    nr_threads: usize,
+) -> Result<RunResult> {
+    run_ycsb_with(
+        db,
+        WorkloadSpec::from_path(workload_path)?,
+        RunOptions::new().threads(nr_threads),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let db = Arc::new(MockDb::new(true));
+
+        run_ycsb(db, "workloads/workload_a.json", 8).unwrap();
+    }
+
+    #[test]
+    fn test_run_phases() {
+        let db = Arc::new(MockDb::new(true));
+        let load = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+        let insert = WorkloadSpec::default()
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5)
+            .operation_count(1000);
+
+        let results = run_phases(db, vec![load.clone(), insert.clone()], 4, None).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].load.as_ref().unwrap().operations, 1000);
+        assert!(results[1].load.is_none());
+        assert_eq!(results[1].run.as_ref().unwrap().operations, 1000);
+
+        // Seeded runs draw the same operations.
+        let op_counts = || {
+            let db = Arc::new(MockDb::new(true));
+            let results = run_phases(db, vec![load.clone(), insert.clone()], 1, Some(7)).unwrap();
+            results
+                .iter()
+                .flat_map(|r| &r.run.as_ref().unwrap().per_op)
+                .map(|op| (op.op, op.operations))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(op_counts(), op_counts());
+    }
+
+    #[test]
+    fn test_run_with_overrides() {
+        let db = Arc::new(MockDb::new(true));
+        let mut overrides = std::collections::HashMap::new();
+        overrides.insert("record_count".to_owned(), "1000".to_owned());
+        overrides.insert("operation_count".to_owned(), "200".to_owned());
+
+        let options = |overrides: &std::collections::HashMap<_, _>| {
+            RunOptions::new().threads(2).overrides(overrides.clone())
+        };
+        let spec = || WorkloadSpec::from_path("workloads/workload_a.json").unwrap();
+
+        let result = run_ycsb_with(&*db, spec(), options(&overrides)).unwrap();
+        assert_eq!(result.run.unwrap().operations, 200);
+
+        let workload =
+            WorkloadSource::workload(Arc::new(CoreWorkload::new(spec()).unwrap()), 10, 10);
+        assert!(run_ycsb_with(&*db, workload, options(&overrides)).is_err());
+
+        overrides.insert("read_proportion".to_owned(), "2".to_owned());
+        assert!(run_ycsb_with(&*db, spec(), options(&overrides)).is_err());
+    }
+
+    #[test]
+    fn test_run_pinned() {
+        let spec = WorkloadSpec::from_path("workloads/workload_a.json").unwrap();
+        let options = RunOptions::new().threads(4).cpu_set(vec![0]);
+
+        run_ycsb_with(&MockDb::new(true), spec, options).unwrap();
+    }
+
+    struct BorrowingDb<'a> {
+        inner: &'a MockDb,
+    }
+
+    impl<'a> Db for BorrowingDb<'a> {
+        type Transaction = &'a MockDb;
+
+        fn start_transaction(&self) -> Result<Self::Transaction> {
+            self.inner.start_transaction()?;
+            Ok(self.inner)
+        }
+
+        fn commit_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction> {
+            txn.commit_transaction(())?;
+            Ok(txn)
+        }
+
+        fn abort_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction> {
+            txn.abort_transaction(())?;
+            Ok(txn)
+        }
+
+        fn read(
+            &self,
+            txn: &mut Self::Transaction,
+            table: &str,
+            key: &str,
+            fields: Option<Vec<String>>,
+        ) -> Result<Option<Vec<(String, String)>>> {
+            txn.read(&mut (), table, key, fields)
+        }
+
+        fn insert(
+            &self,
+            txn: &mut Self::Transaction,
+            table: &str,
+            key: String,
+            values: Vec<(String, String)>,
+        ) -> Result<()> {
+            txn.insert(&mut (), table, key, values)
+        }
+
+        fn update(
+            &self,
+            txn: &mut Self::Transaction,
+            table: &str,
+            key: String,
+            values: Vec<(String, String)>,
+        ) -> Result<()> {
+            txn.update(&mut (), table, key, values)
+        }
+
+        fn scan(
+            &self,
+            txn: &mut Self::Transaction,
+            table: &str,
+            key: &str,
+            length: usize,
+            fields: Option<Vec<String>>,
+        ) -> Result<Vec<Vec<(String, String)>>> {
+            txn.scan(&mut (), table, key, length, fields)
+        }
+    }
+
+    #[test]
+    fn test_run_scoped() {
+        let mock = MockDb::new(true);
+        let db = BorrowingDb { inner: &mock };
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+
+        let results = run_phases_scoped(&db, vec![spec], 4, None).unwrap();
+
+        assert_eq!(results[0].run.as_ref().unwrap().operations, 1000);
+    }
+
+    #[test]
+    fn test_run_with_options() {
+        let db = MockDb::new(true);
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+        let options = RunOptions::new()
+            .threads(2)
+            .load_batch_size(100)
+            .warmup_ops(200)
+            .verbose(true);
+
+        let result = run_ycsb_with(&db, spec, options).unwrap();
+        assert!(result.to_json().unwrap().contains(r#""workload": {"#));
+        assert_eq!(result.workload.unwrap().get_record_count(), 1000);
+        let run = result.run.unwrap();
+
+        assert_eq!(result.load.unwrap().operations, 1000);
+        assert_eq!(run.operations, 800);
+        assert_eq!(run.threads.len(), 2);
+        assert!(run.threads.iter().all(|t| t.operations == 400));
+        assert!(run.threads.iter().all(|t| t.busy <= t.elapsed));
+        assert!(run.fairness().unwrap().max_min_ratio >= 1.0);
+    }
+
+    #[test]
+    fn test_run_prebuilt_workload() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(500)
+            .operation_count(1000);
+        let workload = Arc::new(CoreWorkload::new(spec).unwrap());
+        let source = WorkloadSource::workload(workload, 500, 200);
+
+        let result = run_ycsb_with(&db, source, RunOptions::new().threads(2)).unwrap();
+
+        assert_eq!(result.load.unwrap().operations, 500);
+        assert_eq!(result.run.unwrap().operations, 200);
+        assert_eq!(db.records.lock().unwrap().len(), 500);
+    }
+
+    #[test]
+    fn test_key_mapper() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(400)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5);
+        let mapper: KeyMapper = Arc::new(|key_num, _| format!("tenant42/user/{:08x}", key_num));
+        let workload = Arc::new(CoreWorkload::with_key_mapper(spec, mapper).unwrap());
+        let source = WorkloadSource::workload(workload, 100, 400);
+
+        let options = RunOptions::new().threads(2).verify(true);
+        let result = run_ycsb_with(&db, source, options).unwrap();
+
+        let verification = result.verification.unwrap();
+        assert_eq!((verification.checked, verification.missing), (100, 0));
+        assert_eq!(db.read_misses.load(std::sync::atomic::Ordering::Relaxed), 0);
+        let records = db.records.lock().unwrap();
+        assert!(records.contains_key("usertable/tenant42/user/00000000"));
+        assert!(records.contains_key("usertable/tenant42/user/00000064"));
+        assert!(records
+            .keys()
+            .all(|key| key.starts_with("usertable/tenant42/user/")));
+    }
+
+    #[test]
+    fn test_throughput_series() {
+        let db = MockDb::new(true);
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+
+        let result = run_ycsb_with(&db, spec, RunOptions::new().threads(4)).unwrap();
+        let run = result.run.as_ref().unwrap();
+        let total: u64 = run.throughput_series.iter().map(|(_, ops)| ops).sum();
+
+        assert!(!run.throughput_series.is_empty());
+        assert_eq!(total, run.operations as u64);
+
+        let json = result.to_json().unwrap();
+        assert!(json.contains("throughput_series"));
+    }
+
+    #[test]
+    fn test_key_frequency() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(10_000)
+        };
+
+        let options = RunOptions::new().threads(4).key_frequency(3);
+        let result =
+            run_ycsb_with(&MockDb::new(true), spec().request_constant(7), options).unwrap();
+        let hot_keys = &result.run.as_ref().unwrap().hot_keys;
+        assert_eq!(hot_keys.len(), 1);
+        assert_eq!(hot_keys[0].key_num, 7);
+        assert_eq!(hot_keys[0].table, "usertable");
+        assert_eq!(hot_keys[0].count, 10_000);
+        assert_eq!(hot_keys[0].error, 0);
+        assert!(result.to_json().unwrap().contains("hot_keys"));
+
+        let options = RunOptions::new().threads(4).key_frequency(5);
+        let result = run_ycsb_with(&MockDb::new(true), spec().request_zipfian(0.99), options);
+        let hot_keys = result.unwrap().run.unwrap().hot_keys;
+        assert_eq!(hot_keys.len(), 5);
+        assert!(hot_keys.windows(2).all(|w| w[0].count >= w[1].count));
+        assert!(hot_keys[0].count > 10_000 / 1000 * 10);
+
+        let result = run_ycsb_with(&MockDb::new(true), spec(), RunOptions::new()).unwrap();
+        assert!(result.run.unwrap().hot_keys.is_empty());
+    }
+
+    struct FailingReadDb;
+
+    impl Db for FailingReadDb {
+        type Transaction = ();
+
+        fn start_transaction(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn commit_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        fn abort_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        fn read(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Option<Vec<(String, String)>>> {
+            Err(Error::InvalidArgument("read".to_owned()))
+        }
+
+        fn insert(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn update(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn scan(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _length: usize,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Vec<Vec<(String, String)>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(100)
+                .operation_count(1000)
+                .read_proportion(0.5)
+                .update_proportion(0.5)
+        };
+
+        assert!(run_ycsb_with(&FailingReadDb, spec(), RunOptions::new()).is_err());
+
+        let options = RunOptions::new()
+            .threads(2)
+            .error_policy(ErrorPolicy::CountAndContinue);
+        let run = run_ycsb_with(&FailingReadDb, spec(), options)
+            .unwrap()
+            .run
+            .unwrap();
+
+        assert_eq!(run.operations + run.failed, 1000);
+        assert!(run.failed > 0);
+        assert_eq!(run.per_op.len(), 1);
+        assert_eq!(run.per_op[0].op, Operation::Update);
+        assert_eq!(run.per_op[0].operations, run.operations);
+        assert_eq!(run.errors.len(), 1);
+        assert_eq!(run.errors[0].op, Operation::Read);
+        assert_eq!(run.errors[0].kind, "InvalidArgument");
+        assert_eq!(run.errors[0].count as usize, run.failed);
+    }
+
+    /// Commits take 1ms, and every third one aborts.
+    #[derive(Default)]
+    struct SlowCommitDb {
+        commits: std::sync::atomic::AtomicU64,
+    }
+
+    impl Db for SlowCommitDb {
+        type Transaction = ();
+
+        fn start_transaction(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn commit_transaction(&self, _txn: ()) -> Result<()> {
+            thread::sleep(Duration::from_millis(1));
+
+            match self
+                .commits
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                % 3
+            {
+                2 => Err(Error::TransactionAborted),
+                _ => Ok(()),
+            }
+        }
+
+        fn abort_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        fn read(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Option<Vec<(String, String)>>> {
+            Ok(None)
+        }
+
+        fn insert(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn update(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            _values: Vec<(String, String)>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn scan(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _length: usize,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Vec<Vec<(String, String)>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_latency_breakdown() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(10)
+                .operation_count(60)
+                .read_proportion(1.0)
+                .update_proportion(0.0)
+        };
+        let options = RunOptions::new().phase(Phase::RunOnly);
+
+        let run = run_ycsb_with(&SlowCommitDb::default(), spec(), options)
+            .unwrap()
+            .run
+            .unwrap();
+        let reads = &run.per_op[0];
+        let (execute, commit) = (
+            reads.execute.as_ref().unwrap(),
+            reads.commit.as_ref().unwrap(),
+        );
+        let wasted = reads.wasted.as_ref().unwrap();
+
+        assert_eq!(execute.latency.count, 60);
+        assert_eq!(commit.latency.count, 60);
+        assert!(commit.latency.min_us >= 1000.0 && execute.latency.mean_us < 1000.0);
+        // Every third commit aborts: 29 of them before the 60th succeeds.
+        assert_eq!(wasted.latency.count, 29);
+        assert!(wasted.latency.min_us >= 1000.0);
+        assert!(serde_json::to_string(&run).unwrap().contains("\"wasted\""));
+
+        let options = RunOptions::new()
+            .phase(Phase::RunOnly)
+            .latency_breakdown(false);
+        let run = run_ycsb_with(&SlowCommitDb::default(), spec(), options)
+            .unwrap()
+            .run
+            .unwrap();
+        let reads = &run.per_op[0];
+        assert!(reads.execute.is_none() && reads.commit.is_none() && reads.wasted.is_none());
+    }
+
+    #[test]
+    fn test_run_with_invalid_options() {
+        let db = MockDb::new(true);
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+
+        assert!(run_ycsb_with(&db, spec, RunOptions::new().threads(0)).is_err());
+    }
+
+    #[test]
+    fn test_raw_latency_output() {
+        let db = MockDb::new(true);
+        let path = std::env::temp_dir().join("ycsb_rs_test_raw_latency.csv");
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+        let options = RunOptions::new().threads(4).raw_latency_output(&path);
+
+        run_ycsb_with(&db, spec, options).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next(), Some("timestamp_us,op,latency_us,success"));
+        assert_eq!(lines.clone().count(), 1000);
+        assert!(lines.all(|line| {
+            let cols = line.split(',').collect::<Vec<_>>();
+            cols.len() == 4 && (cols[1] == "READ" || cols[1] == "UPDATE") && cols[3] == "1"
+        }));
+    }
+
+    /// Keeps records under `table/key`.
+    #[derive(Default)]
+    struct MemoryDb {
+        records: std::sync::Mutex<std::collections::HashMap<String, Vec<(String, String)>>>,
+        read_misses: std::sync::atomic::AtomicUsize,
+        keys_read: std::sync::Mutex<std::collections::HashSet<String>>,
+        fields_read: std::sync::Mutex<Vec<Option<Vec<String>>>>,
+        fields_updated: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    impl Db for MemoryDb {
+        type Transaction = ();
+
+        fn start_transaction(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn commit_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        fn abort_transaction(&self, _txn: ()) -> Result<()> {
+            Ok(())
+        }
+
+        fn read(
+            &self,
+            _txn: &mut (),
+            table: &str,
+            key: &str,
+            fields: Option<Vec<String>>,
+        ) -> Result<Option<Vec<(String, String)>>> {
+            self.fields_read.lock().unwrap().push(fields);
+            let record = self
+                .records
+                .lock()
+                .unwrap()
+                .get(&format!("{}/{}", table, key))
+                .cloned();
+            self.keys_read.lock().unwrap().insert(key.to_owned());
+            if record.is_none() {
+                self.read_misses
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+
+            Ok(record)
+        }
+
+        fn insert(
+            &self,
+            _txn: &mut (),
+            table: &str,
+            key: String,
+            values: Vec<(String, String)>,
+        ) -> Result<()> {
+            self.records
+                .lock()
+                .unwrap()
+                .insert(format!("{}/{}", table, key), values);
+            Ok(())
+        }
+
+        fn update(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: String,
+            values: Vec<(String, String)>,
+        ) -> Result<()> {
+            self.fields_updated
+                .lock()
+                .unwrap()
+                .push(values.into_iter().map(|(name, _)| name).collect());
+            Ok(())
+        }
+
+        fn scan(
+            &self,
+            _txn: &mut (),
+            _table: &str,
+            _key: &str,
+            _length: usize,
+            _fields: Option<Vec<String>>,
+        ) -> Result<Vec<Vec<(String, String)>>> {
+            Ok(Vec::new())
+        }
+
+        fn delete(&self, _txn: &mut (), table: &str, key: &str) -> Result<()> {
+            self.records
+                .lock()
+                .unwrap()
+                .remove(&format!("{}/{}", table, key));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fields_written() {
+        for &(write_all_fields, fields_per_write, expected) in
+            &[(true, 1, 5), (true, 3, 5), (false, 1, 1), (false, 3, 3)]
+        {
+            let db = MemoryDb::default();
+            let spec = WorkloadSpec::default()
+                .record_count(20)
+                .operation_count(200)
+                .field_count(5)
+                .read_proportion(0.0)
+                .update_proportion(0.4)
+                .rmw_proportion(0.4)
+                .insert_proportion(0.2)
+                .write_all_fields(write_all_fields)
+                .fields_per_write(fields_per_write);
+            run_ycsb_with(&db, spec, RunOptions::new()).unwrap();
+
+            let fields_updated = db.fields_updated.into_inner().unwrap();
+            assert!(!fields_updated.is_empty());
+            assert!(
+                fields_updated.iter().all(|names| names.len() == expected),
+                "write_all_fields = {}, fields_per_write = {}",
+                write_all_fields,
+                fields_per_write
+            );
+
+            let records = db.records.into_inner().unwrap();
+            assert!(records.len() > 20);
+            assert!(records.values().all(|values| values.len() == 5));
+        }
+    }
+
+    #[test]
+    fn test_rmw_fields() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_count(5)
+            .read_all_fields(false)
+            .fields_per_read(2)
+            .read_proportion(0.0)
+            .update_proportion(0.0)
+            .rmw_proportion(1.0);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let client = Client::new(&db, &workload);
+
+        // Nothing to modify yet.
+        assert_eq!(client.rmw_txn(&mut ()).unwrap(), (None, 0));
+        assert!(db.fields_updated.lock().unwrap().is_empty());
+
+        for key_num in 0..10 {
+            let key = workload.get_key_name(key_num);
+            db.insert(
+                &mut (),
+                "usertable",
+                key.clone(),
+                workload.build_values(&key),
+            )
+            .unwrap();
+        }
+
+        for _ in 0..100 {
+            client.rmw_txn(&mut ()).unwrap();
+
+            let read = db.fields_read.lock().unwrap().last().cloned().unwrap();
+            let updated = db.fields_updated.lock().unwrap().last().cloned().unwrap();
+            assert_eq!(read, Some(updated));
+        }
+    }
+
+    #[test]
+    fn test_client_results() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(2)
+            .field_count(3)
+            .field_len_const(10)
+            .read_proportion(1.0)
+            .update_proportion(0.0);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let client = Client::new(&db, &workload);
+
+        assert_eq!(client.read_txn(&mut ()).unwrap(), (None, false));
+
+        for key_num in 0..2 {
+            db.insert(
+                &mut (),
+                "usertable",
+                workload.get_key_name(key_num),
+                workload.build_values(&workload.get_key_name(key_num)),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(client.read_txn(&mut ()).unwrap().0.unwrap().len(), 3);
+        let (table, key_num, written) = client.insert_txn(&mut ()).unwrap();
+        assert_eq!((table.name(), key_num, written), ("usertable", 2, 30));
+
+        let (record, written) = client.rmw_txn(&mut ()).unwrap();
+        assert_eq!(record.unwrap().len(), 3);
+        assert!(written > 0 && written % 10 == 0);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1000)
+            .field_count(4)
+            .field_len_const(25)
+            .read_all_fields(true)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5);
+
+        let result = run_ycsb_with(&db, spec, RunOptions::new().threads(2)).unwrap();
+        let load = result.load.unwrap();
+        let run = result.run.unwrap();
+        let op = |op| run.per_op.iter().find(|r| r.op == op).unwrap();
+
+        assert_eq!(load.bytes_written, 100 * 100);
+        assert_eq!(op(Operation::Insert).bytes_read, 0);
+        assert_eq!(
+            op(Operation::Insert).bytes_written,
+            op(Operation::Insert).operations as u64 * 100
+        );
+        assert_eq!(op(Operation::Read).bytes_written, 0);
+        assert!(op(Operation::Read).bytes_read <= op(Operation::Read).operations as u64 * 100);
+        assert_eq!(run.bytes_read, op(Operation::Read).bytes_read);
+        assert_eq!(run.bytes_written, op(Operation::Insert).bytes_written);
+        assert!(run.mb_per_sec(run.bytes_written) > 0.0);
+    }
+
+    #[test]
+    fn test_batched_progress() {
+        let pb = ProgressBar::hidden();
+        let mut progress = BatchedProgress::new(&pb);
+
+        for _ in 0..1500 {
+            progress.inc();
+        }
+        assert_eq!(pb.position(), PROGRESS_BATCH);
+
+        drop(progress);
+        assert_eq!(pb.position(), 1500);
+
+        let mut progress = BatchedProgress::new(&pb);
+        progress.add(10);
+        assert_eq!(pb.position(), 1500);
+        thread::sleep(PROGRESS_INTERVAL);
+        progress.inc();
+        assert_eq!(pb.position(), 1511);
+    }
+
+    #[test]
+    fn test_partition() {
+        let parts = (0..4).map(|i| partition(10..20, 4, i)).collect::<Vec<_>>();
+
+        assert_eq!(parts, vec![10..13, 13..16, 16..18, 18..20]);
+        assert_eq!(partition(0..3, 4, 3), 3..3);
+    }
+
+    #[test]
+    fn test_verify() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(1000)
+        };
+        let options = || RunOptions::new().threads(3).verify(true);
+
+        let db = MemoryDb::default();
+        let report = run_ycsb_with(&db, spec(), options())
+            .unwrap()
+            .verification
+            .unwrap();
+
+        assert_eq!(report.checked, 999);
+        assert_eq!(report.missing, 0);
+        assert_eq!(report.corrupted, 0);
+
+        let report = run_ycsb_with(&MockDb::new(true), spec(), options())
+            .unwrap()
+            .verification
+            .unwrap();
+
+        assert_eq!(report.checked, 999);
+        assert_eq!(report.missing, 999);
+    }
+
+    #[test]
+    fn test_partitioned_load() {
+        let db = MemoryDb::default();
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000);
+        let options = RunOptions::new()
+            .threads(3)
+            .partitioned_load(true)
+            .verify(true);
+
+        let result = run_ycsb_with(&db, spec, options).unwrap();
+        let report = result.verification.unwrap();
+
+        assert_eq!(result.load.unwrap().operations, 1000);
+        assert_eq!(db.records.lock().unwrap().len(), 1000);
+        assert_eq!(report.checked, 1000);
+        assert_eq!(report.missing, 0);
+    }
+
+    #[test]
+    fn test_load_checkpoint() {
+        let path = std::env::temp_dir().join(format!(
+            "ycsb_rs_test_load_checkpoint_{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(1000)
+        };
+
+        // As if a load on 2 threads stopped after 300 and 100 records.
+        let workload = CoreWorkload::for_load(spec()).unwrap();
+        let partitions = [0..500, 500..1000];
+        let checkpoint =
+            LoadCheckpoint::open(&path, 1, workload.load_spec_hash(), &partitions).unwrap();
+        checkpoint.advance(0, 300, 1).unwrap();
+        checkpoint.advance(1, 600, 1).unwrap();
+
+        let db = MemoryDb::default();
+        let options = || RunOptions::new().threads(2).load_checkpoint(&path);
+        let result = run_ycsb_with(&db, spec().operation_count(10), options().verify(true));
+        let result = result.unwrap();
+
+        assert_eq!(result.load.unwrap().operations, 600);
+        assert_eq!(db.records.lock().unwrap().len(), 600);
+        assert_eq!(result.verification.unwrap().missing, 400);
+
+        // Finished, so there's nothing left to load.
+        let options = options().phase(Phase::LoadOnly);
+        let result = run_ycsb_with(&MemoryDb::default(), spec(), options).unwrap();
+        assert_eq!(result.load.unwrap().operations, 0);
+
+        let options = RunOptions::new().threads(2).load_checkpoint(&path);
+        assert!(run_ycsb_with(&db, spec().record_count(2000), options).is_err());
+        let options = RunOptions::new().threads(3).load_checkpoint(&path);
+        assert!(run_ycsb_with(&db, spec(), options).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_only_and_run_only() {
+        let db = MemoryDb::default();
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(1000)
+        };
+
+        let result = run_ycsb_with(&db, spec(), RunOptions::new().phase(Phase::LoadOnly)).unwrap();
+
+        assert_eq!(result.load.unwrap().operations, 1000);
+        assert!(result.run.is_none());
+        assert_eq!(db.records.lock().unwrap().len(), 1000);
+
+        let options = RunOptions::new().phase(Phase::RunOnly).verify(true);
+        let result = run_ycsb_with(&db, spec().insert_proportion(0.1), options).unwrap();
+        let report = result.verification.unwrap();
+
+        assert!(result.load.is_none());
+        assert_eq!(result.run.unwrap().operations, 1000);
+        assert_eq!(report.checked, 1000);
+        assert_eq!(report.missing, 0);
+        assert!(db.records.lock().unwrap().len() > 1000);
+        assert!(db.records.lock().unwrap().len() <= 1000 + 1000);
+
+        // Run phase fields that could not run don't stop a load.
+        let load_only = || {
+            spec()
+                .read_proportion(0.0)
+                .update_proportion(0.0)
+                .request_constant(5000)
+        };
+        let options = RunOptions::new().phase(Phase::LoadOnly);
+        let result = run_ycsb_with(&MemoryDb::default(), load_only(), options).unwrap();
+        assert_eq!(result.load.unwrap().operations, 1000);
+        assert!(run_ycsb_with(&MemoryDb::default(), load_only(), RunOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_incremental_load() {
+        let db = MemoryDb::default();
+        let spec = || WorkloadSpec::default().record_count(1000);
+        let load = |spec: WorkloadSpec| {
+            let options = RunOptions::new().threads(2).phase(Phase::LoadOnly);
+            run_ycsb_with(&db, spec, options).unwrap().load.unwrap()
+        };
+
+        assert_eq!(load(spec().insert_count(600)).operations, 600);
+        assert_eq!(db.records.lock().unwrap().len(), 600);
+        assert_eq!(
+            load(spec().insert_start(600).insert_count(400)).operations,
+            400
+        );
+        assert_eq!(db.records.lock().unwrap().len(), 1000);
+
+        let options = RunOptions::new().phase(Phase::RunOnly).verify(true);
+        let result = run_ycsb_with(&db, spec().operation_count(100), options).unwrap();
+        let report = result.verification.unwrap();
+        assert_eq!(report.checked, 1000);
+        assert_eq!(report.missing, 0);
+    }
+
+    #[test]
+    fn test_hooks() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(100)
+                .operation_count(100)
+        };
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook = |name: &'static str| {
+            let calls = calls.clone();
+            move |_: &dyn Db<Transaction = ()>| {
+                calls.lock().unwrap().push(name);
+                Ok(())
+            }
+        };
+
+        let options = RunOptions::new()
+            .before_load(hook("before_load"))
+            .after_load(hook("after_load"))
+            .before_run(hook("before_run"))
+            .after_run(hook("after_run"));
+        run_phases_with(&MockDb::new(true), vec![spec(), spec()], options).unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["before_load", "after_load", "before_run", "after_run"]
+        );
+
+        calls.lock().unwrap().clear();
+        let options = RunOptions::new()
+            .after_load(|_| Err(Error::InvalidArgument("not settled".to_owned())))
+            .before_run(hook("before_run"));
+
+        assert!(run_ycsb_with(&MockDb::new(true), spec(), options).is_err());
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_arrival_rate() {
+        let db = MockDb::new(true);
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1000);
+        let options = RunOptions::new().threads(2).arrival_rate(4000.0);
+
+        let run = run_ycsb_with(&db, spec, options).unwrap().run.unwrap();
+
+        assert_eq!(run.operations, 1000);
+        assert!(
+            run.elapsed >= Duration::from_millis(150),
+            "{:?}",
+            run.elapsed
+        );
+        assert!(RunOptions::<()>::new()
+            .arrival_rate(0.0)
+            .validate(0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_think_time() {
+        let db = MockDb::new(true);
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(100)
+                .operation_count(200)
+                .think_time_dist(DistributionSpec::Constant(2000))
+        };
+        let options = RunOptions::new().threads(2);
+
+        let run = run_ycsb_with(&db, spec(), options).unwrap().run.unwrap();
+
+        // 100 operations per thread, 2ms apart.
+        assert_eq!(run.operations, 200);
+        assert!(
+            run.elapsed >= Duration::from_millis(200),
+            "{:?}",
+            run.elapsed
+        );
+        assert!(run.latency_quantile(Operation::Read, 0.5).unwrap() < Duration::from_millis(2));
+
+        let options = RunOptions::new().arrival_rate(1000.0);
+        assert!(run_ycsb_with(&db, spec(), options).is_err());
+    }
+
+    #[test]
+    fn test_ramp_up() {
+        let db = MockDb::new(true);
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1200)
+            .think_time_dist(DistributionSpec::Constant(1000));
+        let options = RunOptions::new()
+            .threads(4)
+            .ramp_up(Duration::from_millis(100));
+
+        let started = Instant::now();
+        let run = run_ycsb_with(&db, spec, options).unwrap().run.unwrap();
+
+        // Each thread runs for over 300ms, the last one starting 75ms in.
+        assert!(run.ramp_ops > 0 && run.operations > 0, "{:?}", run);
+        assert_eq!(run.operations + run.ramp_ops, 1200);
+        assert!(run.elapsed < started.elapsed() - Duration::from_millis(100));
+        assert_eq!(run.ramp_intervals, vec![0]);
+        let total: u64 = run.throughput_series.iter().map(|(_, ops)| ops).sum();
+        assert_eq!(total, 1200);
+    }
+
+    #[test]
+    fn test_latest_reads_acknowledged_inserts() {
+        let spec = WorkloadSpec::default()
+            .record_count(0)
+            .operation_count(4000)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5)
+            .request_latest();
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(4).warmup_ops(0);
+        run_ycsb_with(&db, spec, options).unwrap();
+
+        // Reads before the first insert is acknowledged go to key 0, which
+        // doesn't exist yet. How many depends on thread scheduling.
+        let misses = db.read_misses.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(misses <= 20, "{}", misses);
+        assert!(db.records.lock().unwrap().len() >= 1900);
+    }
+
+    #[test]
+    fn test_workload_d_reads_hit() {
+        for &ordered in &[false, true] {
+            let spec = WorkloadSpec::workload_d()
+                .record_count(1000)
+                .operation_count(20_000)
+                .ordered_insert(ordered);
+
+            let db = MemoryDb::default();
+            let options = RunOptions::new().threads(4).warmup_ops(0);
+            let result = run_ycsb_with(&db, spec, options).unwrap();
+            let reads = result
+                .run
+                .unwrap()
+                .per_op
+                .iter()
+                .find(|r| r.op == Operation::Read)
+                .unwrap()
+                .operations;
+
+            let misses = db.read_misses.load(std::sync::atomic::Ordering::Relaxed);
+            assert!(misses * 100 < reads, "{} of {} reads missed", misses, reads);
+        }
+    }
+
+    #[test]
+    fn test_read_misses() {
+        let spec = WorkloadSpec::workload_c()
+            .record_count(1000)
+            .operation_count(10_000)
+            .read_miss_proportion(0.1);
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(4).warmup_ops(0);
+        let run = run_ycsb_with(&db, spec, options).unwrap().run.unwrap();
+        let misses = run.read_misses.as_ref().unwrap();
+
+        assert_eq!(misses.op, Operation::Read);
+        assert!(
+            (800..1200).contains(&misses.operations),
+            "{}",
+            misses.operations
+        );
+        assert_eq!(
+            db.read_misses.load(std::sync::atomic::Ordering::Relaxed),
+            misses.operations
+        );
+        assert_eq!(run.per_op[0].operations, 10_000);
+        assert!(serde_json::to_string(&run).unwrap().contains("read_misses"));
+
+        let run = run_ycsb_with(&db, WorkloadSpec::workload_c(), RunOptions::new())
+            .unwrap()
+            .run
+            .unwrap();
+        assert!(run.read_misses.is_none());
+    }
+
+    #[test]
+    fn test_tables() {
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(4000)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5)
+            .tables(vec![("users".to_owned(), 3.0), ("orders".to_owned(), 1.0)]);
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(4).warmup_ops(0);
+        run_ycsb_with(&db, spec, options).unwrap();
+
+        assert_eq!(db.read_misses.load(std::sync::atomic::Ordering::Relaxed), 0);
+        let records = db.records.lock().unwrap();
+        let users = records.keys().filter(|k| k.starts_with("users/")).count();
+        let orders = records.keys().filter(|k| k.starts_with("orders/")).count();
+        assert_eq!(users + orders, records.len());
+        assert!(users > 2 * orders && orders > 500, "{} {}", users, orders);
+    }
+
+    #[test]
+    fn test_uniform_reads_inserted_keys() {
+        let props = "recordcount=100\noperationcount=4000\nreadproportion=0.5\n\
+                     updateproportion=0\ninsertproportion=0.5\nrequestdistribution=uniform\n\
+                     insertorder=ordered\n";
+        let (spec, _) = WorkloadSpec::from_properties(props.as_bytes()).unwrap();
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(4).warmup_ops(0);
+        run_ycsb_with(&db, spec, options).unwrap();
+
+        let inserted_reads = db
+            .keys_read
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|key| key["user".len()..].parse::<usize>().unwrap() >= 200)
+            .count();
+        assert!(inserted_reads > 0);
+        assert_eq!(db.read_misses.load(std::sync::atomic::Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_thread_groups() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(100)
+                .ordered_insert(true)
+        };
+        let readers = spec()
+            .operation_count(3000)
+            .read_proportion(1.0)
+            .update_proportion(0.0)
+            .request_latest();
+        let writers = spec()
+            .operation_count(1000)
+            .read_proportion(0.0)
+            .update_proportion(0.0)
+            .insert_proportion(1.0);
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().thread_groups(vec![(readers.clone(), 3), (writers, 1)]);
+        let run = run_ycsb_with(&db, spec(), options).unwrap().run.unwrap();
+
+        assert_eq!(run.operations, 4000);
+        assert_eq!(run.threads.len(), 4);
+        assert_eq!(run.groups.len(), 2);
+        assert_eq!(run.groups[0].operations, 3000);
+        assert_eq!(run.groups[0].per_op[0].op, Operation::Read);
+        assert_eq!(run.groups[1].threads.len(), 1);
+        assert_eq!(run.groups[1].per_op[0].op, Operation::Insert);
+
+        // The readers follow the writer's inserts.
+        assert_eq!(db.records.lock().unwrap().len(), 1100);
+        assert!(db
+            .keys_read
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|key| key["user".len()..].parse::<usize>().unwrap() >= 100));
+        assert_eq!(db.read_misses.load(std::sync::atomic::Ordering::Relaxed), 0);
+
+        // Each group's share of the warmup comes out of its own operations.
+        let readers = readers
+            .operation_count(900)
+            .read_proportion(0.5)
+            .update_proportion(0.5);
+        let writers = spec().operation_count(100);
+        let options = || {
+            RunOptions::new()
+                .warmup_ops(400)
+                .thread_groups(vec![(readers.clone(), 3), (writers.clone(), 1)])
+        };
+        let run = run_ycsb_with(&MemoryDb::default(), spec(), options())
+            .unwrap()
+            .run
+            .unwrap();
+        assert_eq!(run.groups[0].operations, 600);
+        assert_eq!(run.groups[1].operations, 0);
+
+        let options = options().warmup_ops(800);
+        assert!(matches!(
+            run_ycsb_with(&MemoryDb::default(), spec(), options),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let options = RunOptions::new().thread_groups(vec![(readers.record_count(10), 1)]);
+        assert!(run_ycsb_with(&MemoryDb::default(), spec(), options).is_err());
+    }
+
+    #[test]
+    fn test_key_file() {
+        let path = std::env::temp_dir().join("ycsb_rs_test_run_keys.txt");
+        let keys = (0..100).map(|i| format!("k{}", i * 7)).collect::<Vec<_>>();
+        std::fs::write(&path, keys.join("\n")).unwrap();
+
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1000)
+            .request_keys_from(&path);
+        let db = MemoryDb::default();
+        run_ycsb_with(&db, spec, RunOptions::new().threads(2)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut loaded = db
+            .records
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut expected = keys
+            .iter()
+            .map(|key| format!("usertable/{}", key))
+            .collect::<Vec<_>>();
+        loaded.sort();
+        expected.sort();
+        assert_eq!(loaded, expected);
+        assert_eq!(db.read_misses.load(std::sync::atomic::Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_run_inserts_keep_loaded_keys() {
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(1000)
+            .read_proportion(0.5)
+            .update_proportion(0.0)
+            .insert_proportion(0.5);
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(4).warmup_ops(0);
+        let result = run_ycsb_with(&db, spec, options).unwrap();
+        let inserts = result
+            .run
+            .unwrap()
+            .per_op
+            .iter()
+            .find(|r| r.op == Operation::Insert)
+            .unwrap()
+            .operations;
+
+        assert_eq!(db.records.lock().unwrap().len(), 100 + inserts);
+    }
+
+    #[test]
+    fn test_data_integrity() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(100)
+                .operation_count(1000)
+                .read_proportion(0.5)
+                .update_proportion(0.3)
+                .rmw_proportion(0.2)
+                .data_integrity(true)
+        };
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(2).verify(true);
+        let result = run_ycsb_with(&db, spec(), options).unwrap();
+        assert_eq!(result.run.unwrap().verification_errors, 0);
+        assert_eq!(result.verification.unwrap().corrupted, 0);
+
+        let workload = CoreWorkload::new(spec().read_proportion(1.0).record_count(1)).unwrap();
+        let client = Client::new(&db, &workload);
+        db.records
+            .lock()
+            .unwrap()
+            .get_mut(&format!("usertable/{}", workload.get_key_name(0)))
+            .unwrap()
+            .iter_mut()
+            .for_each(|(_, value)| value.replace_range(90..91, "#"));
+
+        assert!(client.read_txn(&mut ()).unwrap().0.is_some());
+        assert_eq!(client.verification_errors(), 1);
+    }
+
+    #[test]
+    fn test_delete() {
+        let spec = |skip| {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(1000)
+                .read_proportion(0.5)
+                .update_proportion(0.0)
+                .delete_proportion(0.5)
+                .skip_deleted_keys(skip)
+        };
+
+        let db = MemoryDb::default();
+        let options = RunOptions::new().threads(2).verify(true);
+        let result = run_ycsb_with(&db, spec(true), options).unwrap();
+        let run = result.run.unwrap();
+        let report = result.verification.unwrap();
+        let deleted = run
+            .per_op
+            .iter()
+            .find(|r| r.op == Operation::Delete)
+            .unwrap();
+        let remaining = db.records.lock().unwrap().len();
+
+        assert_eq!(remaining, 1000 - deleted.operations);
+        assert_eq!(report.checked, remaining);
+        assert_eq!(report.missing, 0);
+
+        assert!(run_ycsb_with(&MockDb::new(true), spec(false), RunOptions::new()).is_ok());
+        assert!(matches!(
+            run_ycsb_with(
+                &BorrowingDb {
+                    inner: &MockDb::new(true)
+                },
+                spec(false),
+                RunOptions::new()
+            ),
+            Err(Error::InvalidArgument(_))
+        ));
     }
 }
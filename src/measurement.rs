@@ -0,0 +1,1118 @@
+#[cfg(feature = "prometheus")]
+use crate::metrics::Metrics;
+use crate::{Error, KeyFrequency, Operation, Result, WorkloadSpec};
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "prometheus")]
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// Operations completed per sampling interval, with the interval's length.
+pub(crate) type ThroughputSeries = Vec<(Duration, u64)>;
+
+/// Samples buffered per worker before they are written out.
+const RAW_LATENCY_CHUNK: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ErrorCount {
+    pub op: Operation,
+    pub kind: &'static str,
+    pub count: u64,
+}
+
+/// Latency summary of the completed operations of a phase, in microseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_us: f64,
+    pub min_us: f64,
+    pub max_us: f64,
+    pub stddev_us: f64,
+}
+
+/// Part of the time of a transaction; see `RunOptions::latency_breakdown`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stage {
+    Execute,
+    Commit,
+    Wasted,
+}
+
+impl Stage {
+    pub(crate) const ALL: [Stage; 3] = [Stage::Execute, Stage::Commit, Stage::Wasted];
+
+    #[cfg_attr(not(feature = "prometheus"), allow(dead_code))]
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Stage::Execute => "execute",
+            Stage::Commit => "commit",
+            Stage::Wasted => "wasted",
+        }
+    }
+}
+
+/// Latency of one part of the transactions of an operation type.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageLatency {
+    pub latency: LatencySummary,
+    /// Latency distribution, for quantiles.
+    #[serde(skip)]
+    pub histogram: LatencyHistogram,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OpResult {
+    pub op: Operation,
+    pub operations: usize,
+    pub latency: LatencySummary,
+    /// Field value bytes returned by reads and scans.
+    pub bytes_read: u64,
+    /// Field value bytes sent by inserts and updates.
+    pub bytes_written: u64,
+    /// Latency distribution, for quantiles.
+    #[serde(skip)]
+    pub histogram: LatencyHistogram,
+    /// Time of the successful attempts from the start of the transaction
+    /// until the operation's calls returned, with
+    /// `RunOptions::latency_breakdown`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execute: Option<StageLatency>,
+    /// Time of the successful attempts spent committing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<StageLatency>,
+    /// Time of the attempts aborted with `Error::TransactionAborted` and
+    /// retried, if any were.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasted: Option<StageLatency>,
+}
+
+/// What one worker thread did during the run phase.
+#[derive(Clone, Debug, Serialize)]
+pub struct ThreadResult {
+    pub operations: usize,
+    /// Wall time from the thread's first operation to its last, within the
+    /// measured window when the phase has a ramp-up.
+    pub elapsed: Duration,
+    /// Time spent in database calls, aborted attempts included. A thread that
+    /// is busy for much less than `elapsed` was descheduled or held up
+    /// outside the database.
+    pub busy: Duration,
+}
+
+impl ThreadResult {
+    pub fn throughput(&self) -> f64 {
+        self.operations as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Spread of throughput across the worker threads of a phase.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Fairness {
+    /// Throughput of the fastest thread over that of the slowest.
+    pub max_min_ratio: f64,
+    /// Standard deviation of the per-thread throughput over its mean.
+    pub cv: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PhaseResult {
+    pub operations: usize,
+    pub elapsed: Duration,
+    /// Latency of all completed operations, from the first attempt to the
+    /// commit, so aborted attempts count towards it. With open-loop arrivals
+    /// it is measured from the operation's arrival time instead. Only
+    /// measured in the run phase.
+    pub latency: Option<LatencySummary>,
+    /// Field value bytes returned by the database, summed over completed
+    /// operations. Field names are not counted.
+    pub bytes_read: u64,
+    /// Field value bytes sent to the database, summed over completed
+    /// operations.
+    pub bytes_written: u64,
+    /// Records read back with values other than the ones written, when the
+    /// workload checks data integrity.
+    pub verification_errors: u64,
+    /// Completed operations per operation type, for the types that ran.
+    pub per_op: Vec<OpResult>,
+    /// Per worker thread, in thread order. Only measured in the run phase.
+    pub threads: Vec<ThreadResult>,
+    /// Operations that failed and were skipped under
+    /// `ErrorPolicy::CountAndContinue`.
+    pub failed: usize,
+    /// Failed operations grouped by operation type and error kind.
+    pub errors: Vec<ErrorCount>,
+    /// Operations completed in each sampling interval, paired with the
+    /// interval's actual length. The last interval is usually shorter than
+    /// the others; the counts always add up to `operations` plus
+    /// `ramp_ops`.
+    pub throughput_series: Vec<(Duration, u64)>,
+    /// Operations started during the ramp-up or ramp-down, with
+    /// `RunOptions::ramp_up`. They are left out of every other statistic.
+    pub ramp_ops: usize,
+    /// Indices of the `throughput_series` intervals that overlap the
+    /// ramp-up or ramp-down.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ramp_intervals: Vec<usize>,
+    /// Keys drawn most often in the run phase, hottest first, with
+    /// `RunOptions::key_frequency`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hot_keys: Vec<KeyFrequency>,
+    /// Reads of keys that were never inserted, with
+    /// `WorkloadSpec::read_miss_proportion`. They count towards the reads in
+    /// `per_op` too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_misses: Option<OpResult>,
+    /// Results of each group of `RunOptions::thread_groups`, in order, over
+    /// the same elapsed time as the whole phase.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<PhaseResult>,
+}
+
+impl PhaseResult {
+    pub fn new(operations: usize, elapsed: Duration) -> Self {
+        Self {
+            operations,
+            elapsed,
+            latency: None,
+            bytes_read: 0,
+            bytes_written: 0,
+            verification_errors: 0,
+            per_op: Vec::new(),
+            threads: Vec::new(),
+            failed: 0,
+            errors: Vec::new(),
+            throughput_series: Vec::new(),
+            ramp_ops: 0,
+            ramp_intervals: Vec::new(),
+            hot_keys: Vec::new(),
+            read_misses: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Fraction of attempted operations that failed.
+    pub fn failure_rate(&self) -> f64 {
+        let attempted = self.operations + self.failed;
+
+        if attempted == 0 {
+            0.0
+        } else {
+            self.failed as f64 / attempted as f64
+        }
+    }
+
+    /// Operations per second over the whole phase.
+    pub fn throughput(&self) -> f64 {
+        self.operations as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Rate of `bytes` over the whole phase, in megabytes (10^6 bytes) per
+    /// second.
+    pub fn mb_per_sec(&self, bytes: u64) -> f64 {
+        bytes as f64 / 1e6 / self.elapsed.as_secs_f64()
+    }
+
+    /// Latency at quantile `q` (between 0 and 1) of one operation type, or
+    /// `None` if no operation of that type completed.
+    pub fn latency_quantile(&self, op: Operation, q: f64) -> Option<Duration> {
+        self.per_op
+            .iter()
+            .find(|r| r.op == op)
+            .and_then(|r| r.histogram.quantile(q))
+    }
+
+    /// Throughput spread across `threads`, or `None` if there are none.
+    pub fn fairness(&self) -> Option<Fairness> {
+        if self.threads.is_empty() {
+            return None;
+        }
+
+        let throughput = self
+            .threads
+            .iter()
+            .map(ThreadResult::throughput)
+            .collect::<Vec<_>>();
+        let max = throughput.iter().cloned().fold(f64::MIN, f64::max);
+        let min = throughput.iter().cloned().fold(f64::MAX, f64::min);
+        let mean = throughput.iter().sum::<f64>() / throughput.len() as f64;
+        let variance = throughput
+            .iter()
+            .map(|t| (t - mean) * (t - mean))
+            .sum::<f64>()
+            / throughput.len() as f64;
+
+        Some(Fairness {
+            max_min_ratio: max / min,
+            cv: variance.sqrt() / mean,
+        })
+    }
+
+    /// Operations per second of one operation type over the whole phase.
+    pub fn op_throughput(&self, op: Operation) -> f64 {
+        self.per_op
+            .iter()
+            .find(|r| r.op == op)
+            .map_or(0.0, |r| r.operations as f64 / self.elapsed.as_secs_f64())
+    }
+}
+
+/// Outcome of reading back the loaded records after the run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct VerificationReport {
+    pub checked: usize,
+    /// Records that were not found or came back without any fields.
+    pub missing: usize,
+    /// Records whose field names differ from the ones that were loaded, or,
+    /// when the workload checks data integrity, whose values differ from the
+    /// ones written.
+    pub corrupted: usize,
+}
+
+impl VerificationReport {
+    pub(crate) fn merge(&mut self, other: VerificationReport) {
+        self.checked += other.checked;
+        self.missing += other.missing;
+        self.corrupted += other.corrupted;
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunResult {
+    pub load: Option<PhaseResult>,
+    pub run: Option<PhaseResult>,
+    /// Only set on the last phase, and only when verification was enabled.
+    pub verification: Option<VerificationReport>,
+    /// The workload that ran, as `CoreWorkload::effective_spec`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workload: Option<WorkloadSpec>,
+}
+
+impl RunResult {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self).map_err(io::Error::from)?)
+    }
+}
+
+/// Running latency statistics, updated with Welford's method so the variance
+/// stays accurate over long runs.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LatencyStats {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl LatencyStats {
+    pub(crate) fn record(&mut self, latency: Duration) {
+        let x = latency.as_secs_f64() * 1e6;
+
+        if self.count == 0 {
+            self.min = x;
+            self.max = x;
+        } else {
+            self.min = self.min.min(x);
+            self.max = self.max.max(x);
+        }
+
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub(crate) fn merge(&mut self, other: &LatencyStats) {
+        if other.count == 0 {
+            return;
+        }
+
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+
+        let (n_a, n_b) = (self.count as f64, other.count as f64);
+        let n = n_a + n_b;
+        let delta = other.mean - self.mean;
+
+        self.mean += delta * n_b / n;
+        self.m2 += other.m2 + delta * delta * n_a * n_b / n;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+    }
+
+    pub(crate) fn summary(&self) -> Option<LatencySummary> {
+        if self.count == 0 {
+            return None;
+        }
+
+        Some(LatencySummary {
+            count: self.count,
+            mean_us: self.mean,
+            min_us: self.min,
+            max_us: self.max,
+            stddev_us: (self.m2 / self.count as f64).sqrt(),
+        })
+    }
+}
+
+/// Sub-buckets per power of two, giving a relative error below 1/64.
+const HISTOGRAM_SUB_BITS: u32 = 6;
+const HISTOGRAM_SUB_BUCKETS: u64 = 1 << HISTOGRAM_SUB_BITS;
+
+/// Log-linear histogram of latencies in nanoseconds. Values below 128 ns are
+/// counted exactly; larger ones fall into one of 64 buckets per power of two.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    max: u64,
+}
+
+impl LatencyHistogram {
+    fn index(ns: u64) -> usize {
+        if ns < 2 * HISTOGRAM_SUB_BUCKETS {
+            return ns as usize;
+        }
+
+        let exp = 63 - ns.leading_zeros();
+        let sub = (ns >> (exp - HISTOGRAM_SUB_BITS)) & (HISTOGRAM_SUB_BUCKETS - 1);
+
+        ((exp - HISTOGRAM_SUB_BITS) as u64 * HISTOGRAM_SUB_BUCKETS + HISTOGRAM_SUB_BUCKETS + sub)
+            as usize
+    }
+
+    /// Largest value counted in bucket `index`.
+    fn upper_bound(index: usize) -> u64 {
+        let index = index as u64;
+
+        if index < 2 * HISTOGRAM_SUB_BUCKETS {
+            return index;
+        }
+
+        let exp =
+            (index - HISTOGRAM_SUB_BUCKETS) / HISTOGRAM_SUB_BUCKETS + HISTOGRAM_SUB_BITS as u64;
+        let sub = index % HISTOGRAM_SUB_BUCKETS;
+        let width = 1 << (exp - HISTOGRAM_SUB_BITS as u64);
+
+        ((HISTOGRAM_SUB_BUCKETS + sub) << (exp - HISTOGRAM_SUB_BITS as u64)) + (width - 1)
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration) {
+        let ns = latency.as_nanos().min(u64::MAX as u128) as u64;
+        let index = Self::index(ns);
+
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+
+        self.counts[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(ns);
+    }
+
+    pub(crate) fn merge(&mut self, other: &LatencyHistogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+
+        for (total, count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *total += count;
+        }
+
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Smallest latency that at least a fraction `q` of the samples do not
+    /// exceed, to within the bucket width. `None` if the histogram is empty.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+
+            if seen >= rank {
+                let ns = Self::upper_bound(index).min(self.max);
+                return Some(Duration::from_nanos(ns));
+            }
+        }
+
+        Some(Duration::from_nanos(self.max))
+    }
+}
+
+/// Buckets of the interval histogram, covering latencies up to 2^40 ns
+/// (about 18 minutes); longer ones land in the last bucket.
+const INTERVAL_BUCKETS: usize = 2 * HISTOGRAM_SUB_BUCKETS as usize
+    + (40 - HISTOGRAM_SUB_BITS as usize - 1) * HISTOGRAM_SUB_BUCKETS as usize;
+
+/// Latency histogram shared by all workers and emptied at every throughput
+/// sample, for the latency of the last interval.
+pub(crate) struct IntervalHistogram {
+    counts: Vec<AtomicU64>,
+}
+
+impl IntervalHistogram {
+    fn new() -> Self {
+        Self {
+            counts: (0..INTERVAL_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let ns = latency.as_nanos().min(u64::MAX as u128) as u64;
+        let index = LatencyHistogram::index(ns).min(INTERVAL_BUCKETS - 1);
+
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the samples recorded since the last call and starts over.
+    fn take(&self) -> LatencyHistogram {
+        let counts = self
+            .counts
+            .iter()
+            .map(|c| c.swap(0, Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let max = counts
+            .iter()
+            .rposition(|&c| c > 0)
+            .map_or(0, LatencyHistogram::upper_bound);
+
+        LatencyHistogram {
+            count: counts.iter().sum(),
+            counts,
+            max,
+        }
+    }
+}
+
+/// Counters kept by each worker and merged after the workers are joined.
+#[derive(Clone, Default)]
+pub(crate) struct ThreadStats {
+    completed: [usize; Operation::ALL.len()],
+    latency: [LatencyStats; Operation::ALL.len()],
+    histograms: [LatencyHistogram; Operation::ALL.len()],
+    bytes_read: [u64; Operation::ALL.len()],
+    bytes_written: [u64; Operation::ALL.len()],
+    errors: HashMap<(Operation, &'static str), u64>,
+    read_misses: usize,
+    read_miss_latency: LatencyStats,
+    read_miss_histogram: LatencyHistogram,
+    /// By operation type and `Stage`.
+    stages: [[(LatencyStats, LatencyHistogram); Stage::ALL.len()]; Operation::ALL.len()],
+    verification_errors: u64,
+    ramp_ops: usize,
+    busy: Duration,
+    elapsed: Duration,
+}
+
+impl ThreadStats {
+    pub(crate) fn record_completed(&mut self, op: Operation, latency: Duration) {
+        self.completed[op as usize] += 1;
+        self.latency[op as usize].record(latency);
+        self.histograms[op as usize].record(latency);
+    }
+
+    pub(crate) fn record_bytes(&mut self, op: Operation, read: u64, written: u64) {
+        self.bytes_read[op as usize] += read;
+        self.bytes_written[op as usize] += written;
+    }
+
+    /// Marks a read recorded with `record_completed` as a read miss.
+    pub(crate) fn record_read_miss(&mut self, latency: Duration) {
+        self.read_misses += 1;
+        self.read_miss_latency.record(latency);
+        self.read_miss_histogram.record(latency);
+    }
+
+    pub(crate) fn record_stage(&mut self, op: Operation, stage: Stage, latency: Duration) {
+        let (stats, histogram) = &mut self.stages[op as usize][stage as usize];
+        stats.record(latency);
+        histogram.record(latency);
+    }
+
+    /// Counts an operation that started outside the measured window.
+    pub(crate) fn record_ramp_op(&mut self) {
+        self.ramp_ops += 1;
+    }
+
+    pub(crate) fn record_verification_errors(&mut self, count: u64) {
+        self.verification_errors += count;
+    }
+
+    /// Adds the duration of one attempt, successful or not.
+    pub(crate) fn record_busy(&mut self, attempt: Duration) {
+        self.busy += attempt;
+    }
+
+    /// Sets the worker's wall time.
+    pub(crate) fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub(crate) fn thread_result(&self) -> ThreadResult {
+        ThreadResult {
+            operations: self.completed.iter().sum(),
+            elapsed: self.elapsed,
+            busy: self.busy,
+        }
+    }
+
+    pub(crate) fn record_error(&mut self, op: Operation, err: &Error) {
+        *self.errors.entry((op, err.kind())).or_insert(0) += 1;
+    }
+
+    pub(crate) fn merge(&mut self, other: ThreadStats) {
+        for (total, count) in self.completed.iter_mut().zip(other.completed.iter()) {
+            *total += count;
+        }
+
+        for (total, stats) in self.latency.iter_mut().zip(other.latency.iter()) {
+            total.merge(stats);
+        }
+
+        for (total, histogram) in self.histograms.iter_mut().zip(other.histograms.iter()) {
+            total.merge(histogram);
+        }
+
+        for (total, bytes) in self.bytes_read.iter_mut().zip(other.bytes_read.iter()) {
+            *total += bytes;
+        }
+
+        for (total, bytes) in self
+            .bytes_written
+            .iter_mut()
+            .zip(other.bytes_written.iter())
+        {
+            *total += bytes;
+        }
+
+        for (key, count) in other.errors {
+            *self.errors.entry(key).or_insert(0) += count;
+        }
+
+        self.read_misses += other.read_misses;
+        self.read_miss_latency.merge(&other.read_miss_latency);
+        self.read_miss_histogram.merge(&other.read_miss_histogram);
+
+        for (total, stages) in self.stages.iter_mut().zip(other.stages.iter()) {
+            for ((stats, histogram), (s, h)) in total.iter_mut().zip(stages.iter()) {
+                stats.merge(s);
+                histogram.merge(h);
+            }
+        }
+
+        self.verification_errors += other.verification_errors;
+        self.ramp_ops += other.ramp_ops;
+        self.busy += other.busy;
+        self.elapsed = self.elapsed.max(other.elapsed);
+    }
+
+    pub(crate) fn into_result(self, elapsed: Duration) -> PhaseResult {
+        let completed = self.completed;
+        let latency = self.latency;
+        let histograms = self.histograms;
+        let bytes_read = self.bytes_read;
+        let bytes_written = self.bytes_written;
+        let (read_misses, read_miss_histogram) = (self.read_misses, self.read_miss_histogram);
+        let stages = self.stages;
+        let stage = |op: Operation, stage: Stage| {
+            let (stats, histogram) = &stages[op as usize][stage as usize];
+
+            stats.summary().map(|latency| StageLatency {
+                latency,
+                histogram: histogram.clone(),
+            })
+        };
+        let mut errors = self
+            .errors
+            .into_iter()
+            .map(|((op, kind), count)| ErrorCount { op, kind, count })
+            .collect::<Vec<_>>();
+        errors.sort_by_key(|e| (e.op, e.kind));
+
+        let per_op = Operation::ALL
+            .iter()
+            .filter(|&&op| completed[op as usize] > 0)
+            .map(|&op| OpResult {
+                op,
+                operations: completed[op as usize],
+                latency: latency[op as usize].summary().unwrap(),
+                bytes_read: bytes_read[op as usize],
+                bytes_written: bytes_written[op as usize],
+                histogram: histograms[op as usize].clone(),
+                execute: stage(op, Stage::Execute),
+                commit: stage(op, Stage::Commit),
+                wasted: stage(op, Stage::Wasted),
+            })
+            .collect();
+
+        let mut overall = LatencyStats::default();
+        for stats in &latency {
+            overall.merge(stats);
+        }
+
+        let mut result = PhaseResult::new(completed.iter().sum(), elapsed);
+        result.latency = overall.summary();
+        result.bytes_read = bytes_read.iter().sum();
+        result.bytes_written = bytes_written.iter().sum();
+        result.verification_errors = self.verification_errors;
+        result.ramp_ops = self.ramp_ops;
+        result.per_op = per_op;
+        result.read_misses = self.read_miss_latency.summary().map(|latency| OpResult {
+            op: Operation::Read,
+            operations: read_misses,
+            latency,
+            bytes_read: 0,
+            bytes_written: 0,
+            histogram: read_miss_histogram,
+            execute: None,
+            commit: None,
+            wasted: None,
+        });
+        result.failed = errors.iter().map(|e| e.count as usize).sum();
+        result.errors = errors;
+        result
+    }
+}
+
+/// State shared by all workers of a phase while it is being measured.
+pub(crate) struct Measurements {
+    start: Instant,
+    completed: AtomicU64,
+    interval_latency: IntervalHistogram,
+    raw_latency: Option<RawLatencyLog>,
+    ramp: Option<Ramp>,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// Bounds of the measured window of a phase with a ramp-up.
+struct Ramp {
+    /// End of the ramp-up.
+    measure_from: Instant,
+    /// Start of the ramp-down, once the first worker has run out of
+    /// operations. Set once, so workers read it without taking a lock.
+    measure_until: OnceLock<Instant>,
+}
+
+impl Measurements {
+    pub(crate) fn new(raw_latency_output: Option<&Path>) -> Result<Self> {
+        let start = Instant::now();
+        let raw_latency = match raw_latency_output {
+            Some(path) => Some(RawLatencyLog::create(path, start)?),
+            None => None,
+        };
+
+        Ok(Self {
+            start,
+            completed: AtomicU64::new(0),
+            interval_latency: IntervalHistogram::new(),
+            raw_latency,
+            ramp: None,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+        })
+    }
+
+    /// Leaves out of the measured window the first `ramp_up` of the phase,
+    /// and everything from the moment the first worker finishes.
+    pub(crate) fn with_ramp_up(mut self, ramp_up: Duration) -> Self {
+        self.ramp = Some(Ramp {
+            measure_from: self.start + ramp_up,
+            measure_until: OnceLock::new(),
+        });
+        self
+    }
+
+    fn measured_window(&self) -> Option<(Instant, Option<Instant>)> {
+        self.ramp
+            .as_ref()
+            .map(|ramp| (ramp.measure_from, ramp.measure_until.get().copied()))
+    }
+
+    /// Whether an operation started at `at` falls outside the measured
+    /// window.
+    pub(crate) fn in_ramp(&self, at: Instant) -> bool {
+        match self.measured_window() {
+            Some((from, until)) => at < from || until.is_some_and(|until| until <= at),
+            None => false,
+        }
+    }
+
+    /// Whether any of `from..to` falls outside the measured window.
+    pub(crate) fn overlaps_ramp(&self, from: Instant, to: Instant) -> bool {
+        match self.measured_window() {
+            Some((measure_from, until)) => {
+                from < measure_from || until.is_some_and(|until| until < to)
+            }
+            None => false,
+        }
+    }
+
+    /// Called by each worker once it has run all its operations. The first
+    /// call starts the ramp-down.
+    pub(crate) fn worker_finished(&self) {
+        if let Some(ramp) = &self.ramp {
+            ramp.measure_until.get_or_init(Instant::now);
+        }
+    }
+
+    /// Length of the part of `from..to` inside the measured window.
+    pub(crate) fn measured_time(&self, from: Instant, to: Instant) -> Duration {
+        match self.measured_window() {
+            Some((measure_from, until)) => until
+                .unwrap_or(to)
+                .min(to)
+                .saturating_duration_since(from.max(measure_from)),
+            None => to - from,
+        }
+    }
+
+    /// Indices of the intervals of `series` that overlap the ramp-up or
+    /// ramp-down.
+    pub(crate) fn ramp_intervals(&self, series: &[(Duration, u64)]) -> Vec<usize> {
+        let mut from = self.start;
+
+        series
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(interval, _))| {
+                let to = from + interval;
+                let ramp = self.overlaps_ramp(from, to);
+                from = to;
+
+                if ramp {
+                    Some(i)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Publishes this phase's progress to the Prometheus exporter.
+    #[cfg(feature = "prometheus")]
+    pub(crate) fn with_metrics(mut self, metrics: Option<Arc<Metrics>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Time measured so far, leaving out the ramp-up and ramp-down.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.measured_time(self.start, Instant::now())
+    }
+
+    pub(crate) fn raw_latency(&self) -> Option<&RawLatencyLog> {
+        self.raw_latency.as_ref()
+    }
+
+    pub(crate) fn add_completed(&self, count: u64) {
+        self.completed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    pub(crate) fn record_completed(&self, op: Operation, latency: Duration) {
+        self.add_completed(1);
+        self.interval_latency.record(latency);
+
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_completed(op, latency);
+        }
+    }
+
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    pub(crate) fn record_stage(&self, op: Operation, stage: Stage, latency: Duration) {
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_stage(op, stage, latency);
+        }
+    }
+
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    pub(crate) fn record_abort(&self, op: Operation) {
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_abort(op);
+        }
+    }
+
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    pub(crate) fn record_error(&self, op: Operation) {
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_error(op);
+        }
+    }
+
+    /// Snapshots the completed-operation counter every `interval` until
+    /// `stop` is signalled or disconnected, then takes one last sample.
+    /// `on_sample` is called with each interval's length, operation count and
+    /// 99th percentile latency, if any operation recorded its latency.
+    pub(crate) fn sample_throughput<F>(
+        &self,
+        interval: Duration,
+        stop: Receiver<()>,
+        on_sample: F,
+    ) -> ThroughputSeries
+    where
+        F: Fn(Duration, u64, Option<Duration>),
+    {
+        let mut series = Vec::new();
+        let mut last_time = self.start;
+        let mut last_count = 0;
+        let mut next_tick = self.start + interval;
+
+        loop {
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            let stopped = !matches!(stop.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+
+            let now = Instant::now();
+            let count = self.completed.load(Ordering::Relaxed);
+
+            #[cfg(feature = "prometheus")]
+            if let Some(metrics) = &self.metrics {
+                let secs = (now - last_time).as_secs_f64();
+
+                if secs > 0.0 {
+                    metrics.set_throughput((count - last_count) as f64 / secs);
+                }
+            }
+
+            let p99 = self.interval_latency.take().quantile(0.99);
+
+            on_sample(now - last_time, count - last_count, p99);
+            series.push((now - last_time, count - last_count));
+            last_time = now;
+            last_count = count;
+            next_tick += interval;
+
+            if stopped {
+                return series;
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        match self.raw_latency {
+            Some(raw_latency) => raw_latency.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// CSV file of raw per-operation latency samples, shared by all workers of a
+/// phase.
+///
+/// Each line is `timestamp_us,op,latency_us,success`, where `timestamp_us` is
+/// the start of the attempt relative to the start of the phase and `success`
+/// is 0 for attempts that were aborted or failed. Lines are about 20 bytes,
+/// so expect roughly 20 MB per million operations.
+pub(crate) struct RawLatencyLog {
+    start: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl RawLatencyLog {
+    pub(crate) fn create<P: AsRef<Path>>(path: P, start: Instant) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "timestamp_us,op,latency_us,success")?;
+
+        Ok(Self {
+            start,
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub(crate) fn buffer(&self) -> RawLatencyBuffer<'_> {
+        RawLatencyBuffer {
+            log: self,
+            samples: String::new(),
+            count: 0,
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        self.writer.into_inner().unwrap().flush()?;
+        Ok(())
+    }
+}
+
+/// Per-worker buffer that writes to the shared log in large chunks, so the
+/// hot path never takes the lock or issues a syscall.
+pub(crate) struct RawLatencyBuffer<'a> {
+    log: &'a RawLatencyLog,
+    samples: String,
+    count: usize,
+}
+
+impl<'a> RawLatencyBuffer<'a> {
+    pub(crate) fn record(
+        &mut self,
+        op: Operation,
+        started: Instant,
+        latency: Duration,
+        success: bool,
+    ) -> Result<()> {
+        let _ = writeln!(
+            self.samples,
+            "{},{},{},{}",
+            started
+                .saturating_duration_since(self.log.start)
+                .as_micros(),
+            op,
+            latency.as_micros(),
+            success as u8
+        );
+        self.count += 1;
+
+        if self.count >= RAW_LATENCY_CHUNK {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        self.log
+            .writer
+            .lock()
+            .unwrap()
+            .write_all(self.samples.as_bytes())?;
+        self.samples.clear();
+        self.count = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let samples = [120u64, 80, 100, 300, 95, 105];
+
+        let mut all = LatencyStats::default();
+        let mut a = LatencyStats::default();
+        let mut b = LatencyStats::default();
+
+        for (i, &us) in samples.iter().enumerate() {
+            let latency = Duration::from_micros(us);
+
+            all.record(latency);
+            if i % 2 == 0 {
+                a.record(latency);
+            } else {
+                b.record(latency);
+            }
+        }
+        a.merge(&b);
+
+        let all = all.summary().unwrap();
+        let merged = a.summary().unwrap();
+
+        assert_eq!(all.count, 6);
+        assert!((all.mean_us - 800.0 / 6.0).abs() < 1e-9);
+        assert_eq!(all.min_us, 80.0);
+        assert_eq!(all.max_us, 300.0);
+        assert!((merged.mean_us - all.mean_us).abs() < 1e-9);
+        assert!((merged.stddev_us - all.stddev_us).abs() < 1e-9);
+        assert!(LatencyStats::default().summary().is_none());
+    }
+
+    #[test]
+    fn test_fairness() {
+        let thread = |operations, secs| ThreadResult {
+            operations,
+            elapsed: Duration::from_secs(secs),
+            busy: Duration::from_secs(secs),
+        };
+
+        let mut result = PhaseResult::new(0, Duration::from_secs(1));
+        assert!(result.fairness().is_none());
+
+        result.threads = vec![thread(100, 1), thread(100, 2), thread(300, 3)];
+        let fairness = result.fairness().unwrap();
+
+        assert_eq!(fairness.max_min_ratio, 2.0);
+        assert!((fairness.cv - (5000.0f64 / 9.0).sqrt() / (250.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut a = LatencyHistogram::default();
+        let mut b = LatencyHistogram::default();
+
+        for us in 1..=1000u64 {
+            let latency = Duration::from_micros(us);
+
+            if us % 2 == 0 {
+                a.record(latency);
+            } else {
+                b.record(latency);
+            }
+        }
+        a.merge(&b);
+
+        let within = |q: f64, expected_us: f64| {
+            let us = a.quantile(q).unwrap().as_secs_f64() * 1e6;
+            assert!(
+                (us - expected_us).abs() / expected_us < 1.0 / 64.0,
+                "{}",
+                us
+            );
+        };
+
+        assert_eq!(a.count(), 1000);
+        within(0.5, 500.0);
+        within(0.99, 990.0);
+        assert_eq!(a.quantile(1.0), Some(Duration::from_micros(1000)));
+        within(0.0, 1.0);
+        assert!(LatencyHistogram::default().quantile(0.5).is_none());
+
+        let interval = IntervalHistogram::new();
+        for us in 1..=1000u64 {
+            interval.record(Duration::from_micros(us));
+        }
+        let taken = interval.take();
+
+        assert_eq!(taken.count(), 1000);
+        assert_eq!(taken.quantile(0.5), a.quantile(0.5));
+        assert!(interval.take().quantile(0.5).is_none());
+
+        for ns in 0..100_000 {
+            let index = LatencyHistogram::index(ns);
+            assert!(LatencyHistogram::upper_bound(index) >= ns);
+            assert!(index == 0 || LatencyHistogram::upper_bound(index - 1) < ns);
+        }
+    }
+}
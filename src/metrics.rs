@@ -0,0 +1,320 @@
+//! Prometheus metrics exposition, enabled by the `prometheus` feature.
+//!
+//! When `RunOptions::metrics_addr` is set, a small HTTP server answers every
+//! request on that address with the current metrics in the Prometheus text
+//! format:
+//!
+//! - `ycsb_operations_total{op}`: completed run-phase operations.
+//! - `ycsb_aborts_total{op}`: attempts retried after `Error::TransactionAborted`.
+//! - `ycsb_errors_total{op}`: operations that failed with any other error.
+//! - `ycsb_throughput_ops`: operations per second over the last sampling
+//!   interval, for the load and run phases alike.
+//! - `ycsb_latency_seconds{op}`: histogram of run-phase operation latency.
+//! - `ycsb_stage_seconds_total{op,stage}`: time of run-phase attempts spent
+//!   executing the operation, committing it, or wasted on aborted attempts,
+//!   with `RunOptions::latency_breakdown`.
+//!
+//! The server lives for the whole run and is shut down before the run
+//! returns, including when it fails.
+
+use crate::{measurement::Stage, Operation, Result};
+
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Upper bounds of the latency histogram buckets, in microseconds.
+const LATENCY_BUCKETS_US: [u64; 14] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000,
+];
+
+/// How often the server checks whether it should shut down.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default)]
+struct OpMetrics {
+    completed: AtomicU64,
+    aborts: AtomicU64,
+    errors: AtomicU64,
+    /// Per-bucket counts; the last slot holds samples above every bound.
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS_US.len() + 1],
+    latency_sum_us: AtomicU64,
+    /// Nanoseconds spent in each `Stage`.
+    stage_ns: [AtomicU64; Stage::ALL.len()],
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    ops: [OpMetrics; Operation::ALL.len()],
+    throughput: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn record_completed(&self, op: Operation, latency: Duration) {
+        let m = &self.ops[op as usize];
+        let us = latency.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(LATENCY_BUCKETS_US.len());
+
+        m.completed.fetch_add(1, Ordering::Relaxed);
+        m.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        m.latency_sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_stage(&self, op: Operation, stage: Stage, latency: Duration) {
+        self.ops[op as usize].stage_ns[stage as usize]
+            .fetch_add(latency.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_abort(&self, op: Operation) {
+        self.ops[op as usize].aborts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_error(&self, op: Operation) {
+        self.ops[op as usize].errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_throughput(&self, ops_per_sec: f64) {
+        self.throughput
+            .store(ops_per_sec.to_bits(), Ordering::Relaxed);
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_counter(
+            &mut out,
+            "ycsb_operations_total",
+            "Completed operations.",
+            &self.ops,
+            |m| &m.completed,
+        );
+        write_counter(
+            &mut out,
+            "ycsb_aborts_total",
+            "Attempts aborted and retried.",
+            &self.ops,
+            |m| &m.aborts,
+        );
+        write_counter(
+            &mut out,
+            "ycsb_errors_total",
+            "Operations that failed.",
+            &self.ops,
+            |m| &m.errors,
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP ycsb_throughput_ops Operations per second over the last interval."
+        );
+        let _ = writeln!(out, "# TYPE ycsb_throughput_ops gauge");
+        let _ = writeln!(
+            out,
+            "ycsb_throughput_ops {}",
+            f64::from_bits(self.throughput.load(Ordering::Relaxed))
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP ycsb_stage_seconds_total Time spent executing, committing and in aborted attempts."
+        );
+        let _ = writeln!(out, "# TYPE ycsb_stage_seconds_total counter");
+
+        for &op in &Operation::ALL {
+            for &stage in &Stage::ALL {
+                let _ = writeln!(
+                    out,
+                    "ycsb_stage_seconds_total{{op=\"{}\",stage=\"{}\"}} {}",
+                    op,
+                    stage.name(),
+                    self.ops[op as usize].stage_ns[stage as usize].load(Ordering::Relaxed) as f64
+                        / 1e9
+                );
+            }
+        }
+
+        let _ = writeln!(out, "# HELP ycsb_latency_seconds Operation latency.");
+        let _ = writeln!(out, "# TYPE ycsb_latency_seconds histogram");
+
+        for &op in &Operation::ALL {
+            let m = &self.ops[op as usize];
+            let mut cumulative = 0;
+
+            for (i, bucket) in m.latency_buckets.iter().enumerate() {
+                cumulative += bucket.load(Ordering::Relaxed);
+
+                let le = match LATENCY_BUCKETS_US.get(i) {
+                    Some(&bound) => (bound as f64 / 1e6).to_string(),
+                    None => "+Inf".to_owned(),
+                };
+
+                let _ = writeln!(
+                    out,
+                    "ycsb_latency_seconds_bucket{{op=\"{}\",le=\"{}\"}} {}",
+                    op, le, cumulative
+                );
+            }
+
+            let _ = writeln!(
+                out,
+                "ycsb_latency_seconds_sum{{op=\"{}\"}} {}",
+                op,
+                m.latency_sum_us.load(Ordering::Relaxed) as f64 / 1e6
+            );
+            let _ = writeln!(
+                out,
+                "ycsb_latency_seconds_count{{op=\"{}\"}} {}",
+                op, cumulative
+            );
+        }
+
+        out
+    }
+}
+
+fn write_counter<F>(out: &mut String, name: &str, help: &str, ops: &[OpMetrics], counter: F)
+where
+    F: Fn(&OpMetrics) -> &AtomicU64,
+{
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+
+    for &op in &Operation::ALL {
+        let _ = writeln!(
+            out,
+            "{}{{op=\"{}\"}} {}",
+            name,
+            op,
+            counter(&ops[op as usize]).load(Ordering::Relaxed)
+        );
+    }
+}
+
+/// HTTP server publishing `Metrics`. Dropping it stops the server thread.
+pub(crate) struct MetricsServer {
+    metrics: Arc<Metrics>,
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    pub(crate) fn start(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        let local_addr = listener.local_addr()?;
+        let metrics = Arc::new(Metrics::default());
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let metrics = metrics.clone();
+            let stop = stop.clone();
+
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let _ = serve(stream, &metrics);
+                        }
+                        Err(_) => thread::sleep(POLL_INTERVAL),
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            metrics,
+            local_addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub(crate) fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    // The request itself is irrelevant: every path gets the metrics.
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request);
+
+    let body = metrics.render();
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_server() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr();
+
+        server
+            .metrics()
+            .record_completed(Operation::Read, Duration::from_micros(120));
+        server.metrics().record_abort(Operation::Update);
+        server
+            .metrics()
+            .record_stage(Operation::Read, Stage::Commit, Duration::from_millis(2));
+        server.metrics().set_throughput(1500.0);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("ycsb_operations_total{op=\"READ\"} 1\n"));
+        assert!(response.contains("ycsb_aborts_total{op=\"UPDATE\"} 1\n"));
+        assert!(response.contains("ycsb_throughput_ops 1500\n"));
+        assert!(response.contains("ycsb_stage_seconds_total{op=\"READ\",stage=\"commit\"} 0.002\n"));
+        assert!(response.contains("ycsb_latency_seconds_bucket{op=\"READ\",le=\"0.0001\"} 0\n"));
+        assert!(response.contains("ycsb_latency_seconds_bucket{op=\"READ\",le=\"0.00025\"} 1\n"));
+        assert!(response.contains("ycsb_latency_seconds_count{op=\"READ\"} 1\n"));
+
+        drop(server);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
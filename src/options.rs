@@ -0,0 +1,478 @@
+#[cfg(feature = "prometheus")]
+use crate::metrics::Metrics;
+use crate::{Db, Error, Result, SlaSpec, WorkloadSpec};
+
+use std::{collections::HashMap, fmt, path::PathBuf, time::Duration};
+
+use indicatif::ProgressStyle;
+#[cfg(feature = "prometheus")]
+use std::{net::SocketAddr, sync::Arc};
+
+/// What the run phase does when an operation fails with an error other than
+/// `Error::TransactionAborted`, which is always retried.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop the benchmark and return the error.
+    #[default]
+    FailFast,
+    /// Abort the transaction, count the error and move on to the next
+    /// operation.
+    CountAndContinue,
+}
+
+/// Which parts of the benchmark to execute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Phase {
+    /// Load the records, then run the transactions.
+    #[default]
+    Both,
+    /// Only load the records. The fields of a `WorkloadSpec` that only
+    /// matter to the run phase are not checked; see `CoreWorkload::for_load`.
+    LoadOnly,
+    /// Only run the transactions, against records loaded by an earlier run.
+    RunOnly,
+}
+
+/// Setup or teardown step around a phase, given the database under test.
+pub type Hook<T> = Box<dyn FnOnce(&dyn Db<Transaction = T>) -> Result<()> + Send + Sync>;
+
+pub(crate) struct Hooks<T> {
+    pub(crate) before_load: Option<Hook<T>>,
+    pub(crate) after_load: Option<Hook<T>>,
+    pub(crate) before_run: Option<Hook<T>>,
+    pub(crate) after_run: Option<Hook<T>>,
+}
+
+impl<T> Hooks<T> {
+    #[cfg(feature = "async")]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.before_load.is_none()
+            && self.after_load.is_none()
+            && self.before_run.is_none()
+            && self.after_run.is_none()
+    }
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Self {
+            before_load: None,
+            after_load: None,
+            before_run: None,
+            after_run: None,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Hooks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("before_load", &self.before_load.is_some())
+            .field("after_load", &self.after_load.is_some())
+            .field("before_run", &self.before_run.is_some())
+            .field("after_run", &self.after_run.is_some())
+            .finish()
+    }
+}
+
+/// Settings of a benchmark run. `T` is the transaction type of the database
+/// under test, needed only by the setup and teardown hooks.
+#[derive(Debug)]
+pub struct RunOptions<T = ()> {
+    pub(crate) threads: usize,
+    pub(crate) load_batch_size: usize,
+    pub(crate) warmup_ops: usize,
+    pub(crate) cpu_set: Vec<usize>,
+    pub(crate) raw_latency_output: Option<PathBuf>,
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) verify: bool,
+    pub(crate) phase: Phase,
+    pub(crate) sla: Option<SlaSpec>,
+    pub(crate) fairness_threshold: f64,
+    pub(crate) arrival_rate: Option<f64>,
+    pub(crate) ramp_up: Option<Duration>,
+    pub(crate) thread_groups: Vec<(WorkloadSpec, usize)>,
+    pub(crate) overrides: Option<HashMap<String, String>>,
+    pub(crate) latency_breakdown: bool,
+    pub(crate) partitioned_load: bool,
+    pub(crate) load_checkpoint: Option<PathBuf>,
+    pub(crate) checkpoint_interval: usize,
+    pub(crate) key_frequency: Option<usize>,
+    pub(crate) verbose: bool,
+    pub(crate) load_progress_style: Option<ProgressStyle>,
+    pub(crate) run_progress_style: Option<ProgressStyle>,
+    #[cfg(feature = "prometheus")]
+    pub(crate) metrics_addr: Option<SocketAddr>,
+    /// Set by the runner while the exporter is up.
+    #[cfg(feature = "prometheus")]
+    pub(crate) metrics: Option<Arc<Metrics>>,
+    pub(crate) hooks: Hooks<T>,
+}
+
+impl<T> Default for RunOptions<T> {
+    fn default() -> Self {
+        RunOptions {
+            threads: 1,
+            load_batch_size: 32,
+            warmup_ops: 0,
+            cpu_set: Vec::new(),
+            raw_latency_output: None,
+            error_policy: ErrorPolicy::FailFast,
+            verify: false,
+            phase: Phase::Both,
+            sla: None,
+            fairness_threshold: 1.5,
+            arrival_rate: None,
+            ramp_up: None,
+            thread_groups: Vec::new(),
+            overrides: None,
+            latency_breakdown: true,
+            partitioned_load: false,
+            load_checkpoint: None,
+            checkpoint_interval: 100,
+            key_frequency: None,
+            verbose: false,
+            load_progress_style: None,
+            run_progress_style: None,
+            #[cfg(feature = "prometheus")]
+            metrics_addr: None,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+impl<T> RunOptions<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn threads(mut self, count: usize) -> Self {
+        self.threads = count;
+        self
+    }
+
+    /// Number of records inserted per load transaction.
+    pub fn load_batch_size(mut self, size: usize) -> Self {
+        self.load_batch_size = size;
+        self
+    }
+
+    /// Number of operations, taken out of the workload's `operation_count`,
+    /// that are executed before measurement starts.
+    pub fn warmup_ops(mut self, count: usize) -> Self {
+        self.warmup_ops = count;
+        self
+    }
+
+    /// Pins worker thread `i` to core `cpu_set[i % len]`. Only the load and
+    /// transaction workers are pinned; the calling thread, which waits for the
+    /// workers and reports progress, is left alone. Pinning requires the
+    /// `core_affinity` feature and silently does nothing without it or on
+    /// unsupported platforms.
+    pub fn cpu_set(mut self, cpus: Vec<usize>) -> Self {
+        self.cpu_set = cpus;
+        self
+    }
+
+    /// Writes every transaction attempt of the run phase to a CSV file at
+    /// `path` as `timestamp_us,op,latency_us,success`. Workers buffer samples
+    /// and write them in large chunks; expect roughly 20 MB per million
+    /// operations.
+    pub fn raw_latency_output<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.raw_latency_output = Some(path.into());
+        self
+    }
+
+    /// Restricts the run to loading or to running transactions. With
+    /// `Phase::RunOnly`, the workload's key space is set up as if its records
+    /// had just been loaded, so transactions and inserts target the same keys
+    /// as in a full run.
+    pub fn phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Fails the run with `Error::SlaViolated` if any phase's run misses the
+    /// throughput or latency limits of `sla`.
+    pub fn sla(mut self, sla: SlaSpec) -> Self {
+        self.sla = Some(sla);
+        self
+    }
+
+    /// Prints a warning after the run phase when the fastest worker thread's
+    /// throughput exceeds the slowest one's by more than `ratio`. Defaults
+    /// to 1.5.
+    pub fn fairness_threshold(mut self, ratio: f64) -> Self {
+        self.fairness_threshold = ratio;
+        self
+    }
+
+    /// Runs transactions open-loop: operations arrive as a Poisson process
+    /// at `ops_per_sec` in total, split evenly over the worker threads, and
+    /// each worker issues them at their arrival times instead of right after
+    /// the previous one completes. A worker that falls behind issues the late
+    /// operations back to back, and their latency is measured from the
+    /// arrival time, so it includes the time spent waiting.
+    pub fn arrival_rate(mut self, ops_per_sec: f64) -> Self {
+        self.arrival_rate = Some(ops_per_sec);
+        self
+    }
+
+    /// Starts the run phase's worker threads one after another, spread
+    /// evenly over `duration`, instead of all at once. Operations started
+    /// within `duration` of the start of the phase, or after the first worker
+    /// has run out of operations and the others are stopping in turn, are
+    /// only counted in `PhaseResult::ramp_ops` and left out of the other
+    /// statistics, and so is the time they took.
+    pub fn ramp_up(mut self, duration: Duration) -> Self {
+        self.ramp_up = Some(duration);
+        self
+    }
+
+    /// Splits the run phase's worker threads into groups, each running the
+    /// operation mix and `operation_count` of its own spec on its own number
+    /// of threads, e.g. a pool of readers next to a pool of writers. The
+    /// groups share the key space of the workload being run, so each sees
+    /// the others' inserts, and their specs must agree with it on tables, key
+    /// prefix, record count and insert start. The load runs on as many
+    /// threads as the groups together, replacing `threads`.
+    ///
+    /// Results are reported for all groups together and for each one in
+    /// `PhaseResult::groups`. Only runs of a single workload phase can be
+    /// split, and hot keys cannot be counted across groups.
+    pub fn thread_groups(mut self, groups: Vec<(WorkloadSpec, usize)>) -> Self {
+        self.thread_groups = groups;
+        self
+    }
+
+    /// Splits the time of the run phase's transactions into executing the
+    /// operation, committing it and aborted attempts, reported per operation
+    /// type in `OpResult::execute`, `commit` and `wasted`. Defaults to true;
+    /// turning it off saves a clock read per attempt.
+    pub fn latency_breakdown(mut self, val: bool) -> Self {
+        self.latency_breakdown = val;
+        self
+    }
+
+    /// Gives each load thread its own contiguous range of key numbers instead
+    /// of drawing from the shared load counter. The ranges cover exactly
+    /// `record_count` keys; without partitioning, the count is rounded down to
+    /// a multiple of the thread count.
+    pub fn partitioned_load(mut self, val: bool) -> Self {
+        self.partitioned_load = val;
+        self
+    }
+
+    /// Saves the load's progress to `path` as it goes, and resumes from it
+    /// when the file exists, so that a load cut short by a crash doesn't
+    /// start over. The load is partitioned, as with `partitioned_load`, and
+    /// each thread's position only moves past a batch once it has committed,
+    /// so a resumed load may write a batch again but never skips one. A
+    /// checkpoint is only resumed by a load of the same workload, record
+    /// count and thread count; once the load completes, loading again with
+    /// it writes nothing.
+    pub fn load_checkpoint<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.load_checkpoint = Some(path.into());
+        self
+    }
+
+    /// Committed load batches after which a thread saves the checkpoint set
+    /// with `load_checkpoint`. Defaults to 100.
+    pub fn checkpoint_interval(mut self, batches: usize) -> Self {
+        self.checkpoint_interval = batches;
+        self
+    }
+
+    /// Counts how often each key is drawn in the run phase and reports the
+    /// `top_n` hottest ones in `PhaseResult::hot_keys`. Counting takes memory
+    /// for a few times `top_n` keys per worker thread, whatever the record
+    /// count, and the counts of keys that were not much hotter than the rest
+    /// may be overestimated.
+    pub fn key_frequency(mut self, top_n: usize) -> Self {
+        self.key_frequency = Some(top_n);
+        self
+    }
+
+    /// Replaces fields of every workload phase given as a `WorkloadSpec`, as
+    /// `WorkloadSpec::apply_overrides` does. Phases given as a pre-built
+    /// `CoreWorkload` are rejected.
+    pub fn overrides(mut self, overrides: HashMap<String, String>) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// Prints `CoreWorkload::describe` of each workload phase before it
+    /// starts.
+    pub fn verbose(mut self, val: bool) -> Self {
+        self.verbose = val;
+        self
+    }
+
+    /// Style of the load phase's progress bar. The bar's prefix is the phase
+    /// name and its message the throughput of the last second, followed in
+    /// the run phase by that second's 99th percentile latency, available as
+    /// `{prefix}` and `{msg}` in the template.
+    pub fn load_progress_style(mut self, style: ProgressStyle) -> Self {
+        self.load_progress_style = Some(style);
+        self
+    }
+
+    /// Style of the run phase's progress bar. See `load_progress_style`.
+    pub fn run_progress_style(mut self, style: ProgressStyle) -> Self {
+        self.run_progress_style = Some(style);
+        self
+    }
+
+    /// Sets how errors in the run phase are handled. The load phase always
+    /// fails fast, since a missing record would skew every later phase.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// After the last phase, reads back every record written by the load
+    /// phase and reports the ones that are missing or have the wrong fields.
+    /// Field values are only compared when the workload checks data
+    /// integrity; otherwise they are random and only field names are.
+    pub fn verify(mut self, val: bool) -> Self {
+        self.verify = val;
+        self
+    }
+
+    /// Runs `hook` right before the load phase. Hooks run on the calling
+    /// thread, outside the measured phases, and an error from any of them
+    /// stops the run and is returned.
+    pub fn before_load<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&dyn Db<Transaction = T>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.before_load = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` once the load phase has finished, e.g. to wait for
+    /// compaction to settle. The run phase does not start if it fails.
+    pub fn after_load<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&dyn Db<Transaction = T>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.after_load = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` before the first run phase, ahead of any warmup.
+    pub fn before_run<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&dyn Db<Transaction = T>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.before_run = Some(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` after the last run phase, before verification.
+    pub fn after_run<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&dyn Db<Transaction = T>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.after_run = Some(Box::new(hook));
+        self
+    }
+
+    /// Serves Prometheus metrics on `addr` for the duration of the run:
+    /// completed operations, aborts and errors per operation type, the
+    /// current throughput and a latency histogram. Requires the `prometheus`
+    /// feature.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(addr);
+        self
+    }
+
+    /// Checks the options against a workload running `op_count` operations.
+    pub fn validate(&self, op_count: usize) -> Result<()> {
+        if self.threads == 0 {
+            return Err(Error::InvalidArgument(
+                "thread count must be at least 1".to_owned(),
+            ));
+        }
+
+        if self.load_batch_size == 0 {
+            return Err(Error::InvalidArgument(
+                "load batch size must be at least 1".to_owned(),
+            ));
+        }
+
+        if self.warmup_ops > op_count {
+            return Err(Error::InvalidArgument(format!(
+                "warmup of {} operations exceeds the operation count of {}",
+                self.warmup_ops, op_count
+            )));
+        }
+
+        if self.checkpoint_interval == 0 {
+            return Err(Error::InvalidArgument(
+                "checkpoint interval must be at least 1 batch".to_owned(),
+            ));
+        }
+
+        if self.fairness_threshold.is_nan() || self.fairness_threshold < 1.0 {
+            return Err(Error::InvalidArgument(
+                "fairness threshold must be at least 1".to_owned(),
+            ));
+        }
+
+        if let Some(rate) = self.arrival_rate {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(Error::InvalidArgument(format!(
+                    "arrival rate {} must be positive",
+                    rate
+                )));
+            }
+        }
+
+        if self.key_frequency == Some(0) {
+            return Err(Error::InvalidArgument(
+                "key frequency report must list at least 1 key".to_owned(),
+            ));
+        }
+
+        if let Some(sla) = &self.sla {
+            sla.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(RunOptions::<()>::new().threads(8).validate(0).is_ok());
+        assert!(RunOptions::<()>::new().threads(0).validate(100).is_err());
+        assert!(RunOptions::<()>::new()
+            .load_batch_size(0)
+            .validate(100)
+            .is_err());
+        assert!(RunOptions::<()>::new()
+            .warmup_ops(100)
+            .validate(100)
+            .is_ok());
+        assert!(RunOptions::<()>::new()
+            .warmup_ops(101)
+            .validate(100)
+            .is_err());
+        assert!(RunOptions::<()>::new()
+            .fairness_threshold(0.5)
+            .validate(0)
+            .is_err());
+        assert!(RunOptions::<()>::new()
+            .key_frequency(0)
+            .validate(0)
+            .is_err());
+    }
+}
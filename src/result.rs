@@ -1,3 +1,5 @@
+use crate::RunResult;
+
 use std::{
     error::Error as StdError,
     fmt::{self, Display},
@@ -10,8 +12,15 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     InvalidArgument(String),
     UnknownSpecFormat,
+    /// A workload file that could not be parsed, with the parser's message.
+    InvalidSpec(String),
     TransactionAborted,
     Io(io::Error),
+    /// The run finished but missed its `SlaSpec`.
+    SlaViolated {
+        violations: Vec<String>,
+        results: Vec<RunResult>,
+    },
 }
 
 impl From<io::Error> for Error {
@@ -20,6 +29,22 @@ impl From<io::Error> for Error {
     }
 }
 
+impl Error {
+    /// Name of the error variant, used to group errors in reports.
+    pub fn kind(&self) -> &'static str {
+        use self::Error::*;
+
+        match *self {
+            InvalidArgument(_) => "InvalidArgument",
+            UnknownSpecFormat => "UnknownSpecFormat",
+            InvalidSpec(_) => "InvalidSpec",
+            TransactionAborted => "TransactionAborted",
+            Io(_) => "Io",
+            SlaViolated { .. } => "SlaViolated",
+        }
+    }
+}
+
 impl StdError for Error {}
 
 impl Display for Error {
@@ -29,8 +54,12 @@ impl Display for Error {
         match *self {
             InvalidArgument(ref e) => write!(f, "Invalid argument: {}", e),
             UnknownSpecFormat => write!(f, "Unknown workload spec format"),
+            InvalidSpec(ref e) => write!(f, "Invalid workload spec: {}", e),
             TransactionAborted => write!(f, "Transaction aborted"),
             Io(ref e) => write!(f, "IO error: {}", e),
+            SlaViolated { ref violations, .. } => {
+                write!(f, "SLA violated: {}", violations.join("; "))
+            }
         }
     }
 }
@@ -0,0 +1,150 @@
+//! Service level assertions, for gating CI on benchmark results.
+//!
+//! An `SlaSpec` set with `RunOptions::sla` is checked against the run phase
+//! of every workload phase once the whole run has finished. If anything is
+//! violated, the run returns `Error::SlaViolated` with every violation and the
+//! full results:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use ycsb_rs::{run_ycsb_with, Error, NullDb, Operation, RunOptions, SlaSpec, WorkloadSpec};
+//!
+//! let sla = SlaSpec::new()
+//!     .min_throughput(50_000.0)
+//!     .max_latency(Operation::Read, 0.99, Duration::from_millis(5));
+//! let options = RunOptions::new().threads(4).sla(sla);
+//!
+//! match run_ycsb_with(&NullDb::new(), WorkloadSpec::workload_a(), options) {
+//!     Ok(result) => println!("{}", result.to_json().unwrap()),
+//!     Err(Error::SlaViolated { violations, .. }) => {
+//!         for violation in violations {
+//!             eprintln!("{}", violation);
+//!         }
+//!         std::process::exit(1);
+//!     }
+//!     Err(e) => panic!("{}", e),
+//! }
+//! ```
+
+use crate::{Error, Operation, PhaseResult, Result};
+
+use std::time::Duration;
+
+/// Throughput and latency limits that a run phase must meet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlaSpec {
+    /// Minimum operations per second over the whole run phase.
+    pub min_throughput: Option<f64>,
+    /// Maximum latency at a quantile between 0 and 1 for an operation type.
+    /// Operation types that did not run are not checked.
+    pub max_latency: Vec<(Operation, f64, Duration)>,
+}
+
+impl SlaSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_throughput(mut self, ops_per_sec: f64) -> Self {
+        self.min_throughput = Some(ops_per_sec);
+        self
+    }
+
+    /// Adds a limit on the latency of `op` at quantile `q`, e.g. 0.99 for
+    /// the 99th percentile.
+    pub fn max_latency(mut self, op: Operation, q: f64, limit: Duration) -> Self {
+        self.max_latency.push((op, q, limit));
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        for &(op, q, _) in &self.max_latency {
+            if !(q > 0.0 && q <= 1.0) {
+                return Err(Error::InvalidArgument(format!(
+                    "SLA quantile {} for {} must be in (0, 1]",
+                    q, op
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Describes every limit that `run` violates.
+    pub fn check(&self, run: &PhaseResult) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if let Some(min) = self.min_throughput {
+            let throughput = run.throughput();
+
+            if throughput < min {
+                violations.push(format!(
+                    "throughput {:.2} ops/s is below {:.2} ops/s",
+                    throughput, min
+                ));
+            }
+        }
+
+        for &(op, q, limit) in &self.max_latency {
+            if let Some(latency) = run.latency_quantile(op, q) {
+                if latency > limit {
+                    violations.push(format!(
+                        "{} p{} latency {:?} exceeds {:?}",
+                        op,
+                        q * 100.0,
+                        latency,
+                        limit
+                    ));
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_ycsb_with, MockDb, RunOptions, WorkloadSpec};
+
+    #[test]
+    fn test_sla() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .operation_count(1000)
+        };
+
+        let sla = SlaSpec::new().min_throughput(1.0).max_latency(
+            Operation::Read,
+            0.99,
+            Duration::from_secs(10),
+        );
+        let options = RunOptions::new().threads(2).sla(sla);
+        assert!(run_ycsb_with(&MockDb::new(true), spec(), options).is_ok());
+
+        let sla = SlaSpec::new()
+            .min_throughput(f64::INFINITY)
+            .max_latency(Operation::Read, 0.5, Duration::from_nanos(0))
+            .max_latency(Operation::Scan, 0.5, Duration::from_nanos(0));
+        let options = RunOptions::new().threads(2).sla(sla);
+
+        match run_ycsb_with(&MockDb::new(true), spec(), options) {
+            Err(Error::SlaViolated {
+                violations,
+                results,
+            }) => {
+                assert_eq!(violations.len(), 2, "{:?}", violations);
+                assert!(violations[0].starts_with("throughput"));
+                assert!(violations[1].starts_with("READ p50 latency"));
+                assert_eq!(results[0].run.as_ref().unwrap().operations, 1000);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let sla = SlaSpec::new().max_latency(Operation::Read, 99.0, Duration::from_millis(5));
+        let options = RunOptions::new().sla(sla);
+        assert!(run_ycsb_with(&MockDb::new(true), spec(), options).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Goodness-of-fit checks for sampled values, e.g. to sanity-check the keys
+//! picked by a custom generator against the distribution they should follow:
+//!
+//! ```
+//! use rand::Rng;
+//! use ycsb_rs::stats;
+//!
+//! let mut rng = rand::thread_rng();
+//! let observed = stats::frequencies(10, 10_000, || rng.gen_range(0, 10));
+//! let fit = stats::chi_square(&observed, &stats::uniform_probabilities(10)).unwrap();
+//! assert!(fit.p_value > 1e-6);
+//! ```
+
+use crate::{Error, Result};
+
+/// Counts how often each of `0..bins` comes up in `draws` calls of `sample`.
+/// Panics if `sample` returns `bins` or more.
+pub fn frequencies<F: FnMut() -> usize>(bins: usize, draws: usize, mut sample: F) -> Vec<u64> {
+    let mut counts = vec![0; bins];
+
+    for _ in 0..draws {
+        let value = sample();
+        assert!(
+            value < bins,
+            "sampled {}, expected less than {}",
+            value,
+            bins
+        );
+        counts[value] += 1;
+    }
+
+    counts
+}
+
+/// Probabilities of `0..n` under a uniform distribution.
+pub fn uniform_probabilities(n: usize) -> Vec<f64> {
+    vec![1.0 / n as f64; n]
+}
+
+/// Probabilities of `0..n` under a zipfian distribution with exponent
+/// `theta`, 0 being the most popular.
+pub fn zipfian_probabilities(n: usize, theta: f64) -> Vec<f64> {
+    let weights = (1..=n)
+        .map(|rank| 1.0 / (rank as f64).powf(theta))
+        .collect::<Vec<_>>();
+    let zeta = weights.iter().sum::<f64>();
+
+    weights.into_iter().map(|w| w / zeta).collect()
+}
+
+/// Result of a chi-square goodness-of-fit test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: usize,
+    /// Probability of a statistic at least this large if the samples follow
+    /// the expected distribution. Values near 0 mean they don't.
+    pub p_value: f64,
+}
+
+/// Tests whether `observed` counts fit the `expected` probabilities of each
+/// bin. Bins expecting fewer than 5 samples, too few for the test, are
+/// pooled together.
+pub fn chi_square(observed: &[u64], expected: &[f64]) -> Result<ChiSquare> {
+    if observed.len() != expected.len() {
+        return Err(Error::InvalidArgument(format!(
+            "chi-square test of {} observed against {} expected bins",
+            observed.len(),
+            expected.len()
+        )));
+    }
+
+    if expected.iter().any(|p| !(*p >= 0.0 && p.is_finite())) {
+        return Err(Error::InvalidArgument(
+            "chi-square test with negative expected probabilities".to_owned(),
+        ));
+    }
+
+    let total = observed.iter().sum::<u64>() as f64;
+    let scale = total / expected.iter().sum::<f64>();
+
+    let mut statistic = 0.0;
+    let mut bins = 0;
+    let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+
+    for (&count, &p) in observed.iter().zip(expected) {
+        let (count, expected) = (count as f64, p * scale);
+
+        if expected < 5.0 {
+            pooled_observed += count;
+            pooled_expected += expected;
+        } else {
+            statistic += (count - expected).powi(2) / expected;
+            bins += 1;
+        }
+    }
+
+    if pooled_expected > 0.0 {
+        statistic += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+        bins += 1;
+    } else if pooled_observed > 0.0 {
+        statistic = f64::INFINITY;
+    }
+
+    if bins < 2 {
+        return Err(Error::InvalidArgument(
+            "chi-square test needs at least 2 bins expecting 5 samples or more".to_owned(),
+        ));
+    }
+
+    let degrees_of_freedom = bins - 1;
+
+    Ok(ChiSquare {
+        statistic,
+        degrees_of_freedom,
+        p_value: upper_regularized_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0),
+    })
+}
+
+/// Q(a, x) = Γ(a, x) / Γ(a), by its series below a + 1 and its continued
+/// fraction above (Numerical Recipes, 6.2).
+fn upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    const EPS: f64 = 1e-15;
+    const MAX_ITER: usize = 10_000;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x.is_infinite() {
+        return 0.0;
+    }
+
+    let log_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..MAX_ITER {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * EPS {
+                break;
+            }
+        }
+
+        (1.0 - sum * log_prefix.exp()).max(0.0)
+    } else {
+        // Modified Lentz's method.
+        let tiny = f64::MIN_POSITIVE / EPS;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+
+        for i in 1..MAX_ITER {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPS {
+                break;
+            }
+        }
+
+        (log_prefix.exp() * h).min(1.0)
+    }
+}
+
+/// ln Γ(x) for x > 0, by the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_p_values() {
+        // Critical values at the 5% and 1% levels.
+        for &(df, x, p) in &[
+            (1, 3.841, 0.05),
+            (2, 5.991, 0.05),
+            (10, 18.307, 0.05),
+            (10, 23.209, 0.01),
+            (100, 124.342, 0.05),
+        ] {
+            let q = upper_regularized_gamma(df as f64 / 2.0, x / 2.0);
+            assert!((q - p).abs() < 1e-4, "df {} x {}: {}", df, x, q);
+        }
+
+        // With 2 degrees of freedom, Q = exp(-x / 2).
+        for &x in &[0.1, 1.0, 2.5, 10.0, 50.0] {
+            let q = upper_regularized_gamma(1.0, x / 2.0);
+            assert!((q - (-x / 2.0).exp()).abs() < 1e-12, "x {}: {}", x, q);
+        }
+
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_chi_square() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let uniform = uniform_probabilities(20);
+
+        let observed = frequencies(20, 100_000, || rng.gen_range(0, 20));
+        let fit = chi_square(&observed, &uniform).unwrap();
+        assert_eq!(fit.degrees_of_freedom, 19);
+        assert!(fit.p_value > 0.001, "{:?}", fit);
+
+        let observed = frequencies(20, 100_000, || rng.gen_range(0, 19));
+        assert!(chi_square(&observed, &uniform).unwrap().p_value < 1e-9);
+
+        // The tail expects too few samples and is pooled.
+        let zipfian = zipfian_probabilities(1000, 0.99);
+        assert!((zipfian.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        let fit = chi_square(&frequencies(1000, 1000, || 0), &zipfian).unwrap();
+        assert!(fit.degrees_of_freedom < 50);
+        assert!(fit.p_value < 1e-9);
+
+        assert!(chi_square(&[1, 2], &[1.0]).is_err());
+        assert!(chi_square(&[10], &[1.0]).is_err());
+    }
+}
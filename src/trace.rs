@@ -0,0 +1,262 @@
+//! Operation trace export.
+//!
+//! `TraceDb` wraps another `Db` and appends every operation it is asked to
+//! perform to a writer before forwarding it, one JSON object per line:
+//!
+//! ```text
+//! {"op":"READ","table":"usertable","key":"user123","fields":["field3"]}
+//! {"op":"SCAN","table":"usertable","key":"user456","scan_length":42}
+//! {"op":"UPDATE","table":"usertable","key":"user789","fields":["field1"],"values":{"field1":"..."}}
+//! ```
+//!
+//! - `op` is one of `READ`, `INSERT`, `UPDATE`, `SCAN` or `DELETE`. A read-modify-write
+//!   shows up as a `READ` followed by an `UPDATE` of the same key.
+//! - `fields` is omitted when all fields were requested.
+//! - `scan_length` is only present for scans.
+//! - `values` is only present for writes, and only when the trace was created
+//!   with `include_values`.
+//!
+//! Records are written as the operations are issued, so an attempt that is
+//! retried after `Error::TransactionAborted` appears once per attempt. All
+//! threads share one writer behind a lock, so lines are never interleaved.
+
+use crate::{Db, Result};
+
+use std::{collections::BTreeMap, io, io::Write, sync::Mutex};
+
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct TraceRecord<'a> {
+    op: &'static str,
+    table: &'a str,
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<Vec<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+pub struct TraceDb<D> {
+    inner: D,
+    writer: Mutex<Box<dyn Write + Send>>,
+    include_values: bool,
+}
+
+impl<D: Db> TraceDb<D> {
+    pub fn new<W: Write + Send + 'static>(inner: D, writer: W, include_values: bool) -> Self {
+        Self {
+            inner,
+            writer: Mutex::new(Box::new(writer)),
+            include_values,
+        }
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.writer.lock().unwrap().flush()?;
+        Ok(())
+    }
+
+    fn record(&self, record: &TraceRecord) -> Result<()> {
+        let line = serde_json::to_string(record).map_err(io::Error::from)?;
+        let mut writer = self.writer.lock().unwrap();
+
+        writeln!(writer, "{}", line)?;
+        Ok(())
+    }
+
+    fn record_write(
+        &self,
+        op: &'static str,
+        table: &str,
+        key: &str,
+        values: &[(String, String)],
+    ) -> Result<()> {
+        self.record(&TraceRecord {
+            op,
+            table,
+            key,
+            scan_length: None,
+            fields: Some(values.iter().map(|(name, _)| name.as_str()).collect()),
+            values: if self.include_values {
+                Some(
+                    values
+                        .iter()
+                        .map(|(name, val)| (name.as_str(), val.as_str()))
+                        .collect(),
+                )
+            } else {
+                None
+            },
+        })
+    }
+}
+
+fn field_names(fields: &Option<Vec<String>>) -> Option<Vec<&str>> {
+    fields
+        .as_ref()
+        .map(|fields| fields.iter().map(String::as_str).collect())
+}
+
+impl<D: Db> Db for TraceDb<D> {
+    type Transaction = D::Transaction;
+
+    fn start_transaction(&self) -> Result<Self::Transaction> {
+        self.inner.start_transaction()
+    }
+
+    fn commit_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction> {
+        self.inner.commit_transaction(txn)
+    }
+
+    fn abort_transaction(&self, txn: Self::Transaction) -> Result<Self::Transaction> {
+        self.inner.abort_transaction(txn)
+    }
+
+    fn read(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: &str,
+        fields: Option<Vec<String>>,
+    ) -> Result<Option<Vec<(String, String)>>> {
+        self.record(&TraceRecord {
+            op: "READ",
+            table,
+            key,
+            scan_length: None,
+            fields: field_names(&fields),
+            values: None,
+        })?;
+
+        self.inner.read(txn, table, key, fields)
+    }
+
+    fn insert(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.record_write("INSERT", table, &key, &values)?;
+        self.inner.insert(txn, table, key, values)
+    }
+
+    fn update(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: String,
+        values: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.record_write("UPDATE", table, &key, &values)?;
+        self.inner.update(txn, table, key, values)
+    }
+
+    fn scan(
+        &self,
+        txn: &mut Self::Transaction,
+        table: &str,
+        key: &str,
+        length: usize,
+        fields: Option<Vec<String>>,
+    ) -> Result<Vec<Vec<(String, String)>>> {
+        self.record(&TraceRecord {
+            op: "SCAN",
+            table,
+            key,
+            scan_length: Some(length),
+            fields: field_names(&fields),
+            values: None,
+        })?;
+
+        self.inner.scan(txn, table, key, length, fields)
+    }
+
+    fn delete(&self, txn: &mut Self::Transaction, table: &str, key: &str) -> Result<()> {
+        self.record(&TraceRecord {
+            op: "DELETE",
+            table,
+            key,
+            scan_length: None,
+            fields: None,
+            values: None,
+        })?;
+
+        self.inner.delete(txn, table, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockDb;
+
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_records() {
+        let buf = SharedBuf::default();
+        let db = TraceDb::new(MockDb::new(true), buf.clone(), false);
+
+        db.read(&mut (), "t", "k1", Some(vec!["field0".to_owned()]))
+            .unwrap();
+        db.scan(&mut (), "t", "k2", 10, None).unwrap();
+        db.update(
+            &mut (),
+            "t",
+            "k3".to_owned(),
+            vec![("field1".to_owned(), "v".to_owned())],
+        )
+        .unwrap();
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"op":"READ","table":"t","key":"k1","fields":["field0"]}"#,
+                r#"{"op":"SCAN","table":"t","key":"k2","scan_length":10}"#,
+                r#"{"op":"UPDATE","table":"t","key":"k3","fields":["field1"]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_values() {
+        let buf = SharedBuf::default();
+        let db = TraceDb::new(MockDb::new(true), buf.clone(), true);
+
+        db.insert(
+            &mut (),
+            "t",
+            "k".to_owned(),
+            vec![("field0".to_owned(), "abc".to_owned())],
+        )
+        .unwrap();
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+
+        assert_eq!(
+            out.trim_end(),
+            r#"{"op":"INSERT","table":"t","key":"k","fields":["field0"],"values":{"field0":"abc"}}"#
+        );
+    }
+}
@@ -1,47 +1,509 @@
 use crate::{
-    generator::{self, Generator},
-    Error, Result,
+    generator::{self, Generator, WorkloadRng},
+    hot_keys::{KeyFrequency, SpaceSaving},
+    xxh64, Error, Result,
 };
 
-use std::sync::Arc;
-
-use fasthash::xx;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::{self, Display, Write as _},
+    fs,
+    io::{BufRead, BufReader, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use rand::{distributions::Alphanumeric, rngs::SmallRng, Rng, SeedableRng};
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const PRINTABLE: &[u8] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const HEX: &[u8] = b"0123456789abcdef";
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Draws per transaction key before a deleted key is used anyway.
+const MAX_DELETED_RESAMPLES: usize = 64;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A distribution in a workload file.
+///
+/// Written as a table tagged with the distribution's name, which reads well in
+/// TOML as well as JSON:
+///
+/// ```toml
+/// field_len_dist = { type = "constant", value = 100 }
+/// request_dist = { type = "zipfian", theta = 0.99 }
+/// scan_len_dist = { type = "uniform", min = 1, max = 1000 }
+/// ```
+///
+/// `zipfian` also takes an optional number of `items`, and `latest` and
+/// `sequential` take no parameters. A `uniform` range without a `max` is
+/// open-ended. A `histogram` lists weighted lengths:
+///
+/// ```toml
+/// [field_len_dist]
+/// type = "histogram"
+/// buckets = [{ length = 100, weight = 0.9 }, { length = 10000, weight = 0.1 }]
+/// ```
+///
+/// or reads them from a file when the workload is built:
+///
+/// ```toml
+/// field_len_dist = { type = "histogram_file", path = "hist.txt" }
+/// ```
+///
+/// A `pareto` gives heavy-tailed lengths of at least `scale`, a `normal`
+/// lengths around a `mean` and an `exponential` mostly short lengths with a
+/// given `mean`, all cut off at an optional `max` of 1 MiB by default:
+///
+/// ```toml
+/// field_len_dist = { type = "pareto", scale = 100, shape = 1.5 }
+/// scan_len_dist = { type = "normal", mean = 50, std_dev = 10, max = 100 }
+/// ```
+///
+/// A `file` requests keys listed in a file; see `DistributionSpec::File`:
+///
+/// ```toml
+/// request_dist = { type = "file", path = "keys.txt" }
+/// ```
+/// The older externally tagged form, e.g. `{"Zipfian": [0, 0.99]}`,
+/// is still accepted.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "TaggedDistribution")]
 pub enum DistributionSpec {
     Constant(usize),
     Uniform(usize, usize),
     Zipfian(usize, f64),
     Latest,
+    /// Every key in turn, wrapping around after the last one. Only valid as
+    /// a request distribution.
+    Sequential,
+    /// `(length, weight)` buckets, each picked with probability proportional
+    /// to its weight. Only valid as a field length distribution.
+    Histogram(Vec<(usize, f64)>),
+    /// A histogram read by `CoreWorkload::new` from a file in the format of
+    /// `DistributionSpec::histogram_from_path`.
+    HistogramFile(PathBuf),
+    /// Lengths of at least `scale`, more heavy-tailed the smaller `shape` is,
+    /// and at most `max`. Only valid as a field or scan length distribution.
+    Pareto {
+        scale: f64,
+        shape: f64,
+        max: usize,
+    },
+    /// Lengths around `mean`, rounded and kept within `1..=max`. Only valid
+    /// as a field or scan length distribution.
+    Normal {
+        mean: f64,
+        std_dev: f64,
+        max: usize,
+    },
+    /// Exponential lengths with the given `mean`, rounded up and kept within
+    /// `1..=max`. Only valid as a field length distribution.
+    Exponential {
+        mean: f64,
+        max: usize,
+    },
+    /// Keys read from a file with one key per line, optionally followed by
+    /// whitespace and a weight. The load phase inserts the keys of the file
+    /// in order, and transactions request them in proportion to their
+    /// weights. Keys inserted during the run get generated names and are not
+    /// requested. Only valid as a request distribution of a single table.
+    File(PathBuf),
+}
+
+/// Default cap on the lengths drawn from a pareto or normal distribution.
+const MAX_DRAWN_LENGTH: usize = 1 << 20;
+
+fn default_max_drawn_length() -> usize {
+    MAX_DRAWN_LENGTH
+}
+
+impl DistributionSpec {
+    /// Reads a histogram from a text file with one `length weight` pair per
+    /// line, separated by whitespace or a comma. Blank lines and lines
+    /// starting with `#` are skipped. Upstream YCSB's `fieldlengthhistogram`
+    /// files, which start with a `BlockSize` line and give lengths in blocks,
+    /// are read too.
+    /// Lines may come in any order.
+    pub fn histogram_from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        read_histogram(path.as_ref()).map(DistributionSpec::Histogram)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct TableWeight {
+    name: String,
+    weight: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistogramBucket {
+    length: usize,
+    weight: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TaggedDistribution {
+    Constant {
+        value: usize,
+    },
+    Uniform {
+        min: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<usize>,
+    },
+    Zipfian {
+        #[serde(default)]
+        items: usize,
+        theta: f64,
+    },
+    Latest,
+    Sequential,
+    Histogram {
+        buckets: Vec<HistogramBucket>,
+    },
+    Pareto {
+        scale: f64,
+        shape: f64,
+        #[serde(default = "default_max_drawn_length")]
+        max: usize,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+        #[serde(default = "default_max_drawn_length")]
+        max: usize,
+    },
+    Exponential {
+        mean: f64,
+        #[serde(default = "default_max_drawn_length")]
+        max: usize,
+    },
+    File {
+        path: PathBuf,
+    },
+    #[serde(rename = "histogram_file")]
+    HistogramFile {
+        path: PathBuf,
+    },
+}
+
+#[derive(Deserialize)]
+enum LegacyDistribution {
+    Constant(usize),
+    Uniform(usize, usize),
+    Zipfian(usize, f64),
+    Latest,
+    Sequential,
+    Histogram(Vec<(usize, f64)>),
+}
+
+impl<'de> Deserialize<'de> for DistributionSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        // Buffered to tell the two forms apart, so that errors come from the
+        // form that was meant rather than from neither.
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        match value.get("type").and_then(serde_json::Value::as_str) {
+            Some(kind) => TaggedDistribution::deserialize(&value)
+                .map(Into::into)
+                .map_err(|e| de::Error::custom(format!("{} distribution: {}", kind, e))),
+            None => LegacyDistribution::deserialize(&value)
+                .map(Into::into)
+                .map_err(|_| {
+                    de::Error::custom("expected a distribution with a `type`, e.g. zipfian")
+                }),
+        }
+    }
+}
+
+impl From<TaggedDistribution> for DistributionSpec {
+    fn from(dist: TaggedDistribution) -> Self {
+        use DistributionSpec::*;
+
+        match dist {
+            TaggedDistribution::Constant { value } => Constant(value),
+            TaggedDistribution::Uniform { min, max } => Uniform(min, max.unwrap_or(usize::MAX)),
+            TaggedDistribution::Zipfian { items, theta } => Zipfian(items, theta),
+            TaggedDistribution::Latest => Latest,
+            TaggedDistribution::Sequential => Sequential,
+            TaggedDistribution::Histogram { buckets } => {
+                Histogram(buckets.into_iter().map(|b| (b.length, b.weight)).collect())
+            }
+            TaggedDistribution::Pareto { scale, shape, max } => Pareto { scale, shape, max },
+            TaggedDistribution::Normal { mean, std_dev, max } => Normal { mean, std_dev, max },
+            TaggedDistribution::Exponential { mean, max } => Exponential { mean, max },
+            TaggedDistribution::File { path } => File(path),
+            TaggedDistribution::HistogramFile { path } => HistogramFile(path),
+        }
+    }
+}
+
+impl From<LegacyDistribution> for DistributionSpec {
+    fn from(dist: LegacyDistribution) -> Self {
+        use DistributionSpec::*;
+
+        match dist {
+            LegacyDistribution::Constant(value) => Constant(value),
+            LegacyDistribution::Uniform(min, max) => Uniform(min, max),
+            LegacyDistribution::Zipfian(items, theta) => Zipfian(items, theta),
+            LegacyDistribution::Latest => Latest,
+            LegacyDistribution::Sequential => Sequential,
+            LegacyDistribution::Histogram(buckets) => Histogram(buckets),
+        }
+    }
+}
+
+impl From<DistributionSpec> for TaggedDistribution {
+    fn from(dist: DistributionSpec) -> Self {
+        match dist {
+            DistributionSpec::Constant(value) => TaggedDistribution::Constant { value },
+            DistributionSpec::Uniform(min, max) => TaggedDistribution::Uniform {
+                min,
+                max: Some(max).filter(|&max| max != usize::MAX),
+            },
+            DistributionSpec::Zipfian(items, theta) => TaggedDistribution::Zipfian { items, theta },
+            DistributionSpec::Latest => TaggedDistribution::Latest,
+            DistributionSpec::Sequential => TaggedDistribution::Sequential,
+            DistributionSpec::Histogram(buckets) => TaggedDistribution::Histogram {
+                buckets: buckets
+                    .into_iter()
+                    .map(|(length, weight)| HistogramBucket { length, weight })
+                    .collect(),
+            },
+            DistributionSpec::Pareto { scale, shape, max } => {
+                TaggedDistribution::Pareto { scale, shape, max }
+            }
+            DistributionSpec::Normal { mean, std_dev, max } => {
+                TaggedDistribution::Normal { mean, std_dev, max }
+            }
+            DistributionSpec::Exponential { mean, max } => {
+                TaggedDistribution::Exponential { mean, max }
+            }
+            DistributionSpec::File(path) => TaggedDistribution::File { path },
+            DistributionSpec::HistogramFile(path) => TaggedDistribution::HistogramFile { path },
+        }
+    }
+}
+
+/// Characters of the random field values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    /// Letters and digits.
+    Alphanumeric,
+    /// Printable ASCII, including spaces.
+    Ascii,
+    /// Lowercase hexadecimal digits.
+    Hex,
+    /// Random bytes, base64-encoded. The field length is the number of bytes
+    /// before encoding.
+    Bytes,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Random number generators that workloads can draw from.
+///
+/// `ChaCha8` and `ChaCha20` give statistically stronger streams; see the
+/// `bench_build_values_*` and `bench_next_zipfian_key_*` benchmarks for the
+/// cost. On an x86-64 machine, the three drew zipfian keys within noise of
+/// each other (150-210 ns), and `ChaCha20` was the only one noticeably
+/// slower at building values, by up to about 1.6x.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RngKind {
+    #[serde(rename = "small")]
+    Small,
+    #[serde(rename = "chacha8")]
+    ChaCha8,
+    #[serde(rename = "chacha20")]
+    ChaCha20,
+}
+
+/// Functions scrambling unordered key numbers into the integers naming them.
+///
+/// `Fnv64` matches upstream YCSB, which names key `n` after the FNV-1a hash
+/// of its 8 little-endian bytes, made non-negative as a Java `long`. Use it
+/// with the default `key_prefix` to share datasets with upstream clients.
+/// `Xx64` matches datasets loaded by earlier versions of this crate, and
+/// `LegacyXx64` those loaded by the versions before them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyHashKind {
+    /// The SplitMix64 finalizer, a permutation of the `u64` values: no two
+    /// keys share a name.
+    #[serde(rename = "splitmix")]
+    SplitMix,
+    #[serde(rename = "fnv64")]
+    Fnv64,
+    /// The 64-bit wyhash mixing step (wyhash64).
+    #[serde(rename = "wyhash")]
+    Wyhash,
+    #[serde(rename = "xx64")]
+    Xx64,
+    /// xxHash of the platform's native `usize` bytes. Such key names differ
+    /// between 32- and 64-bit or big- and little-endian clients.
+    #[serde(rename = "legacy_xx64")]
+    LegacyXx64,
+}
+
+impl fmt::Display for KeyHashKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyHashKind::SplitMix => "splitmix",
+            KeyHashKind::Fnv64 => "fnv64",
+            KeyHashKind::Wyhash => "wyhash",
+            KeyHashKind::Xx64 => "xx64",
+            KeyHashKind::LegacyXx64 => "legacy_xx64",
+        })
+    }
+}
+
+/// Workload parameters, usually read from a workload file with `from_path`.
+/// Fields missing from a file take their default values.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WorkloadSpec {
     table: String,
+
+    /// Tables with their shares of the records and of the traffic, replacing
+    /// `table` when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tables: Option<Vec<TableWeight>>,
+
+    /// Records per table left by an earlier phase, replacing the split of
+    /// `record_count` by weight.
+    #[serde(skip)]
+    table_record_counts: Option<Vec<usize>>,
+
     field_count: usize,
 
+    /// Names of the fields, replacing `field0`, `field1`, ... and
+    /// `field_count` when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field_names: Option<Vec<String>>,
+
+    /// Name of an extra field holding an increasing timestamp, written by
+    /// every insert and update; see `CoreWorkload::build_values`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    include_timestamp_field: Option<String>,
+
     field_len_dist: DistributionSpec,
 
+    /// Length distributions of single fields, by their index, in place of
+    /// `field_len_dist`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    field_len_overrides: Vec<(usize, DistributionSpec)>,
+
+    value_kind: ValueKind,
+
+    /// Target compression ratio of field values. Values above 1 repeat a
+    /// random prefix to fill each value; 1 keeps them fully random.
+    value_compressibility: f64,
+
     read_all_fields: bool,
+    /// Updates, including those of read-modify-writes, write every field.
+    /// Otherwise they write `fields_per_write` random fields. Inserts and
+    /// loads always write whole records.
     write_all_fields: bool,
 
+    /// Distinct fields requested by reads and scans that don't read all
+    /// fields.
+    fields_per_read: usize,
+    /// Distinct fields written by updates that don't write all fields.
+    fields_per_write: usize,
+
+    /// Derive field values from the key and field name, so that values read
+    /// back can be checked. Needs a constant field length.
+    data_integrity: bool,
+
     ordered_insert: bool,
 
+    /// Prepended to every key number.
+    key_prefix: String,
+
+    /// Minimum number of digits in a key number, padded with zeros so that
+    /// keys sort in numeric order.
+    zero_padding: usize,
+
+    /// How unordered key numbers are scrambled into key names, `splitmix`
+    /// if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_hash: Option<KeyHashKind>,
+
+    /// Older workload files' way of saying `key_hash = "legacy_xx64"`, still
+    /// read from them. Cannot be combined with `key_hash`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    legacy_key_hash: bool,
+
     read_proportion: f64,
     update_proportion: f64,
     insert_proportion: f64,
     scan_proportion: f64,
     rmw_proportion: f64,
+    delete_proportion: f64,
+
+    /// Share of reads requesting a key that is never inserted, to measure
+    /// lookups that miss; see `CoreWorkload::next_missing_key`.
+    read_miss_proportion: f64,
+
+    /// Keep deleted keys out of later transactions.
+    skip_deleted_keys: bool,
 
+    /// Keys to request. Uniform ranges are clamped to the records loaded or
+    /// inserted so far, and a zipfian with `items` set only requests the
+    /// first `items` records.
     request_dist: DistributionSpec,
 
+    /// Hash zipfian ranks to keys as upstream YCSB does, spreading the hot
+    /// keys over the key space. Without it the hot keys are the smallest.
+    scramble_zipfian: bool,
+
     scan_len_dist: DistributionSpec,
 
+    /// Start keys of scans, e.g. `latest` to scan the most recent inserts.
+    /// Scans start at keys drawn like those of other transactions when
+    /// unset.
+    #[serde(alias = "scan_request_dist", skip_serializing_if = "Option::is_none")]
+    scan_start_dist: Option<DistributionSpec>,
+
+    /// Keys of reads, updates and read-modify-writes, each drawn from
+    /// `request_dist` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read_request_dist: Option<DistributionSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_request_dist: Option<DistributionSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rmw_request_dist: Option<DistributionSpec>,
+
+    /// Longest scan to request, capping `scan_len_dist`. With ordered
+    /// inserts, scans are also cut short at the last key inserted.
+    max_scan_length: usize,
+
+    /// Pause of each worker thread after every transaction, in microseconds.
+    /// It counts towards the run's wall-clock time, but not towards latency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    think_time_dist: Option<DistributionSpec>,
+
+    /// Seed for the random choices of each worker thread, for reproducible
+    /// runs. Drawn from entropy when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+
+    /// Random number generator of each worker thread.
+    rng: RngKind,
+
     insert_start: usize,
+    /// Records to load, from `insert_start` on, out of the `record_count`
+    /// that transactions request; see `WorkloadSpec::insert_count`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insert_count: Option<usize>,
     record_count: usize,
     operation_count: usize,
 }
@@ -50,26 +512,62 @@ impl Default for WorkloadSpec {
     fn default() -> Self {
         WorkloadSpec {
             table: "usertable".to_owned(),
+            tables: None,
+            table_record_counts: None,
             field_count: 10,
 
+            field_names: None,
+            include_timestamp_field: None,
+
             field_len_dist: DistributionSpec::Constant(100),
+            field_len_overrides: Vec::new(),
+
+            value_kind: ValueKind::Alphanumeric,
+
+            value_compressibility: 1.0,
 
             read_all_fields: true,
             write_all_fields: false,
+            fields_per_read: 1,
+            fields_per_write: 1,
+
+            data_integrity: false,
 
             ordered_insert: false,
 
+            key_prefix: "user".to_owned(),
+
+            zero_padding: 1,
+
+            key_hash: None,
+            legacy_key_hash: false,
+
             read_proportion: 0.95,
             update_proportion: 0.05,
             insert_proportion: 0.0,
             scan_proportion: 0.0,
             rmw_proportion: 0.0,
+            delete_proportion: 0.0,
+            read_miss_proportion: 0.0,
 
-            request_dist: DistributionSpec::Uniform(1, 1000),
+            skip_deleted_keys: false,
+
+            request_dist: DistributionSpec::Uniform(0, usize::MAX),
+            scramble_zipfian: true,
 
             scan_len_dist: DistributionSpec::Uniform(1, 1000),
+            scan_start_dist: None,
+            read_request_dist: None,
+            update_request_dist: None,
+            rmw_request_dist: None,
+            max_scan_length: 1000,
+            think_time_dist: None,
+
+            seed: None,
+            rng: RngKind::Small,
 
             insert_start: 0,
+            insert_count: None,
             record_count: 0,
             operation_count: 0,
         }
@@ -77,268 +575,5205 @@ impl Default for WorkloadSpec {
 }
 
 impl WorkloadSpec {
-    pub fn field_count(mut self, count: usize) -> Self {
-        self.field_count = count;
+    /// Names the single table holding the records. Ignored when `tables` is
+    /// set.
+    pub fn table(mut self, name: &str) -> Self {
+        self.table = name.to_owned();
         self
     }
 
-    pub fn field_len_const(mut self, len: usize) -> Self {
-        self.field_len_dist = DistributionSpec::Constant(len);
+    /// Spreads the records and operations over several tables, each getting
+    /// a share proportional to its weight.
+    pub fn tables(mut self, tables: Vec<(String, f64)>) -> Self {
+        self.tables = Some(
+            tables
+                .into_iter()
+                .map(|(name, weight)| TableWeight { name, weight })
+                .collect(),
+        );
         self
     }
 
-    pub fn read_all_fields(mut self, val: bool) -> Self {
-        self.read_all_fields = val;
+    pub fn max_scan_length(mut self, len: usize) -> Self {
+        self.max_scan_length = len;
         self
     }
 
-    pub fn write_all_fields(mut self, val: bool) -> Self {
-        self.write_all_fields = val;
+    /// Makes each worker thread pause for a time drawn from `dist`, in
+    /// microseconds, after every transaction, like a user between requests.
+    /// `dist` must be constant, uniform or exponential. Cannot be combined
+    /// with `RunOptions::arrival_rate`, which already sets when operations
+    /// are issued.
+    pub fn think_time_dist(mut self, dist: DistributionSpec) -> Self {
+        self.think_time_dist = Some(dist);
         self
     }
 
-    pub fn read_proportion(mut self, val: f64) -> Self {
-        self.read_proportion = val;
+    pub fn field_count(mut self, count: usize) -> Self {
+        self.field_count = count;
         self
     }
 
-    pub fn update_proportion(mut self, val: f64) -> Self {
-        self.update_proportion = val;
+    /// Uses `names` as the fields of every record, e.g. the columns of an
+    /// existing schema. The field count follows from the number of names.
+    pub fn field_names(mut self, names: Vec<String>) -> Self {
+        self.field_names = Some(names);
         self
     }
 
-    pub fn insert_proportion(mut self, val: f64) -> Self {
-        self.insert_proportion = val;
+    /// Adds a field `name` to every record, holding the time of its last
+    /// insert or update. It is never picked as a random field to read or
+    /// write.
+    pub fn include_timestamp_field(mut self, name: &str) -> Self {
+        self.include_timestamp_field = Some(name.to_owned());
         self
     }
 
-    pub fn scan_proportion(mut self, val: f64) -> Self {
-        self.scan_proportion = val;
+    pub fn field_len_const(mut self, len: usize) -> Self {
+        self.field_len_dist = DistributionSpec::Constant(len);
         self
     }
 
-    pub fn rmw_proportion(mut self, val: f64) -> Self {
-        self.rmw_proportion = val;
+    pub fn field_len_uniform(mut self, min: usize, max: usize) -> Self {
+        self.field_len_dist = DistributionSpec::Uniform(min, max);
         self
     }
 
-    pub fn request_zipfian(mut self, s: f64) -> Self {
-        self.request_dist = DistributionSpec::Zipfian(0, s);
+    pub fn scan_len_const(mut self, len: usize) -> Self {
+        self.scan_len_dist = DistributionSpec::Constant(len);
         self
     }
 
-    pub fn record_count(mut self, val: usize) -> Self {
-        self.record_count = val;
+    pub fn scan_len_uniform(mut self, min: usize, max: usize) -> Self {
+        self.scan_len_dist = DistributionSpec::Uniform(min, max);
         self
     }
 
-    pub fn get_record_count(&self) -> usize {
-        self.record_count
+    /// Draws the start keys of scans from `dist`, which must be uniform,
+    /// zipfian or latest, instead of the request distribution.
+    pub fn scan_start_dist(mut self, dist: DistributionSpec) -> Self {
+        self.scan_start_dist = Some(dist);
+        self
     }
 
-    pub fn operation_count(mut self, val: usize) -> Self {
-        self.operation_count = val;
+    /// Draws the keys of reads from `dist`, which must be uniform, zipfian
+    /// or latest, instead of the request distribution.
+    pub fn read_request_dist(mut self, dist: DistributionSpec) -> Self {
+        self.read_request_dist = Some(dist);
         self
     }
 
-    pub fn get_operation_count(&self) -> usize {
-        self.operation_count
+    /// Like `read_request_dist` for updates, e.g. uniform to spread writes
+    /// while reads stay zipfian.
+    pub fn update_request_dist(mut self, dist: DistributionSpec) -> Self {
+        self.update_request_dist = Some(dist);
+        self
     }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Operation {
-    Insert,
-    Read,
-    Update,
-    Scan,
-    ReadModifyWrite,
-}
 
-impl Default for Operation {
-    fn default() -> Self {
-        Self::Insert
+    /// Like `read_request_dist` for read-modify-writes.
+    pub fn rmw_request_dist(mut self, dist: DistributionSpec) -> Self {
+        self.rmw_request_dist = Some(dist);
+        self
     }
-}
-
-pub struct CoreWorkload {
-    field_len_generator: Box<dyn Generator<usize>>,
-    op_generator:
-        generator::DistributionGenerator<Operation, generator::DiscreteDistribution<Operation>>,
-    key_generator: generator::CounterGenerator,
-    key_sampler: Box<dyn Generator<usize>>,
-    field_generator: generator::DistributionGenerator<usize, rand::distributions::Uniform<usize>>,
-    scan_len_generator: Box<dyn Generator<usize>>,
-    insert_key_sequence: Arc<generator::CounterGenerator>,
-
-    field_count: usize,
-
-    table: String,
-
-    read_all_fields: bool,
-    write_all_fields: bool,
-
-    ordered_insert: bool,
-}
-
-impl CoreWorkload {
-    pub fn new(spec: WorkloadSpec) -> Result<Self> {
-        let field_len_generator: Box<dyn Generator<usize>> = match spec.field_len_dist {
-            DistributionSpec::Constant(c) => Box::new(generator::ConstGenerator::new(c)),
-            DistributionSpec::Uniform(min, max) => Box::new(generator::uniform_gen(min, max)),
-            _ => {
-                return Err(Error::InvalidArgument(
-                    "field length distribution".to_owned(),
-                ))
-            }
-        };
-
-        let mut ops = Vec::new();
-        if spec.read_proportion > 0.0 {
-            ops.push((Operation::Read, spec.read_proportion));
-        }
-        if spec.update_proportion > 0.0 {
-            ops.push((Operation::Update, spec.update_proportion));
-        }
-        if spec.insert_proportion > 0.0 {
-            ops.push((Operation::Insert, spec.insert_proportion));
-        }
-        if spec.scan_proportion > 0.0 {
-            ops.push((Operation::Scan, spec.scan_proportion));
-        }
-        if spec.rmw_proportion > 0.0 {
-            ops.push((Operation::ReadModifyWrite, spec.rmw_proportion));
-        }
-        let op_generator = generator::discrete_gen(ops);
-
-        let key_generator = generator::CounterGenerator::new(spec.insert_start as u64);
-
-        let insert_key_sequence =
-            Arc::new(generator::CounterGenerator::new(spec.record_count as u64));
-
-        let key_sampler: Box<dyn Generator<usize>> = match spec.request_dist {
-            DistributionSpec::Uniform(_, _) => {
-                Box::new(generator::uniform_gen(0, spec.record_count - 1))
-            }
-            DistributionSpec::Zipfian(_, s) => Box::new(generator::zipfian_gen(
-                spec.record_count
-                    + (spec.operation_count as f64 * spec.insert_proportion) as usize * 2,
-                s,
-            )?),
-            DistributionSpec::Latest => Box::new(generator::SkewedLatestGenerator::new(
-                insert_key_sequence.clone(),
-            )),
-            _ => {
-                return Err(Error::InvalidArgument(
-                    "field length distribution".to_owned(),
-                ))
-            }
-        };
-
-        let field_generator = generator::uniform_gen(0, spec.field_count - 1);
-
-        let scan_len_generator: Box<dyn Generator<usize>> = match spec.scan_len_dist {
-            DistributionSpec::Uniform(min, max) => Box::new(generator::uniform_gen(min, max)),
-            DistributionSpec::Zipfian(num_elements, s) => {
-                Box::new(generator::zipfian_gen(num_elements, s)?)
-            }
-            _ => {
-                return Err(Error::InvalidArgument(
-                    "scan length distribution".to_owned(),
-                ))
-            }
-        };
-
-        Ok(Self {
-            field_len_generator,
-            op_generator,
-            key_generator,
-            key_sampler,
-            field_generator,
-            scan_len_generator,
-            insert_key_sequence,
 
-            field_count: spec.field_count,
+    /// Draws scan lengths of 1 to `max` records, favoring short ones.
+    pub fn scan_len_zipfian(mut self, max: usize, s: f64) -> Self {
+        self.scan_len_dist = DistributionSpec::Zipfian(max, s);
+        self
+    }
 
-            table: spec.table,
+    /// Draws field lengths from `(length, weight)` buckets, e.g. to replay a
+    /// production value size distribution. See
+    /// `DistributionSpec::histogram_from_path` to read them from a file.
+    pub fn field_len_histogram(mut self, buckets: Vec<(usize, f64)>) -> Self {
+        self.field_len_dist = DistributionSpec::Histogram(buckets);
+        self
+    }
 
-            read_all_fields: spec.read_all_fields,
-            write_all_fields: spec.write_all_fields,
+    /// Like `field_len_histogram`, with the buckets read from a file by
+    /// `CoreWorkload::new`. See `DistributionSpec::histogram_from_path` for
+    /// the format.
+    pub fn field_len_histogram_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.field_len_dist = DistributionSpec::HistogramFile(path.into());
+        self
+    }
 
-            ordered_insert: spec.ordered_insert,
-        })
+    /// Draws heavy-tailed field lengths of `scale` to `max` bytes; see
+    /// `DistributionSpec::Pareto`.
+    pub fn field_len_pareto(mut self, scale: f64, shape: f64, max: usize) -> Self {
+        self.field_len_dist = DistributionSpec::Pareto { scale, shape, max };
+        self
     }
 
-    pub fn read_all_fields(&self) -> bool {
-        self.read_all_fields
+    /// Draws heavy-tailed scan lengths of `scale` to `max` records.
+    pub fn scan_len_pareto(mut self, scale: f64, shape: f64, max: usize) -> Self {
+        self.scan_len_dist = DistributionSpec::Pareto { scale, shape, max };
+        self
     }
 
-    pub fn write_all_fields(&self) -> bool {
-        self.write_all_fields
+    /// Draws field lengths around `mean` bytes, up to `max`; see
+    /// `DistributionSpec::Normal`.
+    pub fn field_len_normal(mut self, mean: f64, std_dev: f64, max: usize) -> Self {
+        self.field_len_dist = DistributionSpec::Normal { mean, std_dev, max };
+        self
     }
 
-    pub fn next_table(&self) -> String {
-        self.table.clone()
+    /// Draws mostly short field lengths averaging about `mean` bytes, up to
+    /// `max`; see `DistributionSpec::Exponential`.
+    pub fn field_len_exponential(mut self, mean: f64, max: usize) -> Self {
+        self.field_len_dist = DistributionSpec::Exponential { mean, max };
+        self
     }
 
-    pub fn next_operation(&self) -> Operation {
-        self.op_generator.next()
+    /// Draws the lengths of field `index`, in `field_names` order, from
+    /// `dist` instead of the field length distribution.
+    pub fn field_len_override(mut self, index: usize, dist: DistributionSpec) -> Self {
+        self.field_len_overrides.retain(|(i, _)| *i != index);
+        self.field_len_overrides.push((index, dist));
+        self
     }
 
-    fn get_key_name(&self, key_num: usize) -> String {
-        format!(
-            "user{}",
-            if self.ordered_insert {
-                key_num as u64
-            } else {
-                let ip: *const usize = &key_num;
-                let bp: *const u8 = ip as *const _;
-                let bs: &[u8] =
-                    unsafe { std::slice::from_raw_parts(bp, std::mem::size_of::<usize>()) };
+    /// Draws scan lengths around `mean` records, up to `max`.
+    pub fn scan_len_normal(mut self, mean: f64, std_dev: f64, max: usize) -> Self {
+        self.scan_len_dist = DistributionSpec::Normal { mean, std_dev, max };
+        self
+    }
 
-                xx::hash64(bs)
-            }
-        )
+    pub fn read_all_fields(mut self, val: bool) -> Self {
+        self.read_all_fields = val;
+        self
     }
 
-    pub fn next_sequence_key(&self) -> String {
-        self.get_key_name(self.key_generator.next() as usize)
+    /// Makes updates and read-modify-writes write every field instead of
+    /// `fields_per_write` random ones. Inserts always write every field.
+    pub fn write_all_fields(mut self, val: bool) -> Self {
+        self.write_all_fields = val;
+        self
     }
 
-    pub fn next_insert_sequence(&self) -> String {
-        self.get_key_name(self.insert_key_sequence.next() as usize)
+    pub fn fields_per_read(mut self, count: usize) -> Self {
+        self.fields_per_read = count;
+        self
     }
 
-    pub fn next_transaction_key(&self) -> String {
-        self.get_key_name(self.key_sampler.next())
+    pub fn fields_per_write(mut self, count: usize) -> Self {
+        self.fields_per_write = count;
+        self
     }
 
-    pub fn next_field_value(&self) -> String {
-        let rng = SmallRng::from_rng(rand::thread_rng()).unwrap();
+    pub fn value_kind(mut self, kind: ValueKind) -> Self {
+        self.value_kind = kind;
+        self
+    }
 
-        rng.sample_iter(&Alphanumeric)
-            .take(self.field_len_generator.next())
-            .collect::<String>()
+    /// Makes field values compress by about `ratio`, e.g. 2.0 to mimic text
+    /// for engines that compress their data.
+    pub fn value_compressibility(mut self, ratio: f64) -> Self {
+        self.value_compressibility = ratio;
+        self
     }
 
-    pub fn next_scan_length(&self) -> usize {
-        self.scan_len_generator.next()
+    /// Writes values derived from the key and field name instead of random
+    /// ones, and counts values read back that don't match, like upstream
+    /// YCSB's `dataintegrity`.
+    pub fn data_integrity(mut self, val: bool) -> Self {
+        self.data_integrity = val;
+        self
     }
 
-    pub fn build_values(&self) -> Vec<(String, String)> {
-        (0..self.field_count)
-            .map(|i| (format!("field{}", i), self.next_field_value()))
-            .collect::<Vec<_>>()
+    pub fn read_proportion(mut self, val: f64) -> Self {
+        self.read_proportion = val;
+        self
     }
 
-    pub fn next_field_name(&self) -> String {
-        format!("field{}", self.field_generator.next())
+    pub fn update_proportion(mut self, val: f64) -> Self {
+        self.update_proportion = val;
+        self
     }
 
-    pub fn build_update(&self) -> (String, String) {
-        (self.next_field_name(), self.next_field_value())
+    pub fn insert_proportion(mut self, val: f64) -> Self {
+        self.insert_proportion = val;
+        self
     }
-}
 
-unsafe impl Sync for CoreWorkload {}
-unsafe impl Send for CoreWorkload {}
+    pub fn scan_proportion(mut self, val: f64) -> Self {
+        self.scan_proportion = val;
+        self
+    }
+
+    pub fn rmw_proportion(mut self, val: f64) -> Self {
+        self.rmw_proportion = val;
+        self
+    }
+
+    pub fn delete_proportion(mut self, val: f64) -> Self {
+        self.delete_proportion = val;
+        self
+    }
+
+    /// Makes a `val` share of reads request keys that were never inserted.
+    pub fn read_miss_proportion(mut self, val: f64) -> Self {
+        self.read_miss_proportion = val;
+        self
+    }
+
+    /// Remembers every key picked for deletion and draws again when a later
+    /// transaction samples one of them, so the miss rate does not grow with
+    /// the number of deletes. Costs a lock per sampled key.
+    pub fn skip_deleted_keys(mut self, val: bool) -> Self {
+        self.skip_deleted_keys = val;
+        self
+    }
+
+    /// Sweeps the keys in order, e.g. to warm caches. With `ordered_insert`
+    /// the requested keys, including the start keys of scans, increase
+    /// monotonically until the sweep wraps around.
+    pub fn request_sequential(mut self) -> Self {
+        self.request_dist = DistributionSpec::Sequential;
+        self
+    }
+
+    pub fn ordered_insert(mut self, val: bool) -> Self {
+        self.ordered_insert = val;
+        self
+    }
+
+    /// Sets the prefix of every key, `user` by default.
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = prefix.to_owned();
+        self
+    }
+
+    /// Seeds the random choices of every worker thread, so that runs with the
+    /// same seed and thread count draw the same operations, keys and values
+    /// on each thread.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Draws the random choices of each worker thread from a `kind`
+    /// generator.
+    pub fn rng(mut self, kind: RngKind) -> Self {
+        self.rng = kind;
+        self
+    }
+
+    /// Pads key numbers with zeros to at least `digits` digits, like
+    /// upstream YCSB's `zeropadding`.
+    pub fn zero_padding(mut self, digits: usize) -> Self {
+        self.zero_padding = digits;
+        self
+    }
+
+    /// Scrambles unordered key numbers with `kind`, `SplitMix` by default.
+    pub fn key_hash(mut self, kind: KeyHashKind) -> Self {
+        self.key_hash = Some(kind);
+        self
+    }
+
+    /// Requests keys `min..=max`, clamped to the records loaded or inserted
+    /// so far.
+    pub fn request_uniform(mut self, min: usize, max: usize) -> Self {
+        self.request_dist = DistributionSpec::Uniform(min, max);
+        self
+    }
+
+    /// Requests key `key_num` only, e.g. to measure contention on a single
+    /// hot key.
+    pub fn request_constant(mut self, key_num: usize) -> Self {
+        self.request_dist = DistributionSpec::Constant(key_num);
+        self
+    }
+
+    /// Requests the keys listed in a file, which the load phase inserts; see
+    /// `DistributionSpec::File`.
+    pub fn request_keys_from<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.request_dist = DistributionSpec::File(path.into());
+        self
+    }
+
+    /// Favors the most recently inserted keys.
+    pub fn request_latest(mut self) -> Self {
+        self.request_dist = DistributionSpec::Latest;
+        self
+    }
+
+    pub fn request_zipfian(mut self, s: f64) -> Self {
+        self.request_dist = DistributionSpec::Zipfian(0, s);
+        self
+    }
+
+    /// Whether zipfian requests are hashed over the key space (the default)
+    /// or clustered on the smallest keys.
+    pub fn scramble_zipfian(mut self, val: bool) -> Self {
+        self.scramble_zipfian = val;
+        self
+    }
+
+    /// Numbers the records from `val` instead of 0, e.g. to split one key
+    /// space across several loaders. Transactions only touch keys from
+    /// `val` on, unless `insert_count` is set.
+    pub fn insert_start(mut self, val: usize) -> Self {
+        self.insert_start = val;
+        self
+    }
+
+    /// Loads only the `count` records from `insert_start` on, e.g. to append
+    /// them to a table that already holds the others. Transactions then
+    /// request keys out of all `record_count` records, numbered from 0.
+    pub fn insert_count(mut self, count: usize) -> Self {
+        self.insert_count = Some(count);
+        self
+    }
+
+    pub fn record_count(mut self, val: usize) -> Self {
+        self.record_count = val;
+        self
+    }
+
+    pub fn get_record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Starts each table with the given number of records, e.g. the ones
+    /// left by an earlier phase. Ignored unless there is one per table.
+    pub(crate) fn table_record_counts(mut self, counts: Vec<usize>) -> Self {
+        self.record_count = counts.iter().sum();
+        self.table_record_counts = Some(counts);
+        self
+    }
+
+    /// The operation proportions, in `Operation::ALL` order.
+    fn proportions(&self) -> [(Operation, f64); 6] {
+        [
+            (Operation::Insert, self.insert_proportion),
+            (Operation::Read, self.read_proportion),
+            (Operation::Update, self.update_proportion),
+            (Operation::Scan, self.scan_proportion),
+            (Operation::ReadModifyWrite, self.rmw_proportion),
+            (Operation::Delete, self.delete_proportion),
+        ]
+    }
+
+    /// The spec with its operation proportions scaled to sum to 1.
+    fn normalized(&self) -> Self {
+        let total = self.proportions().iter().map(|(_, p)| p).sum::<f64>();
+
+        let mut spec = self.clone();
+        if total <= 0.0 {
+            return spec;
+        }
+        spec.insert_proportion /= total;
+        spec.read_proportion /= total;
+        spec.update_proportion /= total;
+        spec.scan_proportion /= total;
+        spec.rmw_proportion /= total;
+        spec.delete_proportion /= total;
+        spec
+    }
+
+    /// The spec with the fields only used by the run phase reset to their
+    /// defaults, except a key file, which also names the loaded keys.
+    fn without_run_phase(&self) -> Self {
+        let defaults = WorkloadSpec::default();
+
+        WorkloadSpec {
+            read_proportion: defaults.read_proportion,
+            update_proportion: defaults.update_proportion,
+            insert_proportion: defaults.insert_proportion,
+            scan_proportion: defaults.scan_proportion,
+            rmw_proportion: defaults.rmw_proportion,
+            delete_proportion: defaults.delete_proportion,
+            read_miss_proportion: defaults.read_miss_proportion,
+            request_dist: match &self.request_dist {
+                DistributionSpec::File(path) => DistributionSpec::File(path.clone()),
+                _ => defaults.request_dist,
+            },
+            scan_len_dist: defaults.scan_len_dist,
+            scan_start_dist: defaults.scan_start_dist,
+            read_request_dist: defaults.read_request_dist,
+            update_request_dist: defaults.update_request_dist,
+            rmw_request_dist: defaults.rmw_request_dist,
+            max_scan_length: defaults.max_scan_length,
+            think_time_dist: defaults.think_time_dist,
+            ..self.clone()
+        }
+    }
+
+    fn table_weights(&self) -> Vec<(&str, f64)> {
+        match &self.tables {
+            Some(tables) => tables.iter().map(|t| (t.name.as_str(), t.weight)).collect(),
+            None => vec![(self.table.as_str(), 1.0)],
+        }
+    }
+
+    /// Records loaded into each table: `record_count` split by weight.
+    fn records_per_table(&self) -> Vec<usize> {
+        let weights = self.table_weights();
+
+        if let Some(counts) = &self.table_record_counts {
+            if counts.len() == weights.len() {
+                return counts.clone();
+            }
+        }
+
+        let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+        let mut acc = 0.0;
+        let mut loaded = 0;
+
+        weights
+            .iter()
+            .map(|(_, weight)| {
+                acc += weight;
+                let end = (self.record_count as f64 * acc / total).round() as usize;
+                let count = end.min(self.record_count) - loaded;
+                loaded += count;
+                count
+            })
+            .collect()
+    }
+
+    pub fn operation_count(mut self, val: usize) -> Self {
+        self.operation_count = val;
+        self
+    }
+
+    pub fn get_operation_count(&self) -> usize {
+        self.operation_count
+    }
+
+    pub fn get_table(&self) -> &str {
+        &self.table
+    }
+
+    /// The tables set with `tables`, with their weights.
+    pub fn get_tables(&self) -> Option<Vec<(&str, f64)>> {
+        self.tables
+            .as_ref()
+            .map(|tables| tables.iter().map(|t| (t.name.as_str(), t.weight)).collect())
+    }
+
+    pub fn get_field_count(&self) -> usize {
+        self.field_count
+    }
+
+    pub fn get_field_names(&self) -> Option<&[String]> {
+        self.field_names.as_deref()
+    }
+
+    pub fn get_include_timestamp_field(&self) -> Option<&str> {
+        self.include_timestamp_field.as_deref()
+    }
+
+    pub fn get_field_len_dist(&self) -> &DistributionSpec {
+        &self.field_len_dist
+    }
+
+    pub fn get_field_len_overrides(&self) -> &[(usize, DistributionSpec)] {
+        &self.field_len_overrides
+    }
+
+    pub fn get_value_kind(&self) -> ValueKind {
+        self.value_kind
+    }
+
+    pub fn get_value_compressibility(&self) -> f64 {
+        self.value_compressibility
+    }
+
+    pub fn get_read_all_fields(&self) -> bool {
+        self.read_all_fields
+    }
+
+    pub fn get_write_all_fields(&self) -> bool {
+        self.write_all_fields
+    }
+
+    pub fn get_fields_per_read(&self) -> usize {
+        self.fields_per_read
+    }
+
+    pub fn get_fields_per_write(&self) -> usize {
+        self.fields_per_write
+    }
+
+    pub fn get_data_integrity(&self) -> bool {
+        self.data_integrity
+    }
+
+    pub fn get_ordered_insert(&self) -> bool {
+        self.ordered_insert
+    }
+
+    pub fn get_key_prefix(&self) -> &str {
+        &self.key_prefix
+    }
+
+    pub fn get_zero_padding(&self) -> usize {
+        self.zero_padding
+    }
+
+    /// How unordered key numbers are scrambled, including by an older
+    /// file's `legacy_key_hash`.
+    pub fn get_key_hash(&self) -> KeyHashKind {
+        match (self.key_hash, self.legacy_key_hash) {
+            (Some(kind), _) => kind,
+            (None, true) => KeyHashKind::LegacyXx64,
+            (None, false) => KeyHashKind::SplitMix,
+        }
+    }
+
+    pub fn get_read_proportion(&self) -> f64 {
+        self.read_proportion
+    }
+
+    pub fn get_update_proportion(&self) -> f64 {
+        self.update_proportion
+    }
+
+    pub fn get_insert_proportion(&self) -> f64 {
+        self.insert_proportion
+    }
+
+    pub fn get_scan_proportion(&self) -> f64 {
+        self.scan_proportion
+    }
+
+    pub fn get_rmw_proportion(&self) -> f64 {
+        self.rmw_proportion
+    }
+
+    pub fn get_delete_proportion(&self) -> f64 {
+        self.delete_proportion
+    }
+
+    pub fn get_read_miss_proportion(&self) -> f64 {
+        self.read_miss_proportion
+    }
+
+    pub fn get_skip_deleted_keys(&self) -> bool {
+        self.skip_deleted_keys
+    }
+
+    pub fn get_request_dist(&self) -> &DistributionSpec {
+        &self.request_dist
+    }
+
+    pub fn get_scramble_zipfian(&self) -> bool {
+        self.scramble_zipfian
+    }
+
+    pub fn get_scan_len_dist(&self) -> &DistributionSpec {
+        &self.scan_len_dist
+    }
+
+    pub fn get_scan_start_dist(&self) -> Option<&DistributionSpec> {
+        self.scan_start_dist.as_ref()
+    }
+
+    pub fn get_read_request_dist(&self) -> Option<&DistributionSpec> {
+        self.read_request_dist.as_ref()
+    }
+
+    pub fn get_update_request_dist(&self) -> Option<&DistributionSpec> {
+        self.update_request_dist.as_ref()
+    }
+
+    pub fn get_rmw_request_dist(&self) -> Option<&DistributionSpec> {
+        self.rmw_request_dist.as_ref()
+    }
+
+    /// Distributions replacing `request_dist` for single operation types,
+    /// with their names.
+    fn request_dist_overrides(&self) -> Vec<(Operation, &'static str, &DistributionSpec)> {
+        [
+            (
+                Operation::Read,
+                "read_request_dist",
+                &self.read_request_dist,
+            ),
+            (
+                Operation::Update,
+                "update_request_dist",
+                &self.update_request_dist,
+            ),
+            (Operation::Scan, "scan_start_dist", &self.scan_start_dist),
+            (
+                Operation::ReadModifyWrite,
+                "rmw_request_dist",
+                &self.rmw_request_dist,
+            ),
+        ]
+        .iter()
+        .filter_map(|&(op, name, dist)| dist.as_ref().map(|dist| (op, name, dist)))
+        .collect()
+    }
+
+    pub fn get_max_scan_length(&self) -> usize {
+        self.max_scan_length
+    }
+
+    pub fn get_think_time_dist(&self) -> Option<&DistributionSpec> {
+        self.think_time_dist.as_ref()
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn get_rng(&self) -> RngKind {
+        self.rng
+    }
+
+    pub fn get_insert_start(&self) -> usize {
+        self.insert_start
+    }
+
+    pub fn get_insert_count(&self) -> Option<usize> {
+        self.insert_count
+    }
+
+    /// Number of records the load phase inserts.
+    pub(crate) fn load_count(&self) -> usize {
+        self.insert_count.unwrap_or(self.record_count)
+    }
+
+    /// Number of the first record that transactions request.
+    fn key_start(&self) -> usize {
+        match self.insert_count {
+            Some(_) => 0,
+            None => self.insert_start,
+        }
+    }
+
+    /// Checks that `CoreWorkload::new` can build a sensible workload from the
+    /// spec. All problems found are reported together in one
+    /// `Error::InvalidArgument`.
+    pub fn validate(&self) -> Result<()> {
+        self.check(true)
+    }
+
+    /// Like `validate`, for `CoreWorkload::for_load`: skips the fields that
+    /// only matter to the run phase, such as the operation proportions and
+    /// the request and scan length distributions.
+    pub fn validate_load(&self) -> Result<()> {
+        self.check(false)
+    }
+
+    fn check(&self, run_phase: bool) -> Result<()> {
+        let mut problems = Vec::new();
+
+        let proportions = [
+            ("read_proportion", self.read_proportion),
+            ("update_proportion", self.update_proportion),
+            ("insert_proportion", self.insert_proportion),
+            ("scan_proportion", self.scan_proportion),
+            ("rmw_proportion", self.rmw_proportion),
+            ("delete_proportion", self.delete_proportion),
+        ];
+
+        if run_phase {
+            for (name, p) in &proportions {
+                if !(0.0..=1.0).contains(p) {
+                    problems.push(format!("{} must be in [0, 1], got {}", name, p));
+                }
+            }
+
+            if proportions.iter().map(|(_, p)| p).sum::<f64>() <= 0.0 {
+                problems.push("operation proportions sum to zero".to_owned());
+            }
+        }
+
+        if run_phase && !(0.0..=1.0).contains(&self.read_miss_proportion) {
+            problems.push(format!(
+                "read_miss_proportion must be in [0, 1], got {}",
+                self.read_miss_proportion
+            ));
+        } else if run_phase
+            && self.read_miss_proportion > 0.0
+            && self.key_prefix.starts_with(MISSING_KEY_PREFIX)
+        {
+            problems.push(format!(
+                "read_miss_proportion needs a key_prefix not starting with '{}'",
+                MISSING_KEY_PREFIX
+            ));
+        }
+
+        if let Some(count) = self.insert_count {
+            if self.insert_start.saturating_add(count) > self.record_count {
+                problems.push(format!(
+                    "insert_start {} + insert_count {} exceeds the record_count of {}",
+                    self.insert_start, count, self.record_count
+                ));
+            }
+        }
+
+        let reads_existing = self.read_proportion > 0.0
+            || self.update_proportion > 0.0
+            || self.scan_proportion > 0.0
+            || self.rmw_proportion > 0.0
+            || self.delete_proportion > 0.0;
+        // The latest distribution can start from nothing and follow the
+        // inserts.
+        let latest = self.request_dist == DistributionSpec::Latest;
+        let follows_inserts = latest && self.insert_proportion > 0.0;
+
+        if let Some(tables) = &self.tables {
+            if tables.is_empty() {
+                problems.push("tables must not be empty".to_owned());
+            }
+
+            let mut seen = HashSet::new();
+            if let Some(table) = tables.iter().find(|t| !seen.insert(t.name.as_str())) {
+                problems.push(format!("tables contains '{}' more than once", table.name));
+            }
+
+            if let Some(table) = tables
+                .iter()
+                .find(|t| !(t.weight > 0.0 && t.weight.is_finite()))
+            {
+                problems.push(format!(
+                    "tables: weight of '{}' must be positive, got {}",
+                    table.name, table.weight
+                ));
+            }
+        }
+
+        let records = self.records_per_table();
+        let fewest_records = records.iter().copied().min().unwrap_or(0);
+
+        if run_phase && fewest_records == 0 && (reads_existing || latest) && !follows_inserts {
+            if self.record_count == 0 || records.len() == 1 {
+                problems.push(
+                    "record_count must be at least 1 to read existing records or use the latest \
+                     distribution without inserts"
+                        .to_owned(),
+                );
+            } else {
+                problems.push(format!(
+                    "record_count {} leaves some of the {} tables without records",
+                    self.record_count,
+                    records.len()
+                ));
+            }
+        }
+
+        match &self.field_names {
+            Some(names) => {
+                if names.is_empty() {
+                    problems.push("field_names must not be empty".to_owned());
+                }
+
+                let mut seen = HashSet::new();
+                if let Some(name) = names.iter().find(|name| !seen.insert(name.as_str())) {
+                    problems.push(format!("field_names contains '{}' more than once", name));
+                }
+            }
+            None if self.field_count == 0 => {
+                problems.push("field_count must be at least 1".to_owned());
+            }
+            None => {}
+        }
+
+        let fields = self.field_names.as_ref().map_or(self.field_count, Vec::len);
+
+        if let Some(name) = &self.include_timestamp_field {
+            let taken = match &self.field_names {
+                Some(names) => names.contains(name),
+                None => (0..self.field_count).any(|i| *name == format!("field{}", i)),
+            };
+
+            if name.is_empty() {
+                problems.push("include_timestamp_field must not be empty".to_owned());
+            } else if taken {
+                problems.push(format!(
+                    "include_timestamp_field '{}' is already one of the {} fields",
+                    name, fields
+                ));
+            }
+        }
+
+        for (name, count) in &[
+            ("fields_per_read", self.fields_per_read),
+            ("fields_per_write", self.fields_per_write),
+        ] {
+            if *count == 0 || (fields > 0 && *count > fields) {
+                problems.push(format!(
+                    "{} must be between 1 and the {} fields, got {}",
+                    name, fields, count
+                ));
+            }
+        }
+
+        if !(self.value_compressibility >= 1.0 && self.value_compressibility.is_finite()) {
+            problems.push(format!(
+                "value_compressibility must be at least 1, got {}",
+                self.value_compressibility
+            ));
+        }
+
+        if self.data_integrity && self.value_kind != ValueKind::Alphanumeric {
+            problems.push("data_integrity needs an alphanumeric value_kind".to_owned());
+        }
+
+        if self.data_integrity && self.value_compressibility != 1.0 {
+            problems.push("data_integrity needs a value_compressibility of 1".to_owned());
+        }
+
+        if self.data_integrity {
+            let dists = std::iter::once(("field_len_dist".to_owned(), &self.field_len_dist)).chain(
+                self.field_len_overrides
+                    .iter()
+                    .map(|(index, dist)| (format!("field_len_overrides[{}]", index), dist)),
+            );
+
+            for (name, dist) in dists {
+                match *dist {
+                    DistributionSpec::Constant(len) => {
+                        // A value starts with its key and field name, so that
+                        // scanned rows can be checked without knowing their keys.
+                        let key_len = self.key_prefix.len() + self.zero_padding.max(20);
+                        let name_len = match &self.field_names {
+                            Some(names) => names.iter().map(String::len).max().unwrap_or(0),
+                            None => format!("field{}", self.field_count.saturating_sub(1)).len(),
+                        };
+                        let min_len = key_len + name_len + 2;
+
+                        if len < min_len {
+                            problems.push(format!(
+                                "data_integrity needs field values of at least {} bytes to hold \
+                                 the key and field name, got {}",
+                                min_len, len
+                            ));
+                        }
+                    }
+                    _ => problems.push(format!("data_integrity needs a constant {}", name)),
+                }
+            }
+        }
+
+        if self.key_prefix.contains('\0') {
+            problems.push("key_prefix must not contain NUL characters".to_owned());
+        }
+
+        check_field_len("field_len_dist", &self.field_len_dist, &mut problems);
+
+        let mut seen = HashSet::new();
+        for (index, dist) in &self.field_len_overrides {
+            let name = format!("field_len_overrides[{}]", index);
+
+            if *index >= fields {
+                problems.push(format!(
+                    "{}: field index out of range for {} fields",
+                    name, fields
+                ));
+            } else if !seen.insert(index) {
+                problems.push(format!("{}: field overridden more than once", name));
+            }
+
+            check_field_len(&name, dist, &mut problems);
+        }
+
+        match self.request_dist {
+            // A key file also names the keys to load.
+            DistributionSpec::File(_) => {
+                if records.len() > 1 {
+                    problems.push("request_dist: a key file needs a single table".to_owned());
+                }
+            }
+            _ if !run_phase => {}
+            DistributionSpec::Uniform(min, max) => {
+                check_key_uniform("request_dist", min, max, fewest_records, &mut problems)
+            }
+            DistributionSpec::Constant(key_num) => {
+                if key_num >= fewest_records {
+                    problems.push(format!(
+                        "request_dist: constant key {} is past the last of {} records",
+                        key_num, fewest_records
+                    ));
+                }
+            }
+            DistributionSpec::Latest | DistributionSpec::Sequential => {}
+            DistributionSpec::Zipfian(items, theta) => {
+                check_key_zipfian("request_dist", items, theta, fewest_records, &mut problems)
+            }
+            _ => problems.push(
+                "request_dist must be constant, uniform, zipfian, latest, sequential or file"
+                    .to_owned(),
+            ),
+        }
+
+        if run_phase {
+            for (_, name, dist) in self.request_dist_overrides() {
+                match *dist {
+                    DistributionSpec::Uniform(min, max) => {
+                        check_key_uniform(name, min, max, fewest_records, &mut problems)
+                    }
+                    DistributionSpec::Zipfian(items, theta) => {
+                        check_key_zipfian(name, items, theta, fewest_records, &mut problems)
+                    }
+                    DistributionSpec::Latest
+                        if fewest_records == 0 && self.insert_proportion <= 0.0 =>
+                    {
+                        problems.push(format!(
+                            "{}: latest needs a record_count of at least 1 or inserts",
+                            name
+                        ));
+                    }
+                    DistributionSpec::Latest => {}
+                    _ => problems.push(format!("{} must be uniform, zipfian or latest", name)),
+                }
+            }
+
+            match self.scan_len_dist {
+                DistributionSpec::Constant(_) => {}
+                DistributionSpec::Uniform(min, max) => {
+                    check_uniform("scan_len_dist", min, max, &mut problems)
+                }
+                DistributionSpec::Zipfian(items, theta) => {
+                    if items == 0 {
+                        problems.push("scan_len_dist: zipfian needs at least 1 item".to_owned());
+                    }
+                    check_theta("scan_len_dist", theta, &mut problems);
+                }
+                DistributionSpec::Pareto { scale, shape, max } => {
+                    check_pareto("scan_len_dist", scale, shape, max, &mut problems)
+                }
+                DistributionSpec::Normal { mean, std_dev, max } => {
+                    check_normal("scan_len_dist", mean, std_dev, max, &mut problems)
+                }
+                _ => problems.push(
+                    "scan_len_dist must be constant, uniform, zipfian, pareto or normal".to_owned(),
+                ),
+            }
+
+            if self.max_scan_length == 0 {
+                problems.push("max_scan_length must be at least 1".to_owned());
+            } else if let DistributionSpec::Uniform(min, _) = self.scan_len_dist {
+                if min > self.max_scan_length {
+                    problems.push(format!(
+                        "scan_len_dist: min {} exceeds max_scan_length {}",
+                        min, self.max_scan_length
+                    ));
+                }
+            }
+
+            match self.think_time_dist {
+                None | Some(DistributionSpec::Constant(_)) => {}
+                Some(DistributionSpec::Uniform(min, max)) => {
+                    check_uniform("think_time_dist", min, max, &mut problems)
+                }
+                Some(DistributionSpec::Exponential { mean, max }) => {
+                    check_exponential("think_time_dist", mean, max, &mut problems)
+                }
+                Some(_) => problems
+                    .push("think_time_dist must be constant, uniform or exponential".to_owned()),
+            }
+        }
+
+        if let (Some(kind), true) = (self.key_hash, self.legacy_key_hash) {
+            problems.push(format!(
+                "legacy_key_hash cannot be combined with key_hash {}",
+                kind
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidArgument(problems.join("; ")))
+        }
+    }
+
+    fn preset(read: f64, update: f64, insert: f64, scan: f64, rmw: f64) -> Self {
+        WorkloadSpec {
+            read_proportion: read,
+            update_proportion: update,
+            insert_proportion: insert,
+            scan_proportion: scan,
+            rmw_proportion: rmw,
+            request_dist: DistributionSpec::Zipfian(0, 0.99),
+            record_count: 100000,
+            operation_count: 100000,
+            ..Default::default()
+        }
+    }
+
+    /// Workload A, update heavy: 50% reads and 50% updates of zipfian keys.
+    pub fn workload_a() -> Self {
+        Self::preset(0.5, 0.5, 0.0, 0.0, 0.0)
+    }
+
+    /// Workload B, read mostly: 95% reads and 5% updates of zipfian keys.
+    pub fn workload_b() -> Self {
+        Self::preset(0.95, 0.05, 0.0, 0.0, 0.0)
+    }
+
+    /// Workload C, read only: 100% reads of zipfian keys.
+    pub fn workload_c() -> Self {
+        Self::preset(1.0, 0.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Workload D, read latest: 95% reads and 5% inserts, with reads skewed
+    /// towards the most recently inserted keys.
+    pub fn workload_d() -> Self {
+        WorkloadSpec {
+            request_dist: DistributionSpec::Latest,
+            ..Self::preset(0.95, 0.0, 0.05, 0.0, 0.0)
+        }
+    }
+
+    /// Workload E, short ranges: 95% scans of up to 100 records starting at
+    /// zipfian keys and 5% inserts.
+    pub fn workload_e() -> Self {
+        WorkloadSpec {
+            scan_len_dist: DistributionSpec::Uniform(1, 100),
+            ..Self::preset(0.0, 0.0, 0.05, 0.95, 0.0)
+        }
+    }
+
+    /// Workload F, read-modify-write: 50% reads and 50% read-modify-writes of
+    /// zipfian keys.
+    pub fn workload_f() -> Self {
+        Self::preset(0.5, 0.0, 0.0, 0.0, 0.5)
+    }
+
+    /// Reads a workload file, choosing the format by extension: `.json`,
+    /// `.toml`, or `.properties`/`.spec` for upstream YCSB property files.
+    /// Files with any other extension are tried with each parser in that
+    /// order. Warnings about unknown fields and properties are printed to
+    /// stderr.
+    ///
+    /// A JSON or TOML workload may name another one to start from with
+    /// `extends`, relative to its own directory, and set only the fields it
+    /// changes.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+
+        if let Some(serde_json::Value::Object(mut fields)) = parse_fields(path, &data) {
+            if fields.contains_key("extends") {
+                let mut chain = vec![(path.to_owned(), fs::canonicalize(path)?)];
+                fields = extend_fields(path, fields, &mut chain)?;
+
+                let (spec, unknown) = Self::parse_json(
+                    &serde_json::Value::Object(fields).to_string(),
+                )
+                .map_err(|e| match e {
+                    Error::InvalidSpec(msg) => chain_error(&chain, msg),
+                    e => e,
+                })?;
+                for warning in unknown_field_warnings(unknown) {
+                    eprintln!("{}: {}", path.display(), warning);
+                }
+
+                return Ok(spec);
+            }
+        }
+
+        let warn = |(spec, warnings): (Self, Vec<String>)| {
+            for warning in warnings {
+                eprintln!("{}: {}", path.display(), warning);
+            }
+
+            Ok(spec)
+        };
+        let from_json = |data: &str| {
+            let (spec, unknown) = Self::parse_json(data)?;
+            warn((spec, unknown_field_warnings(unknown)))
+        };
+        let from_toml = |data: &str| {
+            let (spec, unknown) = Self::parse_toml(data)?;
+            warn((spec, unknown_field_warnings(unknown)))
+        };
+        let from_properties = |data: &str| {
+            let (mut spec, warnings) = Self::from_properties(data.as_bytes())?;
+
+            // Like `extends`, relative to the property file's directory.
+            if let DistributionSpec::HistogramFile(file) = &mut spec.field_len_dist {
+                *file = path.parent().unwrap_or_else(|| Path::new("")).join(&file);
+            }
+
+            warn((spec, warnings))
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => from_json(&data),
+            Some("toml") => from_toml(&data),
+            Some("properties") | Some("spec") => from_properties(&data),
+            _ => from_json(&data)
+                .or_else(|_| from_toml(&data))
+                .or_else(|_| from_properties(&data))
+                .map_err(|_| Error::UnknownSpecFormat),
+        }
+    }
+
+    /// Parses an upstream YCSB workload property file such as `workloada`.
+    ///
+    /// Understands `recordcount`, `operationcount`, `fieldcount`,
+    /// `fieldlength`, `fieldlengthdistribution` (`constant`, `uniform` or
+    /// `histogram`), `fieldlengthhistogram`, `readallfields`,
+    /// `writeallfields`, the five
+    /// `*proportion` keys, `requestdistribution` (`uniform`, `zipfian` or
+    /// `latest`), `maxscanlength`, `scanlengthdistribution` (`uniform` or
+    /// `zipfian`), `insertorder` (`hashed` or `ordered`), `insertstart`,
+    /// `insertcount`, `zeropadding` and `dataintegrity`.
+    /// Other keys are skipped and returned as warnings alongside the spec.
+    ///
+    /// A relative `fieldlengthhistogram` is kept as it is here;
+    /// `from_path` resolves it against the property file's directory.
+    pub fn from_properties<R: Read>(reader: R) -> Result<(Self, Vec<String>)> {
+        let mut spec = WorkloadSpec::default();
+        let mut warnings = Vec::new();
+        let mut request_dist = "uniform".to_owned();
+        let mut scan_len_dist = "uniform".to_owned();
+        let mut max_scan_len = 1000;
+        let mut field_len_dist = "constant".to_owned();
+        let mut field_len = 100;
+        let mut field_len_histogram = "hist.txt".to_owned();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+
+            let (key, value) = match line.find(|c: char| c == '=' || c == ':' || c.is_whitespace())
+            {
+                Some(pos) => {
+                    let value = line[pos..].trim_start();
+                    let value = value
+                        .strip_prefix(|c: char| c == '=' || c == ':')
+                        .unwrap_or(value);
+
+                    (&line[..pos], value.trim())
+                }
+                None => (line, ""),
+            };
+
+            match key {
+                "recordcount" => spec.record_count = parse_property(key, value)?,
+                "operationcount" => spec.operation_count = parse_property(key, value)?,
+                "fieldcount" => spec.field_count = parse_property(key, value)?,
+                "fieldlength" => field_len = parse_property(key, value)?,
+                "fieldlengthdistribution" => field_len_dist = value.to_lowercase(),
+                "fieldlengthhistogram" => field_len_histogram = value.to_owned(),
+                "readallfields" => spec.read_all_fields = parse_property(key, value)?,
+                "writeallfields" => spec.write_all_fields = parse_property(key, value)?,
+                "readproportion" => spec.read_proportion = parse_property(key, value)?,
+                "updateproportion" => spec.update_proportion = parse_property(key, value)?,
+                "insertproportion" => spec.insert_proportion = parse_property(key, value)?,
+                "scanproportion" => spec.scan_proportion = parse_property(key, value)?,
+                "readmodifywriteproportion" => spec.rmw_proportion = parse_property(key, value)?,
+                "deleteproportion" => spec.delete_proportion = parse_property(key, value)?,
+                "requestdistribution" => request_dist = value.to_lowercase(),
+                "maxscanlength" => {
+                    max_scan_len = parse_property(key, value)?;
+                    spec.max_scan_length = max_scan_len;
+                }
+                "scanlengthdistribution" => scan_len_dist = value.to_lowercase(),
+                "insertorder" => {
+                    spec.ordered_insert = match value {
+                        "ordered" => true,
+                        "hashed" => false,
+                        _ => return Err(invalid_property(key, value)),
+                    }
+                }
+                "insertstart" => spec.insert_start = parse_property(key, value)?,
+                "insertcount" => spec.insert_count = Some(parse_property(key, value)?),
+                "zeropadding" => spec.zero_padding = parse_property(key, value)?,
+                "dataintegrity" => spec.data_integrity = parse_property(key, value)?,
+                _ => warnings.push(format!("ignoring unknown property '{}'", key)),
+            }
+        }
+
+        spec.request_dist = match request_dist.as_str() {
+            "uniform" => DistributionSpec::Uniform(0, usize::MAX),
+            "zipfian" => DistributionSpec::Zipfian(0, 0.99),
+            "latest" => DistributionSpec::Latest,
+            "sequential" => DistributionSpec::Sequential,
+            _ => return Err(invalid_property("requestdistribution", &request_dist)),
+        };
+
+        spec.field_len_dist = match field_len_dist.as_str() {
+            "constant" => DistributionSpec::Constant(field_len),
+            "uniform" => DistributionSpec::Uniform(1, field_len),
+            "histogram" => DistributionSpec::HistogramFile(field_len_histogram.into()),
+            _ => return Err(invalid_property("fieldlengthdistribution", &field_len_dist)),
+        };
+
+        spec.scan_len_dist = match scan_len_dist.as_str() {
+            "uniform" => DistributionSpec::Uniform(1, max_scan_len),
+            "zipfian" => DistributionSpec::Zipfian(max_scan_len, 0.99),
+            _ => return Err(invalid_property("scanlengthdistribution", &scan_len_dist)),
+        };
+
+        Ok((spec, warnings))
+    }
+
+    /// Parses a JSON workload. Missing fields take their default values and
+    /// unknown ones are skipped.
+    pub fn from_json_str(data: &str) -> Result<Self> {
+        Self::parse_json(data).map(|(spec, _)| spec)
+    }
+
+    /// Like `from_json_str`, but fails on unknown fields, e.g. misspelled
+    /// ones, instead of skipping them. Keys inside distributions are not
+    /// checked.
+    pub fn from_json_str_strict(data: &str) -> Result<Self> {
+        reject_unknown_fields(Self::parse_json(data)?)
+    }
+
+    /// Parses a TOML workload. Missing fields take their default values and
+    /// unknown ones are skipped.
+    pub fn from_toml_str(data: &str) -> Result<Self> {
+        Self::parse_toml(data).map(|(spec, _)| spec)
+    }
+
+    /// Like `from_toml_str`, but fails on unknown fields.
+    pub fn from_toml_str_strict(data: &str) -> Result<Self> {
+        reject_unknown_fields(Self::parse_toml(data)?)
+    }
+
+    /// Parses a JSON workload along with the paths of the unknown fields.
+    /// Errors name the field they occurred in, as TOML errors do.
+    fn parse_json(data: &str) -> Result<(Self, Vec<String>)> {
+        let mut de = serde_json::Deserializer::from_str(data);
+        let mut unknown = Vec::new();
+        let mut callback = |path: serde_ignored::Path| unknown.push(field_path(&path));
+
+        let spec = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+            &mut de,
+            &mut callback,
+        ))
+        .map_err(|e| match e.path().to_string() {
+            path if path == "." => Error::InvalidSpec(e.inner().to_string()),
+            path => Error::InvalidSpec(format!("{}: {}", path, e.inner())),
+        })?;
+        de.end().map_err(|e| Error::InvalidSpec(e.to_string()))?;
+
+        Ok((spec, unknown))
+    }
+
+    /// Parses a TOML workload along with the paths of the unknown fields.
+    fn parse_toml(data: &str) -> Result<(Self, Vec<String>)> {
+        deserialize_with_unknown(&mut toml::Deserializer::new(data))
+            .map_err(|e| Error::InvalidSpec(e.to_string()))
+    }
+
+    /// Sets the fields named in `overrides` as in JSON workloads, e.g.
+    /// `record_count` to `"1000"`. Values are parsed as JSON, falling back to
+    /// a plain string, so `request_dist` takes `{"type": "uniform", ...}` and
+    /// `table` takes `orders`. Nothing is set if any override fails.
+    pub fn apply_overrides(&mut self, overrides: &HashMap<String, String>) -> Result<()> {
+        let mut fields = match serde_json::to_value(&*self) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("workload specs serialize to JSON objects"),
+        };
+
+        let mut keys = overrides.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        for key in keys {
+            let value = &overrides[key];
+            let candidates = serde_json::from_str(value)
+                .ok()
+                .into_iter()
+                .chain(std::iter::once(serde_json::Value::String(value.clone())));
+            let mut first_err = None;
+
+            for candidate in candidates {
+                fields.insert(key.clone(), candidate);
+
+                match deserialize_with_unknown(serde_json::Value::Object(fields.clone())) {
+                    Ok((_, unknown)) if unknown.iter().any(|path| path == key) => {
+                        return Err(Error::InvalidArgument(format!(
+                            "unknown workload field '{}'",
+                            key
+                        )));
+                    }
+                    Ok(_) => {
+                        first_err = None;
+                        break;
+                    }
+                    Err(e) => {
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+
+            if let Some(e) = first_err {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid value '{}' for {}: {}",
+                    value, key, e
+                )));
+            }
+        }
+
+        *self = deserialize_with_unknown(serde_json::Value::Object(fields))
+            .map_err(|e| Error::InvalidArgument(e.to_string()))?
+            .0;
+
+        Ok(())
+    }
+
+    /// Collects the environment variables starting with `prefix` as
+    /// overrides for `apply_overrides`, e.g. `YCSB_RECORD_COUNT` as
+    /// `record_count` with the prefix `YCSB_`.
+    pub fn overrides_from_env(prefix: &str) -> HashMap<String, String> {
+        std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .filter_map(|(key, value)| {
+                let field = key.strip_prefix(prefix)?.to_lowercase();
+                Some((field, value))
+            })
+            .collect()
+    }
+}
+
+/// Deepest chain of `extends` that `WorkloadSpec::from_path` follows.
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+/// The fields of a JSON or TOML workload, without checking them against
+/// `WorkloadSpec`.
+fn parse_fields(path: &Path, data: &str) -> Option<serde_json::Value> {
+    let from_toml = || {
+        let value = toml::from_str::<toml::Value>(data).ok()?;
+        serde_json::to_value(value).ok()
+    };
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(data).ok(),
+        Some("toml") => from_toml(),
+        Some("properties") | Some("spec") => None,
+        _ => serde_json::from_str(data).ok().or_else(from_toml),
+    }
+}
+
+/// `fields` of the workload at `path` over those of the workloads it
+/// extends. `chain` lists the files read so far, with their canonical paths
+/// to detect cycles.
+fn extend_fields(
+    path: &Path,
+    mut fields: serde_json::Map<String, serde_json::Value>,
+    chain: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let base = match fields.remove("extends") {
+        None => return Ok(fields),
+        Some(serde_json::Value::String(base)) => {
+            path.parent().unwrap_or_else(|| Path::new("")).join(base)
+        }
+        Some(_) => return Err(chain_error(chain, "extends must be a path")),
+    };
+
+    if chain.len() > MAX_EXTENDS_DEPTH {
+        return Err(chain_error(
+            chain,
+            format!("extends more than {} workloads deep", MAX_EXTENDS_DEPTH),
+        ));
+    }
+
+    let canonical = fs::canonicalize(&base);
+    chain.push((
+        base.clone(),
+        canonical.as_ref().cloned().unwrap_or_default(),
+    ));
+    let canonical = canonical.map_err(|e| chain_error(chain, e))?;
+
+    if chain[..chain.len() - 1]
+        .iter()
+        .any(|(_, c)| *c == canonical)
+    {
+        return Err(chain_error(chain, "extends itself"));
+    }
+
+    let data = fs::read_to_string(&base).map_err(|e| chain_error(chain, e))?;
+    let base_fields = match parse_fields(&base, &data) {
+        Some(serde_json::Value::Object(base_fields)) => base_fields,
+        _ => return Err(chain_error(chain, "not a JSON or TOML workload")),
+    };
+
+    let mut merged = extend_fields(&base, base_fields, chain)?;
+    merged.extend(fields);
+
+    Ok(merged)
+}
+
+/// An `Error::InvalidSpec` naming the files of `chain`, outermost first.
+fn chain_error<E: Display>(chain: &[(PathBuf, PathBuf)], e: E) -> Error {
+    let files = chain
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect::<Vec<_>>();
+
+    Error::InvalidSpec(format!("{}: {}", files.join(" -> "), e))
+}
+
+fn deserialize_with_unknown<'de, D: Deserializer<'de>>(
+    de: D,
+) -> std::result::Result<(WorkloadSpec, Vec<String>), D::Error> {
+    let mut unknown = Vec::new();
+    let spec = serde_ignored::deserialize(de, |path| unknown.push(field_path(&path)))?;
+
+    Ok((spec, unknown))
+}
+
+/// Formats `path` like `tables.0.weight`, leaving out `Option`s.
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path::*;
+
+    let join = |parent, key: &dyn fmt::Display| match field_path(parent) {
+        parent if parent.is_empty() => key.to_string(),
+        parent => format!("{}.{}", parent, key),
+    };
+
+    match path {
+        Root => String::new(),
+        Seq { parent, index } => join(parent, index),
+        Map { parent, key } => join(parent, key),
+        Some { parent } | NewtypeStruct { parent } | NewtypeVariant { parent } => {
+            field_path(parent)
+        }
+    }
+}
+
+fn unknown_field_warnings(unknown: Vec<String>) -> Vec<String> {
+    unknown
+        .into_iter()
+        .map(|path| format!("ignoring unknown field '{}'", path))
+        .collect()
+}
+
+fn reject_unknown_fields((spec, unknown): (WorkloadSpec, Vec<String>)) -> Result<WorkloadSpec> {
+    if unknown.is_empty() {
+        Ok(spec)
+    } else {
+        Err(Error::InvalidSpec(format!(
+            "unknown fields: {}",
+            unknown.join(", ")
+        )))
+    }
+}
+
+fn check_uniform(name: &str, min: usize, max: usize, problems: &mut Vec<String>) {
+    if min > max {
+        problems.push(format!("{}: uniform min {} exceeds max {}", name, min, max));
+    }
+}
+
+/// Checks a uniform distribution of key numbers.
+fn check_key_uniform(
+    name: &str,
+    min: usize,
+    max: usize,
+    fewest_records: usize,
+    problems: &mut Vec<String>,
+) {
+    check_uniform(name, min, max, problems);
+
+    if fewest_records > 0 && min >= fewest_records {
+        problems.push(format!(
+            "{}: uniform min {} is past the last of {} records",
+            name, min, fewest_records
+        ));
+    }
+}
+
+/// Checks a zipfian distribution of key numbers.
+fn check_key_zipfian(
+    name: &str,
+    items: usize,
+    theta: f64,
+    fewest_records: usize,
+    problems: &mut Vec<String>,
+) {
+    if items == 1 {
+        problems.push(format!(
+            "{}: zipfian needs 0 items for the whole key space or at least 2",
+            name
+        ));
+    } else if items > 1 && fewest_records < 2 {
+        problems.push(format!(
+            "{}: zipfian over {} items needs a record_count of at least 2 per table, got {}",
+            name, items, fewest_records
+        ));
+    }
+    check_theta(name, theta, problems)
+}
+
+fn check_theta(name: &str, theta: f64, problems: &mut Vec<String>) {
+    if !(theta > 0.0 && theta.is_finite()) {
+        problems.push(format!(
+            "{}: zipfian theta must be positive, got {}",
+            name, theta
+        ));
+    }
+}
+
+fn check_pareto(name: &str, scale: f64, shape: f64, max: usize, problems: &mut Vec<String>) {
+    if !(scale > 0.0 && scale.is_finite()) {
+        problems.push(format!(
+            "{}: pareto scale must be positive, got {}",
+            name, scale
+        ));
+    }
+
+    if !(shape > 0.0 && shape.is_finite()) {
+        problems.push(format!(
+            "{}: pareto shape must be positive, got {}",
+            name, shape
+        ));
+    }
+
+    if (max as f64) < scale {
+        problems.push(format!(
+            "{}: pareto max {} is below the scale {}",
+            name, max, scale
+        ));
+    }
+}
+
+fn check_normal(name: &str, mean: f64, std_dev: f64, max: usize, problems: &mut Vec<String>) {
+    if !mean.is_finite() {
+        problems.push(format!(
+            "{}: normal mean must be finite, got {}",
+            name, mean
+        ));
+    }
+
+    if !(std_dev > 0.0 && std_dev.is_finite()) {
+        problems.push(format!(
+            "{}: normal std_dev must be positive, got {}",
+            name, std_dev
+        ));
+    }
+
+    if max == 0 {
+        problems.push(format!("{}: normal max must be at least 1", name));
+    }
+}
+
+fn check_exponential(name: &str, mean: f64, max: usize, problems: &mut Vec<String>) {
+    if !(mean > 0.0 && mean.is_finite()) {
+        problems.push(format!(
+            "{}: exponential mean must be positive, got {}",
+            name, mean
+        ));
+    }
+
+    if max == 0 {
+        problems.push(format!("{}: exponential max must be at least 1", name));
+    }
+}
+
+fn check_field_len(name: &str, dist: &DistributionSpec, problems: &mut Vec<String>) {
+    match *dist {
+        DistributionSpec::Constant(_) => {}
+        DistributionSpec::Uniform(min, max) => check_uniform(name, min, max, problems),
+        DistributionSpec::Histogram(ref buckets) => {
+            if buckets.is_empty() {
+                problems.push(format!("{}: histogram has no buckets", name));
+            }
+
+            if let Some(&(length, weight)) = buckets
+                .iter()
+                .find(|(_, weight)| !(*weight > 0.0 && weight.is_finite()))
+            {
+                problems.push(format!(
+                    "{}: histogram weight for length {} must be positive, got {}",
+                    name, length, weight
+                ));
+            }
+        }
+        DistributionSpec::HistogramFile(_) => {}
+        DistributionSpec::Pareto { scale, shape, max } => {
+            check_pareto(name, scale, shape, max, problems)
+        }
+        DistributionSpec::Normal { mean, std_dev, max } => {
+            check_normal(name, mean, std_dev, max, problems)
+        }
+        DistributionSpec::Exponential { mean, max } => check_exponential(name, mean, max, problems),
+        _ => problems.push(format!(
+            "{} must be constant, uniform, histogram, pareto, normal or exponential",
+            name
+        )),
+    }
+}
+
+fn invalid_property(key: &str, value: &str) -> Error {
+    Error::InvalidSpec(format!("invalid value '{}' for property '{}'", value, key))
+}
+
+fn parse_property<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| invalid_property(key, value))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Operation {
+    Insert,
+    Read,
+    Update,
+    Scan,
+    ReadModifyWrite,
+    Delete,
+}
+
+impl Operation {
+    /// Every operation, in discriminant order.
+    pub const ALL: [Operation; 6] = [
+        Operation::Insert,
+        Operation::Read,
+        Operation::Update,
+        Operation::Scan,
+        Operation::ReadModifyWrite,
+        Operation::Delete,
+    ];
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Operation::*;
+
+        match *self {
+            Insert => write!(f, "INSERT"),
+            Read => write!(f, "READ"),
+            Update => write!(f, "UPDATE"),
+            Scan => write!(f, "SCAN"),
+            ReadModifyWrite => write!(f, "READ-MODIFY-WRITE"),
+            Delete => write!(f, "DELETE"),
+        }
+    }
+}
+
+/// A workload to run: either a spec to build the workload from, or a
+/// workload built by the caller together with the counts it was built for.
+pub enum WorkloadSource {
+    Spec(Box<WorkloadSpec>),
+    Workload {
+        workload: Arc<CoreWorkload>,
+        record_count: usize,
+        operation_count: usize,
+    },
+}
+
+impl WorkloadSource {
+    /// A pre-built workload. `record_count` is the number of records to load,
+    /// and should match the `record_count` the workload was created with so
+    /// that transactions draw keys from the loaded range.
+    pub fn workload(
+        workload: Arc<CoreWorkload>,
+        record_count: usize,
+        operation_count: usize,
+    ) -> Self {
+        WorkloadSource::Workload {
+            workload,
+            record_count,
+            operation_count,
+        }
+    }
+
+    pub fn operation_count(&self) -> usize {
+        match self {
+            WorkloadSource::Spec(spec) => spec.get_operation_count(),
+            WorkloadSource::Workload {
+                operation_count, ..
+            } => *operation_count,
+        }
+    }
+
+    pub(crate) fn has_think_time(&self) -> bool {
+        match self {
+            WorkloadSource::Spec(spec) => spec.think_time_dist.is_some(),
+            WorkloadSource::Workload { workload, .. } => workload.think_time_generator.is_some(),
+        }
+    }
+}
+
+impl From<WorkloadSpec> for WorkloadSource {
+    fn from(spec: WorkloadSpec) -> Self {
+        WorkloadSource::Spec(Box::new(spec))
+    }
+}
+
+/// A table of a workload, with its own key numbers. The same key name can
+/// exist in several tables.
+pub struct Table {
+    name: String,
+    /// Position in the workload's tables.
+    index: usize,
+    /// Position of the table's first record in the load sequence.
+    load_offset: usize,
+    /// Draws key numbers counted from `key_start`, the `insert_start` of the
+    /// spec unless it sets an `insert_count`.
+    key_sampler: Box<dyn Generator<u64>>,
+    /// Draws key numbers like `key_sampler` for the operation types whose
+    /// request distribution is overridden, by `Operation` discriminant.
+    op_samplers: Vec<Option<Box<dyn Generator<u64>>>>,
+    key_start: u64,
+    insert_key_sequence: Arc<generator::AcknowledgedCounterGenerator>,
+    /// Key numbers picked for deletion, when deleted keys are skipped.
+    deleted: Option<Arc<Mutex<HashSet<u64>>>>,
+}
+
+impl Table {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Draws a key number for `op`, or for any operation type without a
+    /// request distribution of its own if `None`.
+    fn sample_key_num(&self, rng: &mut WorkloadRng, op: Option<Operation>) -> u64 {
+        let sampler = op
+            .and_then(|op| self.op_samplers[op as usize].as_deref())
+            .unwrap_or(&*self.key_sampler);
+
+        self.key_start + sampler.next(rng)
+    }
+}
+
+/// Prefix of the keys that reads meant to miss request, which no key drawn
+/// from `key_prefix` has.
+const MISSING_KEY_PREFIX: &str = "missing";
+
+/// Turns a key number into a key name, given whether keys are inserted in
+/// order. See `CoreWorkload::with_key_mapper`.
+pub type KeyMapper = Arc<dyn Fn(u64, bool) -> String + Send + Sync>;
+
+/// The key names of `spec`: `key_prefix` followed by the key number, hashed
+/// unless inserts are ordered, and padded to `zero_padding` digits.
+fn default_key_mapper(spec: &WorkloadSpec) -> KeyMapper {
+    let prefix = spec.key_prefix.clone();
+    let width = spec.zero_padding;
+    let key_hash = spec.get_key_hash();
+
+    Arc::new(move |key_num, ordered| {
+        let num = hash_key_num(key_num, ordered, key_hash);
+
+        format!("{}{:0width$}", prefix, num, width = width)
+    })
+}
+
+/// The integer that `default_key_mapper` names a key number with.
+fn hash_key_num(key_num: u64, ordered: bool, key_hash: KeyHashKind) -> u64 {
+    if ordered {
+        return key_num;
+    }
+
+    match key_hash {
+        KeyHashKind::SplitMix => permute_key_num(key_num),
+        KeyHashKind::Fnv64 => generator::fnv_hash64(key_num),
+        KeyHashKind::Wyhash => wyhash_key_num(key_num),
+        KeyHashKind::Xx64 => xxh64::hash64(key_num.to_le_bytes()),
+        KeyHashKind::LegacyXx64 => xxh64::hash64((key_num as usize).to_ne_bytes()),
+    }
+}
+
+/// Scrambles `x` with the SplitMix64 finalizer. Every step is invertible, so
+/// distinct key numbers always get distinct integers.
+fn permute_key_num(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The wyhash64 mixing step: two folded 128-bit multiplications.
+fn wyhash_key_num(x: u64) -> u64 {
+    let mix = |a: u64, b: u64| {
+        let product = a as u128 * b as u128;
+        (product >> 64) as u64 ^ product as u64
+    };
+
+    mix(
+        mix(x.wrapping_add(0x60be_e2be_e120_fc15), 0xa3b1_9535_4a39_b70d),
+        0x1b03_7387_12fa_d5c9,
+    )
+}
+
+pub struct CoreWorkload {
+    field_len_generator: Box<dyn Generator<usize>>,
+    /// Generators replacing `field_len_generator`, by field index.
+    field_len_overrides: Vec<Option<Box<dyn Generator<usize>>>>,
+    op_generator:
+        generator::DistributionGenerator<Operation, generator::DiscreteDistribution<Operation>>,
+    /// Position in the load sequence, which loads the tables one by one.
+    key_generator: generator::CounterGenerator,
+    table_generator:
+        generator::DistributionGenerator<usize, generator::DiscreteDistribution<usize>>,
+    tables: Vec<Table>,
+    field_generator: generator::DistributionGenerator<usize, rand::distributions::Uniform<usize>>,
+    scan_len_generator: Box<dyn Generator<usize>>,
+    /// Think times in microseconds, if any.
+    think_time_generator: Option<Box<dyn Generator<usize>>>,
+
+    /// First position of the load sequence.
+    insert_start: usize,
+    /// Number of the first record of each table.
+    key_start: usize,
+    field_names: Vec<String>,
+    timestamp_field: Option<String>,
+    /// Last value of the timestamp field, so that it never goes back.
+    last_timestamp: AtomicU64,
+    seed: Option<u64>,
+    rng_kind: RngKind,
+
+    read_all_fields: bool,
+    write_all_fields: bool,
+    fields_per_read: usize,
+    fields_per_write: usize,
+    data_integrity: bool,
+    value_kind: ValueKind,
+    value_compressibility: f64,
+    read_miss_proportion: f64,
+
+    ordered_insert: bool,
+    key_hash: KeyHashKind,
+    key_mapper: KeyMapper,
+    max_scan_length: usize,
+    /// Names of the first key numbers, from a key file.
+    file_keys: Vec<String>,
+    /// Draw counts of the hottest table indices and key numbers, while
+    /// tracking them.
+    key_frequency: Mutex<Option<SpaceSaving<(usize, u64)>>>,
+    /// The spec the workload was built from, normalized.
+    spec: WorkloadSpec,
+}
+
+impl CoreWorkload {
+    pub fn new(spec: WorkloadSpec) -> Result<Self> {
+        let key_mapper = default_key_mapper(&spec);
+
+        Self::with_key_mapper(spec, key_mapper)
+    }
+
+    /// Like `new`, naming keys with `key_mapper` instead of `key_prefix`,
+    /// `zero_padding` and the key hash of the spec. Every key the workload
+    /// loads, requests, inserts or verifies is named by it, so it must map
+    /// distinct key numbers to distinct names.
+    pub fn with_key_mapper(spec: WorkloadSpec, key_mapper: KeyMapper) -> Result<Self> {
+        spec.validate()?;
+        let effective_spec = spec.normalized();
+
+        Self::build(spec, effective_spec, key_mapper, None)
+    }
+
+    /// Like `new`, for a workload drawing from the key space of `other`:
+    /// inserts by either workload, and deletes when deleted keys are skipped,
+    /// are seen by both, and keys are named by `other`'s key mapper. `spec`
+    /// must have the same tables, key prefix, record count and insert start
+    /// as the spec `other` was built from.
+    pub fn sharing_key_space(spec: WorkloadSpec, other: &CoreWorkload) -> Result<Self> {
+        spec.validate()?;
+        let effective_spec = spec.normalized();
+
+        let (ours, theirs) = (&effective_spec, &other.spec);
+        let mut problems = Vec::new();
+        if ours.table_weights() != theirs.table_weights() {
+            problems.push("tables");
+        }
+        if ours.key_prefix != theirs.key_prefix {
+            problems.push("key_prefix");
+        }
+        if ours.record_count != theirs.record_count {
+            problems.push("record_count");
+        }
+        if (ours.insert_start, ours.insert_count) != (theirs.insert_start, theirs.insert_count) {
+            problems.push("insert_start");
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::InvalidSpec(format!(
+                "workloads sharing a key space differ in {}",
+                problems.join(", ")
+            )));
+        }
+
+        Self::build(spec, effective_spec, other.key_mapper.clone(), Some(other))
+    }
+
+    /// Like `new`, for a workload that only loads records. The fields of
+    /// `spec` that only matter to the run phase are neither checked nor used,
+    /// so a load-only spec can leave them out; the workload's transaction
+    /// methods follow the default operation mix instead.
+    pub fn for_load(spec: WorkloadSpec) -> Result<Self> {
+        spec.validate_load()?;
+        let key_mapper = default_key_mapper(&spec);
+        let effective_spec = spec.normalized();
+
+        Self::build(spec.without_run_phase(), effective_spec, key_mapper, None)
+    }
+
+    /// Builds the workload of `spec`, on the insert sequences and deleted
+    /// keys of `shared` if given.
+    fn build(
+        spec: WorkloadSpec,
+        effective_spec: WorkloadSpec,
+        key_mapper: KeyMapper,
+        shared: Option<&CoreWorkload>,
+    ) -> Result<Self> {
+        let weights = spec.table_weights();
+        let table_generator =
+            generator::discrete_gen(weights.iter().map(|&(_, w)| w).enumerate().collect());
+
+        let key_start = spec.key_start();
+        let key_hash = spec.get_key_hash();
+
+        let file_keys = match &spec.request_dist {
+            DistributionSpec::File(path) => {
+                let keys = read_key_file(path)?;
+                let wanted = key_start + spec.record_count;
+
+                if keys.len() < wanted {
+                    return Err(Error::InvalidSpec(format!(
+                        "{} lists {} keys, fewer than the {} to load",
+                        path.display(),
+                        keys.len(),
+                        wanted
+                    )));
+                }
+
+                Some(keys)
+            }
+            _ => None,
+        };
+
+        let mut tables = Vec::new();
+        let mut load_offset = 0;
+        for (&(name, _), record_count) in weights.iter().zip(spec.records_per_table()) {
+            let shared_table = shared.map(|workload| &workload.tables[tables.len()]);
+            let insert_key_sequence = match shared_table {
+                Some(table) => table.insert_key_sequence.clone(),
+                None => Arc::new(generator::AcknowledgedCounterGenerator::new(
+                    (key_start + record_count) as u64,
+                )),
+            };
+            let mut op_samplers = Vec::new();
+            op_samplers.resize_with(Operation::ALL.len(), || None);
+            for (op, _, dist) in spec.request_dist_overrides() {
+                op_samplers[op as usize] = Some(key_sampler(
+                    &spec,
+                    dist,
+                    record_count,
+                    &insert_key_sequence,
+                )?);
+            }
+            let key_sampler: Box<dyn Generator<u64>> = match &file_keys {
+                // The keys loaded so far, by the weights in the file.
+                Some(keys) => Box::new(generator::discrete_gen(
+                    keys[key_start..key_start + record_count]
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (_, weight))| (i as u64, *weight))
+                        .collect(),
+                )),
+                None => key_sampler(
+                    &spec,
+                    &spec.request_dist,
+                    record_count,
+                    &insert_key_sequence,
+                )?,
+            };
+
+            tables.push(Table {
+                name: name.to_owned(),
+                index: tables.len(),
+                load_offset,
+                key_sampler,
+                op_samplers,
+                key_start: key_start as u64,
+                insert_key_sequence,
+                deleted: match spec.skip_deleted_keys {
+                    true => Some(
+                        shared_table
+                            .and_then(|table| table.deleted.clone())
+                            .unwrap_or_default(),
+                    ),
+                    false => None,
+                },
+            });
+            load_offset += record_count;
+        }
+
+        let mut ops = Vec::new();
+        if spec.read_proportion > 0.0 {
+            ops.push((Operation::Read, spec.read_proportion));
+        }
+        if spec.update_proportion > 0.0 {
+            ops.push((Operation::Update, spec.update_proportion));
+        }
+        if spec.insert_proportion > 0.0 {
+            ops.push((Operation::Insert, spec.insert_proportion));
+        }
+        if spec.scan_proportion > 0.0 {
+            ops.push((Operation::Scan, spec.scan_proportion));
+        }
+        if spec.rmw_proportion > 0.0 {
+            ops.push((Operation::ReadModifyWrite, spec.rmw_proportion));
+        }
+        if spec.delete_proportion > 0.0 {
+            ops.push((Operation::Delete, spec.delete_proportion));
+        }
+        let op_generator = generator::discrete_gen(ops);
+
+        let key_generator = generator::CounterGenerator::new(spec.insert_start as u64);
+
+        let field_names = match spec.field_names {
+            Some(names) => names,
+            None => (0..spec.field_count)
+                .map(|i| format!("field{}", i))
+                .collect(),
+        };
+        let field_generator = generator::uniform_gen(0, field_names.len() - 1);
+
+        let mut field_len_overrides = Vec::new();
+        field_len_overrides.resize_with(field_names.len(), || None);
+        for (index, dist) in spec.field_len_overrides {
+            field_len_overrides[index] = Some(field_len_generator(dist)?);
+        }
+        let think_time_generator = spec.think_time_dist.map(field_len_generator).transpose()?;
+        let field_len_generator = field_len_generator(spec.field_len_dist)?;
+
+        let scan_len_generator: Box<dyn Generator<usize>> = match spec.scan_len_dist {
+            DistributionSpec::Constant(len) => Box::new(generator::ConstGenerator::new(len)),
+            DistributionSpec::Uniform(min, max) => {
+                Box::new(generator::uniform_gen(min, max.min(spec.max_scan_length)))
+            }
+            DistributionSpec::Zipfian(num_elements, s) => {
+                Box::new(generator::zipfian_gen(num_elements, s)?)
+            }
+            DistributionSpec::Pareto { scale, shape, max } => {
+                Box::new(generator::ParetoGenerator::new(scale, shape, max))
+            }
+            DistributionSpec::Normal { mean, std_dev, max } => {
+                Box::new(generator::NormalGenerator::new(mean, std_dev, max)?)
+            }
+            _ => {
+                return Err(Error::InvalidArgument(
+                    "scan length distribution".to_owned(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            field_len_generator,
+            field_len_overrides,
+            op_generator,
+            key_generator,
+            table_generator,
+            tables,
+            field_generator,
+            scan_len_generator,
+            think_time_generator,
+
+            insert_start: spec.insert_start,
+            key_start,
+            field_names,
+            timestamp_field: spec.include_timestamp_field,
+            last_timestamp: AtomicU64::new(0),
+            seed: spec.seed,
+            rng_kind: spec.rng,
+            spec: effective_spec,
+
+            read_all_fields: spec.read_all_fields,
+            fields_per_read: spec.fields_per_read,
+            fields_per_write: spec.fields_per_write,
+            write_all_fields: spec.write_all_fields,
+            data_integrity: spec.data_integrity,
+            value_kind: spec.value_kind,
+            value_compressibility: spec.value_compressibility,
+            read_miss_proportion: spec.read_miss_proportion,
+
+            ordered_insert: spec.ordered_insert,
+            key_hash,
+            max_scan_length: spec.max_scan_length,
+            file_keys: file_keys
+                .map(|keys| keys.into_iter().map(|(key, _)| key).collect())
+                .unwrap_or_default(),
+            key_mapper,
+            key_frequency: Mutex::new(None),
+        })
+    }
+
+    pub fn read_all_fields(&self) -> bool {
+        self.read_all_fields
+    }
+
+    pub fn write_all_fields(&self) -> bool {
+        self.write_all_fields
+    }
+
+    pub fn data_integrity(&self) -> bool {
+        self.data_integrity
+    }
+
+    /// The spec the workload was built from, with its operation proportions
+    /// scaled to sum to 1. Building a workload from it does the same as this
+    /// one.
+    pub fn effective_spec(&self) -> WorkloadSpec {
+        self.spec.clone()
+    }
+
+    /// Hash of the parts of the spec that decide which records the load
+    /// writes, to tell whether a load checkpoint was written for it.
+    pub(crate) fn load_spec_hash(&self) -> u64 {
+        let spec = WorkloadSpec {
+            operation_count: 0,
+            ..self.spec.without_run_phase()
+        };
+
+        xxh64::hash64(serde_json::to_vec(&spec).expect("workload specs serialize"))
+    }
+
+    /// A multi-line summary of what the workload does, for logs and bug
+    /// reports: its tables and keys, operation mix and distributions.
+    pub fn describe(&self) -> String {
+        let spec = &self.spec;
+        let mut out = String::new();
+
+        let tables = spec
+            .table_weights()
+            .iter()
+            .zip(spec.records_per_table())
+            .map(|((name, _), count)| format!("{} ({} records)", name, count))
+            .collect::<Vec<_>>();
+        writeln!(out, "Tables: {}", tables.join(", ")).unwrap();
+        writeln!(
+            out,
+            "Keys: {} from key number {}, {}, e.g. {}",
+            spec.record_count,
+            spec.key_start(),
+            match spec.ordered_insert {
+                true => "in order".to_owned(),
+                false => format!("scrambled with {}", spec.get_key_hash()),
+            },
+            self.get_key_name(spec.key_start() as u64)
+        )
+        .unwrap();
+
+        let mix = spec
+            .proportions()
+            .iter()
+            .filter(|(_, p)| *p > 0.0)
+            .map(|(op, p)| format!("{} {:.1}%", op, p * 100.0))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "Operations: {} of {}",
+            spec.operation_count,
+            mix.join(", ")
+        )
+        .unwrap();
+
+        writeln!(
+            out,
+            "Request distribution: {:?}{}",
+            spec.request_dist,
+            match (&spec.request_dist, spec.scramble_zipfian) {
+                (DistributionSpec::Zipfian(..), true) => ", scrambled",
+                _ => "",
+            }
+        )
+        .unwrap();
+        for (op, _, dist) in spec.request_dist_overrides() {
+            writeln!(out, "  {} keys: {:?}", op, dist).unwrap();
+        }
+        if spec.read_miss_proportion > 0.0 {
+            writeln!(
+                out,
+                "  READ misses: {:.1}%",
+                spec.read_miss_proportion * 100.0
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            out,
+            "Scan lengths: {:?}, at most {}",
+            spec.scan_len_dist, spec.max_scan_length
+        )
+        .unwrap();
+        if let Some(dist) = &spec.think_time_dist {
+            writeln!(out, "Think time (us): {:?}", dist).unwrap();
+        }
+        writeln!(
+            out,
+            "Fields: {} of {:?} bytes, {:?} values",
+            self.field_names.len(),
+            spec.field_len_dist,
+            spec.value_kind
+        )
+        .unwrap();
+        for (index, dist) in &spec.field_len_overrides {
+            writeln!(out, "  {}: {:?} bytes", self.field_names[*index], dist).unwrap();
+        }
+
+        out
+    }
+
+    /// Picks the table of the next operation by the tables' weights.
+    pub fn next_table(&self) -> &Table {
+        generator::with_rng(|rng| self.next_table_with(rng))
+    }
+
+    fn next_table_with(&self, rng: &mut WorkloadRng) -> &Table {
+        match self.tables.len() {
+            1 => &self.tables[0],
+            _ => &self.tables[self.table_generator.next(rng)],
+        }
+    }
+
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    pub fn next_operation(&self) -> Operation {
+        self.op_generator.next_value()
+    }
+
+    /// `n` operations, drawn in one pass like `n` calls of `next_operation`.
+    pub fn next_operations(&self, n: usize) -> Vec<Operation> {
+        let mut ops = Vec::with_capacity(n);
+        generator::with_rng(|rng| self.op_generator.sample_many_with(rng, n, &mut ops));
+        ops
+    }
+
+    /// A session seeded from this thread's random number generator, so that
+    /// `seed_thread` applies to it.
+    pub(crate) fn thread_session(&self) -> WorkloadSession<'_> {
+        self.session_with(generator::with_rng(|rng| {
+            WorkloadRng::from_rng(self.rng_kind, rng)
+        }))
+    }
+
+    /// Starts drawing operations and keys for one worker thread. The session
+    /// has its own random number generator, seeded from the spec's seed and
+    /// `stream` when there is one, and shares only the insert counters with
+    /// other sessions.
+    pub fn session(&self, stream: usize) -> WorkloadSession<'_> {
+        self.session_with(WorkloadRng::new(self.rng_kind, self.seed, stream))
+    }
+
+    fn session_with(&self, rng: WorkloadRng) -> WorkloadSession<'_> {
+        let key_frequency = self
+            .key_frequency
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|summary| SpaceSaving::new(summary.capacity()));
+
+        WorkloadSession {
+            workload: self,
+            rng,
+            key_frequency,
+        }
+    }
+
+    /// Starts counting the keys drawn by sessions created from now on for
+    /// reads, updates, read-modify-writes and scans, forgetting earlier
+    /// counts. Only about the `capacity` hottest keys are kept, so the
+    /// counts take bounded memory. A capacity of 0 stops counting.
+    pub fn track_key_frequency(&self, capacity: usize) {
+        *self.key_frequency.lock().unwrap() = match capacity {
+            0 => None,
+            _ => Some(SpaceSaving::new(capacity)),
+        };
+    }
+
+    /// The `top_n` keys drawn most often by the sessions dropped since
+    /// `track_key_frequency`, hottest first.
+    pub fn key_frequency_report(&self, top_n: usize) -> Vec<KeyFrequency> {
+        match &*self.key_frequency.lock().unwrap() {
+            Some(summary) => summary
+                .top(top_n)
+                .into_iter()
+                .map(|((table, key_num), count, error)| KeyFrequency {
+                    table: self.tables[table].name.clone(),
+                    key_num,
+                    key: self.get_key_name(key_num),
+                    count,
+                    error,
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Names the key number `key_num`. With the default key mapper this only
+    /// formats `get_integer_key` behind the `key_prefix`, so stores keyed by
+    /// integers can use the `*_num` variants of the key methods instead.
+    pub fn get_key_name(&self, key_num: u64) -> String {
+        let file_key = usize::try_from(key_num)
+            .ok()
+            .and_then(|i| self.file_keys.get(i));
+
+        match file_key {
+            Some(key) => key.clone(),
+            None => (self.key_mapper)(key_num, self.ordered_insert),
+        }
+    }
+
+    /// The integer key of the key number `key_num`: the number itself with
+    /// ordered inserts, else its hash. Key files and custom key mappers are
+    /// not taken into account.
+    pub fn get_integer_key(&self, key_num: u64) -> u64 {
+        hash_key_num(key_num, self.ordered_insert, self.key_hash)
+    }
+
+    /// The next record to load and its table.
+    pub fn next_sequence_key(&self) -> (&Table, String) {
+        let (table, key_num) = self.next_sequence_key_index();
+
+        (table, self.get_key_name(key_num))
+    }
+
+    /// Like `next_sequence_key`, as an integer key; see `get_integer_key`.
+    pub fn next_sequence_key_num(&self) -> (&Table, u64) {
+        let (table, key_num) = self.next_sequence_key_index();
+
+        (table, self.get_integer_key(key_num))
+    }
+
+    fn next_sequence_key_index(&self) -> (&Table, u64) {
+        self.load_key_num(self.key_generator.next_value() as usize)
+    }
+
+    /// The table and key number of position `i` of the load sequence, as
+    /// found in `load_key_range`.
+    pub(crate) fn load_key_num(&self, i: usize) -> (&Table, u64) {
+        let pos = i - self.key_start;
+        let table = &self.tables[self.tables.partition_point(|t| t.load_offset <= pos) - 1];
+
+        (table, (self.key_start + pos - table.load_offset) as u64)
+    }
+
+    /// Reserves the next key number to insert into `table`. The key is not
+    /// requested by other transactions until it is passed to
+    /// `acknowledge_insert`.
+    pub fn next_insert_key_num(&self, table: &Table) -> u64 {
+        table.insert_key_sequence.next_value()
+    }
+
+    /// Like `next_insert_key_num`, also returning the integer key of the
+    /// reserved key number; see `get_integer_key`. The key number is still
+    /// the one to acknowledge.
+    pub fn next_insert_sequence_num(&self, table: &Table) -> (u64, u64) {
+        let key_num = self.next_insert_key_num(table);
+
+        (key_num, self.get_integer_key(key_num))
+    }
+
+    /// Marks an insert from `next_insert_key_num` as finished, normally once
+    /// its transaction has committed. Failed inserts must be acknowledged
+    /// too, or keys inserted after them are never requested.
+    pub fn acknowledge_insert(&self, table: &Table, key_num: u64) {
+        table.insert_key_sequence.acknowledge(key_num);
+    }
+
+    /// Number of keys inserted so far, i.e. one past the last key number
+    /// acknowledged without gaps, summed over the tables.
+    pub fn inserted_key_count(&self) -> u64 {
+        let inserted = self
+            .tables
+            .iter()
+            .map(|t| t.insert_key_sequence.last_value() - t.key_start)
+            .sum::<u64>();
+
+        self.key_start as u64 + inserted
+    }
+
+    /// Number of keys of each table inserted so far.
+    pub(crate) fn table_key_counts(&self) -> Vec<usize> {
+        self.tables
+            .iter()
+            .map(|t| (t.insert_key_sequence.last_value() - t.key_start) as usize)
+            .collect()
+    }
+
+    /// Moves the load sequence past `count` records without generating them,
+    /// leaving the workload as it would be after loading them.
+    pub(crate) fn skip_load(&self, count: usize) {
+        self.key_generator.advance(count as u64);
+    }
+
+    /// The first `count` positions of the load sequence, i.e. the ones
+    /// `next_sequence_key` hands out first. See `load_key_num` for their
+    /// tables and key numbers.
+    pub(crate) fn load_key_range(&self, count: usize) -> Range<usize> {
+        self.insert_start..self.insert_start + count
+    }
+
+    /// Takes the next `count` positions of the load sequence at once, like
+    /// as many calls to `next_sequence_key`.
+    pub(crate) fn next_load_positions(&self, count: usize) -> Range<usize> {
+        let start = self.key_generator.advance(count as u64) as usize;
+
+        start..start + count
+    }
+
+    /// Picks an existing key of `table` for a read, update or scan, from the
+    /// request distribution. See `next_transaction_key_for` for the
+    /// distribution of a given operation type.
+    pub fn next_transaction_key(&self, table: &Table) -> String {
+        self.get_key_name(self.sample_transaction_key_num(table))
+    }
+
+    /// Like `next_transaction_key`, as an integer key; see
+    /// `get_integer_key`.
+    pub fn next_transaction_key_num(&self, table: &Table) -> u64 {
+        self.get_integer_key(self.sample_transaction_key_num(table))
+    }
+
+    /// Like `next_transaction_key`, from the request distribution of `op`.
+    pub fn next_transaction_key_for(&self, op: Operation, table: &Table) -> String {
+        let key_num =
+            generator::with_rng(|rng| self.next_transaction_key_num_with(rng, table, Some(op)));
+
+        self.get_key_name(key_num)
+    }
+
+    /// With probability `read_miss_proportion`, a key for a read to request
+    /// that is never inserted. Keys from a custom key mapper or key file must
+    /// not start with `missing` for it to miss.
+    pub fn next_missing_key(&self) -> Option<String> {
+        generator::with_rng(|rng| self.next_missing_key_with(rng))
+    }
+
+    fn next_missing_key_with(&self, rng: &mut WorkloadRng) -> Option<String> {
+        if self.read_miss_proportion > 0.0 && rng.gen_bool(self.read_miss_proportion) {
+            Some(format!("{}{}", MISSING_KEY_PREFIX, rng.gen::<u64>()))
+        } else {
+            None
+        }
+    }
+
+    /// Picks an existing key to delete, like `next_transaction_key`.
+    pub fn next_delete_key(&self, table: &Table) -> String {
+        generator::with_rng(|rng| self.next_delete_key_with(rng, table))
+    }
+
+    fn next_delete_key_with(&self, rng: &mut WorkloadRng, table: &Table) -> String {
+        let key_num = match &table.deleted {
+            Some(deleted) => {
+                let mut deleted = deleted.lock().unwrap();
+                let key_num = sample_live_key(rng, table, None, &deleted);
+
+                deleted.insert(key_num);
+                key_num
+            }
+            None => table.sample_key_num(rng, None),
+        };
+
+        self.get_key_name(key_num)
+    }
+
+    /// Whether `key_num` of `table` was picked for deletion. Always false
+    /// unless deleted keys are skipped.
+    pub(crate) fn is_deleted(&self, table: &Table, key_num: u64) -> bool {
+        table
+            .deleted
+            .as_ref()
+            .is_some_and(|deleted| deleted.lock().unwrap().contains(&key_num))
+    }
+
+    fn sample_transaction_key_num(&self, table: &Table) -> u64 {
+        generator::with_rng(|rng| self.next_transaction_key_num_with(rng, table, None))
+    }
+
+    fn next_transaction_key_num_with(
+        &self,
+        rng: &mut WorkloadRng,
+        table: &Table,
+        op: Option<Operation>,
+    ) -> u64 {
+        match &table.deleted {
+            Some(deleted) => sample_live_key(rng, table, op, &deleted.lock().unwrap()),
+            None => table.sample_key_num(rng, op),
+        }
+    }
+
+    /// `n` keys of `table`, drawn in one pass like `n` calls of
+    /// `next_transaction_key`.
+    pub fn next_transaction_keys(&self, table: &Table, n: usize) -> Vec<String> {
+        generator::with_rng(|rng| self.next_transaction_key_nums_with(rng, table, n))
+            .into_iter()
+            .map(|key_num| self.get_key_name(key_num))
+            .collect()
+    }
+
+    fn next_transaction_key_nums_with(
+        &self,
+        rng: &mut WorkloadRng,
+        table: &Table,
+        n: usize,
+    ) -> Vec<u64> {
+        let mut key_nums = Vec::with_capacity(n);
+
+        match &table.deleted {
+            Some(deleted) => {
+                let deleted = deleted.lock().unwrap();
+                key_nums.extend((0..n).map(|_| sample_live_key(rng, table, None, &deleted)));
+            }
+            None => {
+                table.key_sampler.sample_many(rng, n, &mut key_nums);
+                for key_num in &mut key_nums {
+                    *key_num += table.key_start;
+                }
+            }
+        }
+
+        key_nums
+    }
+
+    /// A random value drawn from the field length distribution, ignoring
+    /// `field_len_overrides`. See `build_update` for the value of a field.
+    pub fn next_field_value(&self) -> String {
+        let mut value = String::new();
+        generator::with_rng(|rng| {
+            let len = self.field_len_generator.next(rng);
+            self.random_value_with(rng, len, &mut value)
+        });
+        value
+    }
+
+    /// Sets `value` to a random value of `len` bytes, reusing its allocation.
+    fn random_value_with(&self, rng: &mut WorkloadRng, len: usize, value: &mut String) {
+        let charset = match self.value_kind {
+            ValueKind::Alphanumeric => Some((ALPHANUMERIC, 6)),
+            ValueKind::Ascii => Some((PRINTABLE, 7)),
+            ValueKind::Hex => Some((HEX, 4)),
+            ValueKind::Bytes => None,
+        };
+
+        let mut buf = std::mem::take(value).into_bytes();
+        buf.clear();
+
+        match charset {
+            Some((charset, bits)) => {
+                random_chars(
+                    rng,
+                    charset,
+                    bits,
+                    self.random_len(len, charset.len()),
+                    &mut buf,
+                );
+                repeat_to_len(&mut buf, len);
+            }
+            None => {
+                let mut bytes = vec![0; self.random_len(len, 256)];
+                rng.fill(&mut bytes[..]);
+                repeat_to_len(&mut bytes, len);
+                base64_encode(&bytes, &mut buf);
+            }
+        }
+
+        *value = String::from_utf8(buf).unwrap();
+    }
+
+    /// Number of random symbols from an alphabet of `symbols` that compress
+    /// to `1 / value_compressibility` of `len` bytes when repeated to fill
+    /// them.
+    fn random_len(&self, len: usize, symbols: usize) -> usize {
+        if self.value_compressibility == 1.0 {
+            return len;
+        }
+
+        let bits = (symbols as f64).log2();
+        let random_len = len as f64 * 8.0 / (bits * self.value_compressibility);
+
+        (random_len.ceil() as usize).clamp(len.min(1), len)
+    }
+
+    /// Makes the values drawn on the calling thread from now on depend only
+    /// on the workload's seed and `stream`, e.g. the index of a worker
+    /// thread. Does nothing unless the spec sets a seed or an `rng` other
+    /// than `Small`.
+    pub fn seed_thread(&self, stream: usize) {
+        if self.seed.is_some() || self.rng_kind != RngKind::Small {
+            generator::set_rng(WorkloadRng::new(self.rng_kind, self.seed, stream));
+        }
+    }
+
+    pub fn next_scan_length(&self) -> usize {
+        generator::with_rng(|rng| self.next_scan_length_with(rng))
+    }
+
+    /// How long to pause after a transaction, if the spec sets a
+    /// `think_time_dist`.
+    pub fn next_think_time(&self) -> Option<Duration> {
+        self.think_time_generator.as_ref()?;
+
+        generator::with_rng(|rng| self.next_think_time_with(rng))
+    }
+
+    fn next_think_time_with(&self, rng: &mut WorkloadRng) -> Option<Duration> {
+        let generator = self.think_time_generator.as_ref()?;
+
+        Some(Duration::from_micros(generator.next(rng) as u64))
+    }
+
+    fn next_scan_length_with(&self, rng: &mut WorkloadRng) -> usize {
+        self.scan_len_generator.next(rng).min(self.max_scan_length)
+    }
+
+    /// Picks the first key and the length of a scan of `table`. With ordered
+    /// inserts the scan stops at the last key inserted so far.
+    pub fn next_scan(&self, table: &Table) -> (String, usize) {
+        generator::with_rng(|rng| self.next_scan_with(rng, table))
+    }
+
+    fn next_scan_with(&self, rng: &mut WorkloadRng, table: &Table) -> (String, usize) {
+        let (key_num, length) = self.next_scan_num_with(rng, table);
+        (self.get_key_name(key_num), length)
+    }
+
+    fn next_scan_num_with(&self, rng: &mut WorkloadRng, table: &Table) -> (u64, usize) {
+        let key_num = self.next_transaction_key_num_with(rng, table, Some(Operation::Scan));
+        let mut length = self.next_scan_length_with(rng);
+
+        if self.ordered_insert {
+            let end = table.insert_key_sequence.last_value();
+            let remaining = end.saturating_sub(key_num).max(1);
+            length = length.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        }
+
+        (key_num, length)
+    }
+
+    /// Length distribution of the field at `index`.
+    fn field_len(&self, index: usize) -> &dyn Generator<usize> {
+        match self.field_len_overrides.get(index) {
+            Some(Some(generator)) => generator.as_ref(),
+            _ => self.field_len_generator.as_ref(),
+        }
+    }
+
+    /// Sets `value` to the value of the field at `index` of record `key`:
+    /// random, or derived from both when checking data integrity.
+    fn field_value_with(&self, rng: &mut WorkloadRng, key: &str, index: usize, value: &mut String) {
+        let len = self.field_len(index).next(rng);
+
+        if self.data_integrity {
+            deterministic_value(key, &self.field_names[index], len, value)
+        } else {
+            self.random_value_with(rng, len, value)
+        }
+    }
+
+    pub fn build_values(&self, key: &str) -> Vec<(String, String)> {
+        let mut values = Vec::with_capacity(self.field_names.len());
+        self.build_values_into(key, &mut values);
+        values
+    }
+
+    /// Like `build_values`, reusing the strings already in `values`. Once
+    /// they are large enough this allocates nothing.
+    pub fn build_values_into(&self, key: &str, values: &mut Vec<(String, String)>) {
+        generator::with_rng(|rng| self.build_values_with(rng, key, values))
+    }
+
+    fn build_values_with(
+        &self,
+        rng: &mut WorkloadRng,
+        key: &str,
+        values: &mut Vec<(String, String)>,
+    ) {
+        let fields = self.field_names.len();
+        let len = fields + self.timestamp_field.is_some() as usize;
+        values.truncate(len);
+        values.resize_with(len, Default::default);
+
+        for (index, (name, value)) in values[..fields].iter_mut().enumerate() {
+            name.clear();
+            name.push_str(&self.field_names[index]);
+            self.field_value_with(rng, key, index, value);
+        }
+
+        if let Some(field) = &self.timestamp_field {
+            let (name, value) = &mut values[fields];
+            name.clear();
+            name.push_str(field);
+            self.timestamp_value(value);
+        }
+    }
+
+    /// Sets `value` to the next timestamp: microseconds since the epoch, or
+    /// a counter if the workload is seeded, zero-padded so that later values
+    /// also sort after earlier ones as strings. Never repeats a value.
+    fn timestamp_value(&self, value: &mut String) {
+        let now = match self.seed {
+            Some(_) => 0,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_micros() as u64),
+        };
+        let last = self
+            .last_timestamp
+            .fetch_update(AtomicOrdering::Relaxed, AtomicOrdering::Relaxed, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+
+        value.clear();
+        write!(value, "{:020}", now.max(last + 1)).unwrap();
+    }
+
+    fn push_timestamp(&self, values: &mut Vec<(String, String)>) {
+        if let Some(field) = &self.timestamp_field {
+            let mut value = String::new();
+            self.timestamp_value(&mut value);
+            values.push((field.clone(), value));
+        }
+    }
+
+    fn is_timestamp_field(&self, name: &str) -> bool {
+        self.timestamp_field.as_deref() == Some(name)
+    }
+
+    /// Whether `values` read from record `key` are the ones written to it.
+    /// Always true unless checking data integrity.
+    pub fn check_values(&self, key: &str, values: &[(String, String)]) -> bool {
+        let mut expected = String::new();
+
+        !self.data_integrity
+            || values.iter().all(|(name, value)| {
+                if self.is_timestamp_field(name) {
+                    return true;
+                }
+
+                let len = match self.field_names.iter().position(|field| field == name) {
+                    Some(index) => self.field_len(index).next_value(),
+                    None => return false,
+                };
+                deterministic_value(key, name, len, &mut expected);
+                *value == expected
+            })
+    }
+
+    /// Like `check_values` for a scanned row, whose key is read from the start
+    /// of its values.
+    pub fn check_row(&self, values: &[(String, String)]) -> bool {
+        !self.data_integrity
+            || values
+                .iter()
+                .filter(|(name, _)| !self.is_timestamp_field(name))
+                .all(|(name, value)| match value.find(&format!(":{}:", name)) {
+                    Some(pos) => self.check_values(&value[..pos], &[(name.clone(), value.clone())]),
+                    None => false,
+                })
+    }
+
+    /// Names of all fields of a record, in the order `build_values` writes
+    /// them, ending with the timestamp field if there is one.
+    pub fn field_names(&self) -> Vec<String> {
+        let mut names = self.field_names.clone();
+        names.extend(self.timestamp_field.clone());
+        names
+    }
+
+    pub fn next_field_name(&self) -> &str {
+        generator::with_rng(|rng| self.next_field_name_with(rng))
+    }
+
+    fn next_field_name_with(&self, rng: &mut WorkloadRng) -> &str {
+        &self.field_names[self.field_generator.next(rng)]
+    }
+
+    /// A fresh value for one random field. Unlike `next_update_values`, this
+    /// leaves out the timestamp field.
+    pub fn build_update(&self, key: &str) -> (String, String) {
+        generator::with_rng(|rng| self.build_update_with(rng, key))
+    }
+
+    fn build_update_with(&self, rng: &mut WorkloadRng, key: &str) -> (String, String) {
+        let index = self.field_generator.next(rng);
+        let mut value = String::new();
+        self.field_value_with(rng, key, index, &mut value);
+
+        (self.field_names[index].clone(), value)
+    }
+
+    /// `count` distinct field names, picked uniformly at random.
+    pub fn next_field_names(&self, count: usize) -> Vec<String> {
+        generator::with_rng(|rng| self.next_field_names_with(rng, count))
+    }
+
+    fn next_field_names_with(&self, rng: &mut WorkloadRng, count: usize) -> Vec<String> {
+        self.next_field_indices_with(rng, count)
+            .into_iter()
+            .map(|i| self.field_names[i].clone())
+            .collect()
+    }
+
+    fn next_field_indices_with(&self, rng: &mut WorkloadRng, count: usize) -> Vec<usize> {
+        if count == 1 {
+            return vec![self.field_generator.next(rng)];
+        }
+
+        rand::seq::index::sample(rng, self.field_names.len(), count).into_vec()
+    }
+
+    /// Fields to request in a read or scan, `None` meaning all fields.
+    pub fn next_read_fields(&self) -> Option<Vec<String>> {
+        generator::with_rng(|rng| self.next_read_fields_with(rng))
+    }
+
+    fn next_read_fields_with(&self, rng: &mut WorkloadRng) -> Option<Vec<String>> {
+        if self.read_all_fields {
+            None
+        } else {
+            Some(self.next_field_names_with(rng, self.fields_per_read))
+        }
+    }
+
+    /// Values to write in an update: every field, or `fields_per_write`
+    /// random ones and the timestamp field.
+    pub fn next_update_values(&self, key: &str) -> Vec<(String, String)> {
+        generator::with_rng(|rng| self.next_update_values_with(rng, key))
+    }
+
+    fn next_update_values_with(&self, rng: &mut WorkloadRng, key: &str) -> Vec<(String, String)> {
+        if self.write_all_fields {
+            let mut values = Vec::with_capacity(self.field_names.len() + 1);
+            self.build_values_with(rng, key, &mut values);
+            return values;
+        }
+
+        let mut values = if self.fields_per_write == 1 {
+            vec![self.build_update_with(rng, key)]
+        } else {
+            let fields = self.next_field_indices_with(rng, self.fields_per_write);
+            self.field_values_with(rng, key, fields)
+        };
+        self.push_timestamp(&mut values);
+        values
+    }
+
+    /// Values to write in the write half of a read-modify-write that read
+    /// `read_fields`: fresh values for the fields read, or those of an
+    /// update if it read every field.
+    pub fn next_rmw_values(
+        &self,
+        key: &str,
+        read_fields: Option<&[String]>,
+    ) -> Vec<(String, String)> {
+        generator::with_rng(|rng| self.next_rmw_values_with(rng, key, read_fields))
+    }
+
+    fn next_rmw_values_with(
+        &self,
+        rng: &mut WorkloadRng,
+        key: &str,
+        read_fields: Option<&[String]>,
+    ) -> Vec<(String, String)> {
+        match read_fields {
+            Some(names) => {
+                let fields = names
+                    .iter()
+                    .filter_map(|name| self.field_names.iter().position(|field| field == name))
+                    .collect();
+                let mut values = self.field_values_with(rng, key, fields);
+                self.push_timestamp(&mut values);
+                values
+            }
+            None => self.next_update_values_with(rng, key),
+        }
+    }
+
+    fn field_values_with(
+        &self,
+        rng: &mut WorkloadRng,
+        key: &str,
+        fields: Vec<usize>,
+    ) -> Vec<(String, String)> {
+        fields
+            .into_iter()
+            .map(|index| {
+                let mut value = String::new();
+                self.field_value_with(rng, key, index, &mut value);
+                (self.field_names[index].clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// The operations and keys of one worker thread, drawn with the session's
+/// own random number generator. See `CoreWorkload::session`.
+pub struct WorkloadSession<'a> {
+    workload: &'a CoreWorkload,
+    rng: WorkloadRng,
+    /// Keys drawn by this session, added to the workload's counts when it
+    /// is dropped.
+    key_frequency: Option<SpaceSaving<(usize, u64)>>,
+}
+
+impl Drop for WorkloadSession<'_> {
+    fn drop(&mut self) {
+        if let Some(counts) = &self.key_frequency {
+            if let Some(summary) = &mut *self.workload.key_frequency.lock().unwrap() {
+                summary.merge(counts);
+            }
+        }
+    }
+}
+
+impl<'a> WorkloadSession<'a> {
+    pub fn workload(&self) -> &'a CoreWorkload {
+        self.workload
+    }
+
+    pub fn next_operation(&mut self) -> Operation {
+        self.workload.op_generator.next(&mut self.rng)
+    }
+
+    pub fn next_operations(&mut self, n: usize) -> Vec<Operation> {
+        let mut ops = Vec::with_capacity(n);
+        self.workload
+            .op_generator
+            .sample_many_with(&mut self.rng, n, &mut ops);
+        ops
+    }
+
+    pub fn next_table(&mut self) -> &'a Table {
+        self.workload.next_table_with(&mut self.rng)
+    }
+
+    pub fn next_transaction_key(&mut self, table: &Table) -> String {
+        let key_num = self
+            .workload
+            .next_transaction_key_num_with(&mut self.rng, table, None);
+        self.count_key(table, key_num);
+
+        self.workload.get_key_name(key_num)
+    }
+
+    pub fn next_transaction_key_for(&mut self, op: Operation, table: &Table) -> String {
+        let key_num = self
+            .workload
+            .next_transaction_key_num_with(&mut self.rng, table, Some(op));
+        self.count_key(table, key_num);
+
+        self.workload.get_key_name(key_num)
+    }
+
+    pub fn next_transaction_keys(&mut self, table: &Table, n: usize) -> Vec<String> {
+        let key_nums = self
+            .workload
+            .next_transaction_key_nums_with(&mut self.rng, table, n);
+
+        key_nums
+            .into_iter()
+            .map(|key_num| {
+                self.count_key(table, key_num);
+                self.workload.get_key_name(key_num)
+            })
+            .collect()
+    }
+
+    fn count_key(&mut self, table: &Table, key_num: u64) {
+        if let Some(summary) = &mut self.key_frequency {
+            summary.record((table.index, key_num));
+        }
+    }
+
+    pub fn next_missing_key(&mut self) -> Option<String> {
+        self.workload.next_missing_key_with(&mut self.rng)
+    }
+
+    pub fn next_delete_key(&mut self, table: &Table) -> String {
+        self.workload.next_delete_key_with(&mut self.rng, table)
+    }
+
+    pub fn next_think_time(&mut self) -> Option<Duration> {
+        self.workload.next_think_time_with(&mut self.rng)
+    }
+
+    pub fn next_scan(&mut self, table: &Table) -> (String, usize) {
+        let (key_num, length) = self.workload.next_scan_num_with(&mut self.rng, table);
+        self.count_key(table, key_num);
+
+        (self.workload.get_key_name(key_num), length)
+    }
+
+    pub fn next_read_fields(&mut self) -> Option<Vec<String>> {
+        self.workload.next_read_fields_with(&mut self.rng)
+    }
+
+    pub fn next_update_values(&mut self, key: &str) -> Vec<(String, String)> {
+        self.workload.next_update_values_with(&mut self.rng, key)
+    }
+
+    pub fn next_rmw_values(
+        &mut self,
+        key: &str,
+        read_fields: Option<&[String]>,
+    ) -> Vec<(String, String)> {
+        self.workload
+            .next_rmw_values_with(&mut self.rng, key, read_fields)
+    }
+
+    pub fn build_values_into(&mut self, key: &str, values: &mut Vec<(String, String)>) {
+        self.workload.build_values_with(&mut self.rng, key, values)
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut WorkloadRng {
+        &mut self.rng
+    }
+}
+
+/// Samples keys by `dist` from a table holding `record_count` records to
+/// start with.
+fn key_sampler(
+    spec: &WorkloadSpec,
+    dist: &DistributionSpec,
+    record_count: usize,
+    insert_key_sequence: &Arc<generator::AcknowledgedCounterGenerator>,
+) -> Result<Box<dyn Generator<u64>>> {
+    Ok(match *dist {
+        DistributionSpec::Constant(key_num) => {
+            Box::new(generator::ConstGenerator::new(key_num as u64))
+        }
+        DistributionSpec::Uniform(min, max) => Box::new(generator::GrowingUniformGenerator::new(
+            insert_key_sequence.clone(),
+            spec.key_start() as u64,
+            min,
+            max,
+        )),
+        DistributionSpec::Zipfian(0, s) => Box::new(generator::GrowingZipfianGenerator::new(
+            insert_key_sequence.clone(),
+            spec.key_start() as u64,
+            s,
+            spec.scramble_zipfian,
+        )?),
+        DistributionSpec::Zipfian(items, s) => {
+            let num_items = items.min(record_count);
+
+            if spec.scramble_zipfian {
+                Box::new(generator::ScrambledZipfianGenerator::new(num_items, s)?)
+            } else {
+                Box::new(generator::zipfian_gen::<u64>(num_items - 1, s)?)
+            }
+        }
+        DistributionSpec::Sequential => Box::new(generator::SequentialGenerator::new(record_count)),
+        DistributionSpec::Latest => Box::new(generator::SkewedLatestGenerator::new(
+            insert_key_sequence.clone(),
+            spec.key_start() as u64,
+        )),
+        _ => return Err(Error::InvalidArgument("request distribution".to_owned())),
+    })
+}
+
+/// Reads the buckets of a histogram file, see
+/// `DistributionSpec::histogram_from_path`.
+fn field_len_generator(dist: DistributionSpec) -> Result<Box<dyn Generator<usize>>> {
+    Ok(match dist {
+        DistributionSpec::Constant(c) => Box::new(generator::ConstGenerator::new(c)),
+        DistributionSpec::Uniform(min, max) => Box::new(generator::uniform_gen(min, max)),
+        DistributionSpec::Histogram(buckets) => Box::new(generator::histogram_gen(buckets)),
+        DistributionSpec::HistogramFile(path) => {
+            Box::new(generator::histogram_gen(read_histogram(&path)?))
+        }
+        DistributionSpec::Pareto { scale, shape, max } => {
+            Box::new(generator::ParetoGenerator::new(scale, shape, max))
+        }
+        DistributionSpec::Normal { mean, std_dev, max } => {
+            Box::new(generator::NormalGenerator::new(mean, std_dev, max)?)
+        }
+        DistributionSpec::Exponential { mean, max } => {
+            Box::new(generator::ExponentialLengthGenerator::new(mean, max))
+        }
+        _ => {
+            return Err(Error::InvalidArgument(
+                "field length distribution".to_owned(),
+            ))
+        }
+    })
+}
+
+fn read_histogram(path: &Path) -> Result<Vec<(usize, f64)>> {
+    let data = fs::read_to_string(path)?;
+    let mut block_size = 1;
+    let mut buckets = Vec::new();
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid =
+            |what: &str| Error::InvalidSpec(format!("{}:{}: {}", path.display(), i + 1, what));
+        let mut columns = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|c| !c.is_empty());
+
+        match (columns.next(), columns.next(), columns.next()) {
+            (Some("BlockSize"), Some(size), None) => {
+                block_size = size
+                    .parse()
+                    .map_err(|_| invalid(&format!("invalid block size '{}'", size)))?
+            }
+            (Some(length), Some(weight), None) => {
+                let length = length
+                    .parse::<usize>()
+                    .map_err(|_| invalid(&format!("invalid length '{}'", length)))?;
+                let weight = match weight.parse::<f64>() {
+                    Ok(weight) if weight >= 0.0 && weight.is_finite() => weight,
+                    _ => return Err(invalid(&format!("invalid weight '{}'", weight))),
+                };
+
+                // Upstream histograms list empty buckets too.
+                if weight > 0.0 {
+                    buckets.push((length * block_size, weight));
+                }
+            }
+            _ => return Err(invalid("expected a length and a weight")),
+        }
+    }
+
+    if buckets.is_empty() {
+        return Err(Error::InvalidSpec(format!(
+            "{}: histogram has no buckets",
+            path.display()
+        )));
+    }
+
+    Ok(buckets)
+}
+
+/// Reads the keys and weights of a key file, see `DistributionSpec::File`.
+fn read_key_file(path: &Path) -> Result<Vec<(String, f64)>> {
+    let file = fs::File::open(path)?;
+    let mut keys = Vec::new();
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let invalid =
+            |what: &str| Error::InvalidSpec(format!("{}:{}: {}", path.display(), i + 1, what));
+
+        let mut columns = line.split_whitespace();
+        let (key, weight) = match (columns.next(), columns.next(), columns.next()) {
+            (None, _, _) => continue,
+            (Some(key), None, _) => (key, 1.0),
+            (Some(key), Some(weight), None) => match weight.parse::<f64>() {
+                Ok(weight) if weight > 0.0 && weight.is_finite() => (key, weight),
+                _ => return Err(invalid(&format!("invalid weight '{}'", weight))),
+            },
+            _ => return Err(invalid("expected a key and an optional weight")),
+        };
+
+        keys.push((key.to_owned(), weight));
+    }
+
+    if keys.is_empty() {
+        return Err(Error::InvalidSpec(format!(
+            "{} lists no keys",
+            path.display()
+        )));
+    }
+
+    let mut seen = HashSet::with_capacity(keys.len());
+    if let Some((key, _)) = keys.iter().find(|(key, _)| !seen.insert(key.as_str())) {
+        return Err(Error::InvalidSpec(format!(
+            "{} lists key '{}' more than once",
+            path.display(),
+            key
+        )));
+    }
+
+    Ok(keys)
+}
+
+fn sample_live_key(
+    rng: &mut WorkloadRng,
+    table: &Table,
+    op: Option<Operation>,
+    deleted: &HashSet<u64>,
+) -> u64 {
+    // Give up eventually, so a workload that deleted most of its keys still
+    // makes progress.
+    let mut key_num = table.sample_key_num(rng, op);
+    for _ in 0..MAX_DELETED_RESAMPLES {
+        if !deleted.contains(&key_num) {
+            break;
+        }
+
+        key_num = table.sample_key_num(rng, op);
+    }
+
+    key_num
+}
+
+/// Appends `len` characters from `charset`, which must be shorter than
+/// `1 << bits`. Each draw is split into `bits`-bit indices, skipping those
+/// past the end of the charset.
+fn random_chars(rng: &mut WorkloadRng, charset: &[u8], bits: u32, len: usize, value: &mut Vec<u8>) {
+    let len = value.len() + len;
+    value.reserve(len);
+
+    while value.len() < len {
+        let mut draw = rng.gen::<u64>();
+
+        for _ in 0..64 / bits {
+            let i = (draw & ((1 << bits) - 1)) as usize;
+            draw >>= bits;
+
+            if i < charset.len() && value.len() < len {
+                value.push(charset[i]);
+            }
+        }
+    }
+}
+
+/// Repeats the contents of `chunk` up to `len` bytes.
+fn repeat_to_len(chunk: &mut Vec<u8>, len: usize) {
+    let random_len = chunk.len();
+
+    while chunk.len() < len {
+        let n = random_len.min(len - chunk.len());
+        chunk.extend_from_within(..n);
+    }
+}
+
+/// Appends `bytes` in standard base64 with padding.
+fn base64_encode(bytes: &[u8], encoded: &mut Vec<u8>) {
+    encoded.reserve(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 63) as usize]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+}
+
+/// Sets `value` to `key:field:` followed by characters seeded by both, cut to
+/// `len` bytes.
+fn deterministic_value(key: &str, field: &str, len: usize, value: &mut String) {
+    value.clear();
+    value.push_str(key);
+    value.push(':');
+    value.push_str(field);
+    value.push(':');
+    let rng = SmallRng::seed_from_u64(xxh64::hash64(value.as_bytes()));
+
+    value.extend(
+        rng.sample_iter(&Alphanumeric)
+            .take(len.saturating_sub(value.len())),
+    );
+    value.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_matches_json() {
+        for name in &["a", "b", "c", "d"] {
+            let json =
+                WorkloadSpec::from_path(format!("workloads/workload_{}.json", name)).unwrap();
+            let toml =
+                WorkloadSpec::from_path(format!("workloads/workload_{}.toml", name)).unwrap();
+
+            assert_eq!(json, toml, "workload_{}", name);
+        }
+    }
+
+    #[test]
+    fn test_spec_round_trip() {
+        let spec = WorkloadSpec::default()
+            .request_zipfian(0.99)
+            .record_count(1000)
+            .operation_count(2000);
+
+        let toml = toml::to_string(&toml::Value::try_from(&spec).unwrap()).unwrap();
+        assert!(toml.contains("[request_dist]\nitems = 0\ntheta = 0.99\ntype = \"zipfian\"\n"));
+        assert_eq!(WorkloadSpec::from_toml_str(&toml).unwrap(), spec);
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        let err = WorkloadSpec::from_toml_str("record_count = \"many\"").unwrap_err();
+
+        match err {
+            Error::InvalidSpec(msg) => assert!(msg.contains("record_count")),
+            err => panic!("unexpected error: {}", err),
+        }
+
+        let partial = WorkloadSpec::from_toml_str("record_count = 10").unwrap();
+        assert_eq!(partial, WorkloadSpec::default().record_count(10));
+    }
+
+    #[test]
+    fn test_distribution_errors() {
+        let err = WorkloadSpec::from_json_str(r#"{"request_dist": {"type": "zipfian"}}"#);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("request_dist: zipfian distribution: missing field `theta`"));
+
+        let err = WorkloadSpec::from_toml_str("scan_len_dist = { type = \"uniform\", min = -1 }");
+        let msg = err.unwrap_err().to_string();
+        assert!(
+            msg.contains("uniform distribution: invalid value"),
+            "{}",
+            msg
+        );
+        assert!(msg.contains("for key `scan_len_dist`"), "{}", msg);
+
+        let err = WorkloadSpec::from_json_str(r#"{"field_len_dist": {"Zipfian": [0]}}"#);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("field_len_dist: expected a distribution with a `type`"));
+
+        let spec = WorkloadSpec::from_json_str(
+            r#"{"request_dist": "Latest", "scan_len_dist": {"Uniform": [1, 100]}}"#,
+        )
+        .unwrap();
+        assert_eq!(spec.request_dist, DistributionSpec::Latest);
+        assert_eq!(spec.scan_len_dist, DistributionSpec::Uniform(1, 100));
+    }
+
+    #[test]
+    fn test_builder() {
+        let spec = WorkloadSpec::default()
+            .table("accounts")
+            .ordered_insert(true)
+            .insert_start(100)
+            .request_latest()
+            .scan_len_zipfian(50, 0.9)
+            .field_len_uniform(10, 20);
+
+        assert_eq!(spec.get_table(), "accounts");
+        assert!(spec.get_ordered_insert());
+        assert_eq!(spec.get_insert_start(), 100);
+        assert_eq!(spec.get_request_dist(), &DistributionSpec::Latest);
+        assert_eq!(
+            spec.get_scan_len_dist(),
+            &DistributionSpec::Zipfian(50, 0.9)
+        );
+        assert_eq!(
+            spec.get_field_len_dist(),
+            &DistributionSpec::Uniform(10, 20)
+        );
+        assert_eq!(spec.get_tables(), None);
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+
+        let spec = spec
+            .tables(vec![("a".to_owned(), 1.0), ("b".to_owned(), 3.0)])
+            .scan_len_uniform(1, 10)
+            .record_count(8);
+        assert_eq!(spec.get_tables(), Some(vec![("a", 1.0), ("b", 3.0)]));
+        assert_eq!(spec.get_scan_len_dist(), &DistributionSpec::Uniform(1, 10));
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert_eq!(workload.tables()[1].name(), "b");
+    }
+
+    #[test]
+    fn test_partial_spec() {
+        assert_eq!(
+            WorkloadSpec::from_json_str("{}").unwrap(),
+            WorkloadSpec::default()
+        );
+        assert_eq!(
+            WorkloadSpec::from_toml_str("").unwrap(),
+            WorkloadSpec::default()
+        );
+
+        let json = serde_json::to_string(&WorkloadSpec::default()).unwrap();
+        assert_eq!(
+            WorkloadSpec::from_json_str_strict(&json).unwrap(),
+            WorkloadSpec::default()
+        );
+
+        let json = r#"{"record_count": 100000, "operation_count": 1000000,
+                       "read_proportion": 0.5, "update_proportion": 0.5}"#;
+        let expected = WorkloadSpec::default()
+            .record_count(100000)
+            .operation_count(1000000)
+            .read_proportion(0.5)
+            .update_proportion(0.5);
+        assert_eq!(WorkloadSpec::from_json_str_strict(json).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let json = r#"{"record_count": 10, "raed_proportion": 0.5}"#;
+        assert_eq!(
+            WorkloadSpec::from_json_str(json).unwrap(),
+            WorkloadSpec::default().record_count(10)
+        );
+        match WorkloadSpec::from_json_str_strict(json) {
+            Err(Error::InvalidSpec(msg)) => assert_eq!(msg, "unknown fields: raed_proportion"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let toml = "recordcount = 10\n[[tables]]\nname = \"a\"\nweight = 1.0\nwieght = 2.0\n";
+        assert!(WorkloadSpec::from_toml_str(toml).is_ok());
+        match WorkloadSpec::from_toml_str_strict(toml) {
+            Err(Error::InvalidSpec(msg)) => {
+                assert_eq!(msg, "unknown fields: recordcount, tables.0.wieght")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_effective_spec() {
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .operation_count(10)
+            .read_proportion(1.0)
+            .update_proportion(1.0)
+            .update_request_dist(DistributionSpec::Uniform(0, 9));
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let effective = workload.effective_spec();
+        assert_eq!(effective.get_read_proportion(), 0.5);
+        assert_eq!(effective.get_update_proportion(), 0.5);
+        assert_eq!(effective.get_record_count(), 100);
+
+        let description = workload.describe();
+        assert!(description.contains("Tables: usertable (100 records)\n"));
+        assert!(description.contains("Operations: 10 of READ 50.0%, UPDATE 50.0%\n"));
+        assert!(description.contains("  UPDATE keys: Uniform(0, 9)\n"));
+        assert_eq!(
+            CoreWorkload::new(effective).unwrap().describe(),
+            description
+        );
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let overrides = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let mut spec = WorkloadSpec::workload_a();
+        spec.apply_overrides(&overrides(&[
+            ("record_count", "5000"),
+            ("read_proportion", "0.9"),
+            ("table", "orders"),
+            ("seed", "7"),
+            (
+                "request_dist",
+                r#"{"type": "uniform", "min": 0, "max": 99}"#,
+            ),
+        ]))
+        .unwrap();
+
+        let expected = WorkloadSpec::workload_a()
+            .record_count(5000)
+            .read_proportion(0.9)
+            .table("orders")
+            .seed(7)
+            .request_uniform(0, 99);
+        assert_eq!(spec, expected);
+
+        for (pairs, msg) in &[
+            (
+                &[("recordcount", "10")][..],
+                "unknown workload field 'recordcount'",
+            ),
+            (
+                &[("record_count", "10"), ("operation_count", "lots")][..],
+                "invalid value 'lots' for operation_count",
+            ),
+            (
+                &[("record_count", "1.5")][..],
+                "invalid value '1.5' for record_count",
+            ),
+        ] {
+            match spec.apply_overrides(&overrides(pairs)) {
+                Err(Error::InvalidArgument(e)) => assert!(e.starts_with(msg), "{}", e),
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+        assert_eq!(spec, expected);
+
+        std::env::set_var("YCSB_TEST_OVERRIDES_RECORD_COUNT", "42");
+        let env = WorkloadSpec::overrides_from_env("YCSB_TEST_OVERRIDES_");
+        assert_eq!(env, overrides(&[("record_count", "42")]));
+        spec.apply_overrides(&env).unwrap();
+        assert_eq!(spec.get_record_count(), 42);
+    }
+
+    #[test]
+    fn test_extends() {
+        let dir = std::env::temp_dir().join("ycsb_rs_test_extends");
+        fs::create_dir_all(dir.join("base")).unwrap();
+        let write = |name: &str, data: &str| fs::write(dir.join(name), data).unwrap();
+
+        write(
+            "base/root.json",
+            r#"{"record_count": 500, "operation_count": 100, "read_proportion": 0.5}"#,
+        );
+        write(
+            "base/middle.toml",
+            "extends = \"root.json\"\nupdate_proportion = 0.5\n",
+        );
+        write(
+            "child.json",
+            r#"{"extends": "base/middle.toml", "read_proportion": 0.25}"#,
+        );
+
+        let expected = WorkloadSpec::default()
+            .record_count(500)
+            .operation_count(100)
+            .read_proportion(0.25)
+            .update_proportion(0.5);
+        assert_eq!(
+            WorkloadSpec::from_path(dir.join("child.json")).unwrap(),
+            expected
+        );
+
+        write("cycle_a.json", r#"{"extends": "cycle_b.json"}"#);
+        write("cycle_b.json", r#"{"extends": "cycle_a.json"}"#);
+        match WorkloadSpec::from_path(dir.join("cycle_a.json")) {
+            Err(Error::InvalidSpec(msg)) => {
+                assert!(msg.ends_with("cycle_a.json: extends itself"), "{}", msg);
+                assert_eq!(msg.matches(" -> ").count(), 2, "{}", msg);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        write(
+            "bad.json",
+            r#"{"extends": "base/root.json", "record_count": "many"}"#,
+        );
+        match WorkloadSpec::from_path(dir.join("bad.json")) {
+            Err(Error::InvalidSpec(msg)) => {
+                assert!(msg.contains("bad.json -> "), "{}", msg);
+                assert!(msg.contains("record_count"), "{}", msg);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        write("missing.json", r#"{"extends": "nowhere.json"}"#);
+        assert!(WorkloadSpec::from_path(dir.join("missing.json"))
+            .unwrap_err()
+            .to_string()
+            .contains("nowhere.json"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_properties() {
+        let spec = WorkloadSpec::from_path("workloads/workloada.properties").unwrap();
+
+        let expected = WorkloadSpec::default()
+            .record_count(1000)
+            .operation_count(1000)
+            .read_proportion(0.5)
+            .update_proportion(0.5)
+            .request_zipfian(0.99);
+        assert_eq!(spec, expected);
+
+        let props = "fieldcount: 5\nmaxscanlength 100\nscanlengthdistribution=zipfian\n\
+                     insertorder=ordered\nthreadcount=4\n";
+        let (spec, warnings) = WorkloadSpec::from_properties(props.as_bytes()).unwrap();
+
+        assert_eq!(spec.field_count, 5);
+        assert_eq!(spec.scan_len_dist, DistributionSpec::Zipfian(100, 0.99));
+        assert_eq!(spec.max_scan_length, 100);
+        assert!(spec.ordered_insert);
+        assert_eq!(warnings, vec!["ignoring unknown property 'threadcount'"]);
+
+        assert!(WorkloadSpec::from_properties("readproportion=lots".as_bytes()).is_err());
+        assert!(WorkloadSpec::from_properties("requestdistribution=hotspot".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_presets() {
+        let presets = vec![
+            ("a", WorkloadSpec::workload_a()),
+            ("b", WorkloadSpec::workload_b()),
+            ("c", WorkloadSpec::workload_c()),
+            ("d", WorkloadSpec::workload_d()),
+        ];
+
+        for (name, preset) in presets {
+            let file =
+                WorkloadSpec::from_path(format!("workloads/workload_{}.json", name)).unwrap();
+            assert_eq!(preset, file, "workload_{}", name);
+        }
+
+        let e = WorkloadSpec::workload_e();
+        assert_eq!((e.scan_proportion, e.insert_proportion), (0.95, 0.05));
+        assert_eq!(e.request_dist, DistributionSpec::Zipfian(0, 0.99));
+        assert_eq!(e.scan_len_dist, DistributionSpec::Uniform(1, 100));
+
+        let f = WorkloadSpec::workload_f();
+        assert_eq!((f.read_proportion, f.rmw_proportion), (0.5, 0.5));
+        assert_eq!(f.update_proportion, 0.0);
+
+        let presets = vec![
+            WorkloadSpec::workload_a(),
+            WorkloadSpec::workload_b(),
+            WorkloadSpec::workload_c(),
+            WorkloadSpec::workload_d(),
+            WorkloadSpec::workload_e(),
+            WorkloadSpec::workload_f(),
+        ];
+
+        for preset in presets {
+            let spec = preset.record_count(1000).operation_count(500);
+            assert_eq!(spec.get_record_count(), 1000);
+            assert!(CoreWorkload::new(spec).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(WorkloadSpec::default().record_count(1).validate().is_ok());
+        assert!(WorkloadSpec::default()
+            .record_count(0)
+            .read_proportion(0.0)
+            .update_proportion(0.0)
+            .insert_proportion(1.0)
+            .validate()
+            .is_ok());
+
+        let problems = |spec: WorkloadSpec| match spec.validate() {
+            Err(Error::InvalidArgument(msg)) => msg,
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        };
+
+        let msg = problems(
+            WorkloadSpec::default()
+                .record_count(0)
+                .read_proportion(1.5)
+                .field_count(0)
+                .request_zipfian(0.0),
+        );
+        assert!(msg.contains("read_proportion must be in [0, 1], got 1.5"));
+        assert!(msg.contains("record_count must be at least 1"));
+        assert!(msg.contains("field_count must be at least 1"));
+        assert!(msg.contains("request_dist: zipfian theta"));
+
+        let msg = problems(
+            WorkloadSpec::default()
+                .record_count(10)
+                .read_proportion(0.0)
+                .update_proportion(0.0),
+        );
+        assert_eq!(msg, "operation proportions sum to zero");
+
+        let spec = WorkloadSpec {
+            field_len_dist: DistributionSpec::Latest,
+            ..WorkloadSpec::default().record_count(10)
+        };
+        assert!(problems(spec).contains(
+            "field_len_dist must be constant, uniform, histogram, pareto, normal or exponential"
+        ));
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_histogram(vec![(100, 1.0), (200, 0.0)]);
+        assert!(problems(spec).contains("histogram weight for length 200 must be positive"));
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_histogram(Vec::new());
+        assert!(problems(spec).contains("histogram has no buckets"));
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_pareto(100.0, 0.0, 50);
+        let msg = problems(spec);
+        assert!(msg.contains("pareto shape must be positive"));
+        assert!(msg.contains("pareto max 50 is below the scale 100"));
+    }
+
+    #[test]
+    fn test_degenerate_counts() {
+        let error = |spec: WorkloadSpec| CoreWorkload::new(spec).err().unwrap().to_string();
+
+        assert!(error(WorkloadSpec::default().record_count(0)).contains("record_count"));
+        assert!(
+            error(WorkloadSpec::default().record_count(1).field_count(0))
+                .contains("field_count must be at least 1")
+        );
+
+        let spec = WorkloadSpec {
+            request_dist: DistributionSpec::Zipfian(5, 0.99),
+            ..WorkloadSpec::default().record_count(1)
+        };
+        assert!(error(spec).contains("zipfian over 5 items needs a record_count of at least 2"));
+
+        let spec = WorkloadSpec {
+            request_dist: DistributionSpec::Zipfian(5, 0.99),
+            ..WorkloadSpec::default()
+                .record_count(0)
+                .read_proportion(0.0)
+                .update_proportion(0.0)
+                .insert_proportion(1.0)
+        };
+        assert!(error(spec).contains("record_count of at least 2 per table, got 0"));
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .request_zipfian(0.99);
+        assert!(CoreWorkload::new(spec).is_ok());
+    }
+
+    #[test]
+    fn test_fields_per_operation() {
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .read_all_fields(false)
+            .fields_per_read(3)
+            .fields_per_write(4);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        for _ in 0..100 {
+            let fields = workload.next_read_fields().unwrap();
+            assert_eq!(fields.iter().collect::<HashSet<_>>().len(), 3);
+
+            let values = workload.next_update_values("user1");
+            assert_eq!(
+                values
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<HashSet<_>>()
+                    .len(),
+                4
+            );
+            assert!(values.iter().all(|(_, value)| value.len() == 100));
+        }
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .write_all_fields(true);
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert_eq!(workload.next_update_values("user1").len(), 10);
+
+        let workload = CoreWorkload::new(WorkloadSpec::default().record_count(1)).unwrap();
+        assert_eq!(workload.next_update_values("user1").len(), 1);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_count(2)
+            .fields_per_read(3)
+            .fields_per_write(0);
+        let msg = CoreWorkload::new(spec).err().unwrap().to_string();
+        assert!(msg.contains("fields_per_read must be between 1 and the 2 fields, got 3"));
+        assert!(msg.contains("fields_per_write must be between 1 and the 2 fields, got 0"));
+    }
+
+    #[test]
+    fn test_insert_start() {
+        let specs = vec![
+            WorkloadSpec::default().request_uniform(0, usize::MAX),
+            WorkloadSpec::default().request_zipfian(0.99),
+            WorkloadSpec::default().request_latest(),
+            WorkloadSpec::default().request_constant(0),
+        ];
+
+        for spec in specs {
+            let spec = spec
+                .record_count(100)
+                .insert_start(1000)
+                .ordered_insert(true)
+                .insert_proportion(0.5)
+                .delete_proportion(0.1);
+            let workload = CoreWorkload::new(spec).unwrap();
+            let key_num = |key: String| key["user".len()..].parse::<usize>().unwrap();
+
+            for _ in 0..1000 {
+                let table = workload.next_table();
+                assert!(key_num(workload.next_transaction_key(table)) >= 1000);
+                assert!(key_num(workload.next_delete_key(table)) >= 1000);
+                assert!(key_num(workload.next_scan(table).0) >= 1000);
+
+                let key_num = workload.next_insert_key_num(table);
+                assert!(key_num >= 1100);
+                workload.acknowledge_insert(table, key_num);
+            }
+        }
+    }
+
+    #[test]
+    fn test_insert_count() {
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .insert_start(40)
+            .insert_count(10)
+            .request_uniform(0, usize::MAX)
+            .ordered_insert(true)
+            .insert_proportion(0.5);
+        assert_eq!(spec.load_count(), 10);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let key_num = |key: String| key["user".len()..].parse::<usize>().unwrap();
+
+        let loaded = (0..10)
+            .map(|_| key_num(workload.next_sequence_key().1))
+            .collect::<Vec<_>>();
+        assert_eq!(loaded, (40..50).collect::<Vec<_>>());
+
+        let table = &workload.tables()[0];
+        let requested = (0..1000)
+            .map(|_| key_num(workload.next_transaction_key(table)))
+            .collect::<Vec<_>>();
+        assert!(requested.iter().all(|&k| k < 100));
+        assert!(requested.iter().any(|&k| k < 40));
+        assert_eq!(workload.next_insert_key_num(table), 100);
+
+        let (spec, _) = WorkloadSpec::from_properties(
+            "recordcount=100\ninsertstart=95\ninsertcount=10\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(spec.get_insert_count(), Some(10));
+        assert!(CoreWorkload::new(spec)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("insert_start 95 + insert_count 10 exceeds the record_count of 100"));
+    }
+
+    #[test]
+    fn test_scan_start_dist() {
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .ordered_insert(true)
+            .insert_proportion(0.5)
+            .scan_proportion(0.5)
+            .scan_start_dist(DistributionSpec::Latest);
+        let json = serde_json::to_string(&spec).unwrap();
+        assert!(json.contains(r#""scan_start_dist":{"type":"latest"}"#));
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let table = workload.next_table();
+        for _ in 0..100 {
+            let key_num = workload.next_insert_key_num(table);
+            workload.acknowledge_insert(table, key_num);
+        }
+
+        let key_num = |key: String| key["user".len()..].parse::<usize>().unwrap();
+        let recent = (0..1000)
+            .filter(|_| key_num(workload.next_scan(table).0) >= 150)
+            .count();
+        let requested = (0..1000)
+            .filter(|_| key_num(workload.next_transaction_key(table)) >= 150)
+            .count();
+        // The latest 50 of 200 keys get about 76% of a zipfian.
+        assert!(recent > 650, "{}", recent);
+        assert!(requested < 400, "{}", requested);
+
+        let spec = WorkloadSpec::default()
+            .record_count(0)
+            .read_proportion(0.0)
+            .update_proportion(0.0)
+            .insert_proportion(0.0)
+            .delete_proportion(1.0)
+            .scan_start_dist(DistributionSpec::Latest);
+        assert!(spec
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("scan_start_dist: latest needs a record_count of at least 1 or inserts"));
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .scan_start_dist(DistributionSpec::Sequential);
+        assert!(spec
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("scan_start_dist must be uniform, zipfian or latest"));
+    }
+
+    #[test]
+    fn test_request_dist_overrides() {
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .ordered_insert(true)
+            .update_request_dist(DistributionSpec::Uniform(500, 999));
+        let json = serde_json::to_string(&spec).unwrap();
+        assert!(json.contains(r#""update_request_dist":{"type":"uniform","#));
+        assert!(!json.contains("read_request_dist"));
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let table = workload.next_table();
+        let key_num = |key: String| key["user".len()..].parse::<usize>().unwrap();
+
+        assert!((0..1000)
+            .map(|_| key_num(workload.next_transaction_key_for(Operation::Update, table)))
+            .all(|k| (500..1000).contains(&k)));
+        // Reads fall back to the request distribution over all keys.
+        let low = (0..1000)
+            .filter(|_| key_num(workload.next_transaction_key_for(Operation::Read, table)) < 500)
+            .count();
+        assert!(low > 100, "{}", low);
+
+        let spec = WorkloadSpec::from_json_str(
+            r#"{"record_count": 10, "scan_request_dist": {"type": "latest"}}"#,
+        )
+        .unwrap();
+        assert_eq!(spec.get_scan_start_dist(), Some(&DistributionSpec::Latest));
+
+        let err = WorkloadSpec::default()
+            .record_count(10)
+            .read_request_dist(DistributionSpec::Uniform(10, 20))
+            .rmw_request_dist(DistributionSpec::Sequential)
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("read_request_dist: uniform min 10"), "{}", err);
+        assert!(
+            err.contains("rmw_request_dist must be uniform, zipfian or latest"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_request_constant() {
+        let mut spec = WorkloadSpec::default()
+            .record_count(100)
+            .request_constant(7);
+        spec.scan_len_dist = DistributionSpec::Constant(10);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        for _ in 0..100 {
+            let table = workload.next_table();
+            assert_eq!(
+                workload.next_transaction_key(table),
+                workload.get_key_name(7)
+            );
+            assert_eq!(workload.next_scan(table), (workload.get_key_name(7), 10));
+        }
+
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .request_constant(100);
+        assert!(CoreWorkload::new(spec)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("request_dist: constant key 100 is past the last of 100 records"));
+    }
+
+    #[test]
+    fn test_normal() {
+        let spec = WorkloadSpec::from_toml_str(
+            "scan_len_dist = { type = \"normal\", mean = 5, std_dev = 10, max = 20 }",
+        )
+        .unwrap();
+        assert_eq!(
+            spec.scan_len_dist,
+            DistributionSpec::Normal {
+                mean: 5.0,
+                std_dev: 10.0,
+                max: 20
+            }
+        );
+
+        let workload = CoreWorkload::new(spec.record_count(1)).unwrap();
+        let lens = (0..10_000)
+            .map(|_| workload.next_scan_length())
+            .collect::<Vec<_>>();
+        assert!(lens.iter().all(|len| (1..=20).contains(len)));
+        // Half the draws are at or below the mean of 5, most of them below 1.
+        assert!(lens.iter().filter(|&&len| len == 1).count() > 2500);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_len_normal(100.0, 0.0, 200);
+        assert!(CoreWorkload::new(spec)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("field_len_dist: normal std_dev must be positive, got 0"));
+    }
+
+    #[test]
+    fn test_exponential() {
+        let spec =
+            WorkloadSpec::from_toml_str("field_len_dist = { type = \"exponential\", mean = 50 }")
+                .unwrap();
+        assert_eq!(
+            spec.field_len_dist,
+            DistributionSpec::Exponential {
+                mean: 50.0,
+                max: MAX_DRAWN_LENGTH
+            }
+        );
+
+        let spec = spec.record_count(1).field_len_exponential(50.0, 100);
+        let toml = toml::to_string(&toml::Value::try_from(&spec).unwrap()).unwrap();
+        assert_eq!(WorkloadSpec::from_toml_str(&toml).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let lens = (0..10_000)
+            .map(|_| workload.next_field_value().len())
+            .collect::<Vec<_>>();
+        assert!(lens.iter().all(|len| (1..=100).contains(len)));
+        assert!(lens.iter().filter(|&&len| len <= 50).count() > 5000);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_len_exponential(0.0, 0);
+        let msg = CoreWorkload::new(spec).err().unwrap().to_string();
+        assert!(msg.contains("field_len_dist: exponential mean must be positive, got 0"));
+        assert!(msg.contains("field_len_dist: exponential max must be at least 1"));
+    }
+
+    #[test]
+    fn test_field_len_overrides() {
+        let spec = WorkloadSpec::from_toml_str(
+            r#"
+            field_names = ["status", "email", "payload"]
+            field_len_overrides = [[0, { type = "constant", value = 1 }], [2, { type = "constant", value = 1000 }]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            spec.get_field_len_overrides(),
+            &[
+                (0, DistributionSpec::Constant(1)),
+                (2, DistributionSpec::Constant(1000))
+            ]
+        );
+
+        let spec = spec.record_count(1).fields_per_write(2);
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let expected = |name: &str| match name {
+            "status" => 1,
+            "email" => 100,
+            _ => 1000,
+        };
+
+        let values = workload.build_values("key");
+        let lens = values
+            .iter()
+            .map(|(_, value)| value.len())
+            .collect::<Vec<_>>();
+        assert_eq!(lens, vec![1, 100, 1000]);
+
+        for _ in 0..100 {
+            let (name, value) = workload.build_update("key");
+            assert_eq!(value.len(), expected(&name), "{}", name);
+
+            for (name, value) in workload.next_update_values("key") {
+                assert_eq!(value.len(), expected(&name), "{}", name);
+            }
+        }
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_count(2)
+            .field_len_override(1, DistributionSpec::Uniform(10, 5))
+            .field_len_override(2, DistributionSpec::Constant(5));
+        let msg = CoreWorkload::new(spec).err().unwrap().to_string();
+        assert!(msg.contains("field_len_overrides[1]: uniform min 10 exceeds max 5"));
+        assert!(msg.contains("field_len_overrides[2]: field index out of range for 2 fields"));
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .data_integrity(true)
+            .field_len_const(64)
+            .field_len_override(0, DistributionSpec::Uniform(64, 128));
+        let msg = CoreWorkload::new(spec).err().unwrap().to_string();
+        assert!(msg.contains("data_integrity needs a constant field_len_overrides[0]"));
+    }
+
+    #[test]
+    fn test_timestamp_field() {
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_count(3)
+            .read_all_fields(false)
+            .fields_per_read(3)
+            .seed(1)
+            .include_timestamp_field("ts");
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert_eq!(workload.field_names(), ["field0", "field1", "field2", "ts"]);
+
+        let values = workload.build_values("key");
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[3], ("ts".to_owned(), format!("{:020}", 1)));
+
+        let update = workload.next_update_values("key");
+        assert_eq!(update.len(), 2);
+        assert_eq!(update[1], ("ts".to_owned(), format!("{:020}", 2)));
+        assert_ne!(workload.build_update("key").0, "ts");
+
+        let read = workload.next_read_fields().unwrap();
+        assert!(!read.contains(&"ts".to_owned()));
+
+        let rmw = workload.next_rmw_values("key", Some(&read[..1]));
+        assert_eq!(
+            rmw.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            [&read[0], "ts"]
+        );
+        assert!(rmw[1].1 > update[1].1);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .include_timestamp_field("ts");
+        let workload = CoreWorkload::new(spec).unwrap();
+        let ts = |values: Vec<(String, String)>| values.last().unwrap().1.parse::<u64>().unwrap();
+        let first = ts(workload.build_values("key"));
+        assert!(first > 1_500_000_000_000_000, "{}", first);
+        assert!(ts(workload.build_values("key")) > first);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .data_integrity(true)
+            .include_timestamp_field("ts");
+        let workload = CoreWorkload::new(spec).unwrap();
+        let values = workload.build_values("key");
+        assert!(workload.check_values("key", &values));
+        assert!(workload.check_row(&values));
+
+        for (spec, msg) in [
+            (
+                WorkloadSpec::default().include_timestamp_field("field9"),
+                "include_timestamp_field 'field9' is already one of the 10 fields",
+            ),
+            (
+                WorkloadSpec::default()
+                    .field_names(vec!["a".to_owned(), "b".to_owned()])
+                    .include_timestamp_field("b"),
+                "include_timestamp_field 'b' is already one of the 2 fields",
+            ),
+            (
+                WorkloadSpec::default().include_timestamp_field(""),
+                "include_timestamp_field must not be empty",
+            ),
+        ] {
+            let err = CoreWorkload::new(spec.record_count(1)).err().unwrap();
+            assert!(err.to_string().contains(msg), "{}", err);
+        }
+        assert!(CoreWorkload::new(
+            WorkloadSpec::default()
+                .record_count(1)
+                .include_timestamp_field("field10")
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_pareto() {
+        let spec = WorkloadSpec::from_toml_str(
+            "field_len_dist = { type = \"pareto\", scale = 10, shape = 1.5 }",
+        )
+        .unwrap();
+        assert_eq!(
+            spec.field_len_dist,
+            DistributionSpec::Pareto {
+                scale: 10.0,
+                shape: 1.5,
+                max: MAX_DRAWN_LENGTH
+            }
+        );
+        let toml = toml::to_string(&toml::Value::try_from(&spec).unwrap()).unwrap();
+        assert_eq!(WorkloadSpec::from_toml_str(&toml).unwrap(), spec);
+
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_len_pareto(10.0, 0.5, 100)
+            .scan_len_pareto(1.0, 1.0, 50);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let lens = (0..1000)
+            .map(|_| workload.next_field_value().len())
+            .collect::<Vec<_>>();
+        assert!(lens.iter().all(|len| (10..=100).contains(len)));
+        assert!(lens.contains(&100));
+        assert!((0..1000).all(|_| (1..=50).contains(&workload.next_scan_length())));
+    }
+
+    #[test]
+    fn test_single_record() {
+        let spec = WorkloadSpec::default().record_count(1).field_count(1);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        assert_eq!(
+            workload.next_transaction_key(workload.next_table()),
+            workload.get_key_name(0)
+        );
+        assert_eq!(workload.next_field_name(), "field0");
+    }
+
+    #[test]
+    fn test_sequential() {
+        let spec = WorkloadSpec::default()
+            .record_count(3)
+            .ordered_insert(true)
+            .request_sequential();
+        let workload = CoreWorkload::new(spec).unwrap();
+        let keys = (0..4)
+            .map(|_| workload.next_transaction_key(workload.next_table()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(keys, ["user0", "user1", "user2", "user0"]);
+
+        let spec = WorkloadSpec::from_toml_str("request_dist = { type = \"sequential\" }").unwrap();
+        assert_eq!(spec.request_dist, DistributionSpec::Sequential);
+    }
+
+    #[test]
+    fn test_uniform_endpoints() {
+        let mut spec = WorkloadSpec::default().record_count(3);
+        spec.field_len_dist = DistributionSpec::Uniform(4, 6);
+        spec.scan_len_dist = DistributionSpec::Uniform(1, 3);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let keys = (0..1000)
+            .map(|_| workload.sample_transaction_key_num(workload.next_table()))
+            .collect::<Vec<_>>();
+        assert!(keys.contains(&0) && keys.contains(&2));
+
+        let lens = (0..1000)
+            .map(|_| workload.next_scan_length())
+            .collect::<Vec<_>>();
+        assert!(lens.contains(&1) && lens.contains(&3));
+
+        let lens = (0..1000)
+            .map(|_| workload.next_field_value().len())
+            .collect::<Vec<_>>();
+        assert!(lens.contains(&4) && lens.contains(&6));
+    }
+
+    #[test]
+    fn test_scan_length() {
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .ordered_insert(true)
+            .max_scan_length(5);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let table = workload.next_table();
+
+        let mut lens = Vec::new();
+        for _ in 0..1000 {
+            let (key, len) = workload.next_scan(table);
+            let key_num = key["user".len()..].parse::<usize>().unwrap();
+            assert!(len >= 1 && key_num + len <= 10, "{} {}", key, len);
+
+            if key_num == 9 {
+                lens.push(len);
+            }
+        }
+        assert!(!lens.is_empty() && lens.iter().all(|&len| len == 1));
+
+        let spec = WorkloadSpec::default().record_count(10).max_scan_length(5);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let lens = (0..1000)
+            .map(|_| workload.next_scan(workload.next_table()).1)
+            .collect::<Vec<_>>();
+        assert!(lens.contains(&5) && lens.iter().all(|&len| (1..=5).contains(&len)));
+
+        let spec = WorkloadSpec::default().record_count(10).max_scan_length(0);
+        assert!(CoreWorkload::new(spec).is_err());
+    }
+
+    #[test]
+    fn test_think_time() {
+        let spec = || WorkloadSpec::default().record_count(10);
+        let workload = CoreWorkload::new(spec()).unwrap();
+        assert_eq!(workload.next_think_time(), None);
+
+        let spec = spec().think_time_dist(DistributionSpec::Uniform(100, 200));
+        let workload = CoreWorkload::new(spec).unwrap();
+        for _ in 0..100 {
+            let think_time = workload.next_think_time().unwrap();
+            assert!(
+                think_time >= Duration::from_micros(100)
+                    && think_time <= Duration::from_micros(200),
+                "{:?}",
+                think_time
+            );
+        }
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .think_time_dist(DistributionSpec::Latest);
+        assert!(CoreWorkload::new(spec).is_err());
+    }
+
+    #[test]
+    fn test_request_range() {
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .request_uniform(10, 19);
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert!((0..1000)
+            .map(|_| workload.sample_transaction_key_num(workload.next_table()))
+            .all(|key| (10..20).contains(&key)));
+
+        let spec = WorkloadSpec::default()
+            .record_count(100)
+            .request_uniform(90, 1000);
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert!((0..1000)
+            .map(|_| workload.sample_transaction_key_num(workload.next_table()))
+            .all(|key| (90..100).contains(&key)));
+
+        // Inserted keys are requested once acknowledged.
+        for _ in 0..10 {
+            workload.acknowledge_insert(
+                workload.next_table(),
+                workload.next_insert_key_num(workload.next_table()),
+            );
+        }
+        let keys = (0..1000)
+            .map(|_| workload.sample_transaction_key_num(workload.next_table()))
+            .collect::<Vec<_>>();
+        assert!(keys.iter().all(|key| (90..110).contains(key)));
+        assert!(keys.iter().any(|&key| key >= 100));
+
+        let mut spec = WorkloadSpec::default()
+            .record_count(1000)
+            .scramble_zipfian(false);
+        spec.request_dist = DistributionSpec::Zipfian(10, 0.99);
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert!((0..1000)
+            .map(|_| workload.sample_transaction_key_num(workload.next_table()))
+            .all(|key| key < 10));
+
+        let e = WorkloadSpec::default()
+            .record_count(100)
+            .request_uniform(100, 200)
+            .validate()
+            .unwrap_err();
+        assert!(e.to_string().contains("request_dist: uniform min 100"));
+
+        let toml =
+            toml::to_string(&toml::Value::try_from(WorkloadSpec::default()).unwrap()).unwrap();
+        assert!(toml.contains("[request_dist]\nmin = 0\ntype = \"uniform\"\n"));
+        assert_eq!(
+            WorkloadSpec::from_toml_str(&toml).unwrap(),
+            WorkloadSpec::default()
+        );
+    }
+
+    #[test]
+    fn test_scramble_zipfian() {
+        let hot_head = |scramble| {
+            let spec = WorkloadSpec::default()
+                .record_count(1000)
+                .request_zipfian(0.99)
+                .scramble_zipfian(scramble);
+            let workload = CoreWorkload::new(spec).unwrap();
+
+            (0..10_000)
+                .filter(|_| workload.sample_transaction_key_num(workload.next_table()) < 100)
+                .count()
+        };
+
+        assert!(hot_head(false) > 5000);
+        assert!(hot_head(true) < 4000);
+    }
+
+    #[test]
+    fn test_zipfian_within_records() {
+        for &items in &[0, 1000, 5000] {
+            for &scramble in &[false, true] {
+                let mut spec = WorkloadSpec::default()
+                    .record_count(1000)
+                    .insert_proportion(0.0)
+                    .scramble_zipfian(scramble);
+                spec.request_dist = DistributionSpec::Zipfian(items, 0.99);
+                let workload = CoreWorkload::new(spec).unwrap();
+
+                assert!((0..200_000)
+                    .all(|_| workload.sample_transaction_key_num(workload.next_table()) < 1000));
+            }
+        }
+    }
+
+    #[test]
+    fn test_latest_from_empty() {
+        let spec = || {
+            WorkloadSpec::default()
+                .read_proportion(0.5)
+                .update_proportion(0.0)
+                .insert_proportion(0.5)
+                .request_latest()
+                .ordered_insert(true)
+        };
+
+        assert!(spec().record_count(0).validate().is_ok());
+        assert!(spec().insert_proportion(0.0).validate().is_err());
+        let e = CoreWorkload::new(spec().record_count(0).insert_proportion(0.0)).err();
+        assert!(format!("{:?}", e).contains("latest distribution without inserts"));
+
+        for record_count in 0..2 {
+            let workload = CoreWorkload::new(spec().record_count(record_count)).unwrap();
+            assert_eq!(
+                workload.next_transaction_key(workload.next_table()),
+                "user0"
+            );
+        }
+
+        let workload = CoreWorkload::new(spec().record_count(0)).unwrap();
+        for i in 0..10 {
+            assert_eq!(workload.next_insert_key_num(workload.next_table()), i);
+            assert!(
+                workload.sample_transaction_key_num(workload.next_table()) <= i.saturating_sub(1)
+            );
+            workload.acknowledge_insert(workload.next_table(), i);
+            assert!(workload.sample_transaction_key_num(workload.next_table()) <= i);
+        }
+    }
+
+    #[test]
+    fn test_key_hash() {
+        let key_names = |spec: WorkloadSpec| {
+            let workload = CoreWorkload::new(spec.record_count(1)).unwrap();
+            [0, 1, 12, 1_000_000]
+                .iter()
+                .map(|&n| workload.get_key_name(n))
+                .collect::<Vec<_>>()
+        };
+        let with_hash = |kind| key_names(WorkloadSpec::default().key_hash(kind));
+
+        // Pinned so that key names stay compatible with loaded datasets.
+        assert_eq!(
+            with_hash(KeyHashKind::SplitMix),
+            key_names(WorkloadSpec::default())
+        );
+        assert_eq!(
+            with_hash(KeyHashKind::SplitMix),
+            vec![
+                "user16294208416658607535",
+                "user10451216379200822465",
+                "user10682531704454680323",
+                "user7497680628364559847",
+            ]
+        );
+        // The key names of upstream YCSB.
+        assert_eq!(
+            with_hash(KeyHashKind::Fnv64),
+            vec![
+                "user6284781860667377211",
+                "user8517097267634966620",
+                "user2644479767202980425",
+                "user1011632231655643464",
+            ]
+        );
+        assert_eq!(
+            with_hash(KeyHashKind::Wyhash),
+            vec![
+                "user6661202149082483300",
+                "user963824419065665235",
+                "user14066056800570241111",
+                "user4974628214165239195",
+            ]
+        );
+
+        assert_eq!(
+            with_hash(KeyHashKind::Xx64),
+            vec![
+                "user3803688792395291579",
+                "user11468921228449061269",
+                "user12152329712329987385",
+                "user17376604006145374750",
+            ]
+        );
+        if cfg!(all(target_pointer_width = "64", target_endian = "little")) {
+            assert_eq!(
+                with_hash(KeyHashKind::LegacyXx64),
+                with_hash(KeyHashKind::Xx64)
+            );
+        }
+
+        let spec = WorkloadSpec::from_json_str(r#"{"key_hash": "xx64"}"#).unwrap();
+        assert_eq!(spec.get_key_hash(), KeyHashKind::Xx64);
+
+        // Older files name the legacy hash with a flag of its own.
+        let spec = WorkloadSpec::from_json_str(r#"{"legacy_key_hash": true}"#).unwrap();
+        assert_eq!(spec.get_key_hash(), KeyHashKind::LegacyXx64);
+        assert_eq!(key_names(spec.clone()), with_hash(KeyHashKind::LegacyXx64));
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(WorkloadSpec::from_json_str(&json).unwrap(), spec);
+
+        for kind in ["legacy_xx64", "wyhash"] {
+            let json = format!(r#"{{"legacy_key_hash": true, "key_hash": "{}"}}"#, kind);
+            let spec = WorkloadSpec::from_json_str(&json).unwrap();
+            assert!(spec.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_key_permutation_unique() {
+        let mut keys = (0..100_000u64).map(permute_key_num).collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), 100_000);
+    }
+
+    #[test]
+    #[ignore = "names 10M keys; run with --ignored"]
+    fn test_key_names_unique() {
+        let workload = CoreWorkload::new(WorkloadSpec::default().record_count(1)).unwrap();
+
+        let mut names = (0..10_000_000u64)
+            .map(|key_num| workload.get_key_name(key_num))
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), 10_000_000);
+    }
+
+    #[test]
+    fn test_zero_padding() {
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .ordered_insert(true)
+            .zero_padding(20);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let keys = (0..1000)
+            .map(|_| workload.next_sequence_key().1)
+            .collect::<Vec<_>>();
+        assert_eq!(keys[12], "user00000000000000000012");
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        let key_num = workload.next_insert_key_num(workload.next_table());
+        assert_eq!(key_num, 1000);
+        assert_eq!(workload.get_key_name(key_num), "user00000000000000001000");
+
+        // Hashed key numbers have up to 20 digits.
+        let spec = WorkloadSpec::default().record_count(1).zero_padding(25);
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert_eq!(workload.get_key_name(12).len(), "user".len() + 25);
+    }
+
+    #[test]
+    fn test_key_prefix() {
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .ordered_insert(true)
+            .key_prefix("orders:");
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        assert_eq!(workload.next_sequence_key().1, "orders:0");
+        assert!(workload
+            .next_transaction_key(workload.next_table())
+            .starts_with("orders:"));
+        let key_num = workload.next_insert_key_num(workload.next_table());
+        assert_eq!(workload.get_key_name(key_num), "orders:10");
+
+        let workload =
+            CoreWorkload::new(WorkloadSpec::default().record_count(1).key_prefix("")).unwrap();
+        assert!(workload.next_sequence_key().1.parse::<u64>().is_ok());
+
+        let e = WorkloadSpec::default()
+            .record_count(1)
+            .key_prefix("a\0b")
+            .validate()
+            .unwrap_err();
+        assert!(e.to_string().contains("key_prefix must not contain NUL"));
+        assert_eq!(WorkloadSpec::from_toml_str("").unwrap().key_prefix, "user");
+    }
+
+    #[test]
+    fn test_integer_keys() {
+        for &ordered in &[false, true] {
+            let spec = || {
+                WorkloadSpec::default()
+                    .record_count(10)
+                    .ordered_insert(ordered)
+            };
+            let named = CoreWorkload::new(spec()).unwrap();
+            let numbered = CoreWorkload::new(spec()).unwrap();
+            let table = numbered.next_table();
+            let num = |key: String| key["user".len()..].parse::<u64>().unwrap();
+
+            let loaded = (0..10)
+                .map(|_| {
+                    let integer_key = numbered.next_sequence_key_num().1;
+                    assert_eq!(num(named.next_sequence_key().1), integer_key);
+                    integer_key
+                })
+                .collect::<HashSet<_>>();
+            assert_eq!(loaded.len(), 10);
+            assert!((0..100).all(|_| loaded.contains(&numbered.next_transaction_key_num(table))));
+
+            let (key_num, integer_key) = numbered.next_insert_sequence_num(table);
+            assert_eq!(key_num, 10);
+            assert_eq!(integer_key == 10, ordered);
+            assert_eq!(num(named.get_key_name(key_num)), integer_key);
+        }
+    }
+
+    #[test]
+    fn test_key_nums_past_u32() {
+        // Key numbers stay 64-bit on every target, so these names must not
+        // depend on the width of `usize`.
+        let key_names = |spec: WorkloadSpec| {
+            let workload = CoreWorkload::new(spec.record_count(1)).unwrap();
+            [1 << 32, (1 << 32) + 1, 1 << 40]
+                .iter()
+                .map(|&n| workload.get_key_name(n))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            key_names(WorkloadSpec::default()),
+            vec![
+                "user14135772400868000056",
+                "user2324861979054413167",
+                "user2296115805719413641",
+            ]
+        );
+        assert_eq!(
+            key_names(WorkloadSpec::default().key_hash(KeyHashKind::Fnv64)),
+            vec![
+                "user634246865027890484",
+                "user3010618080379317301",
+                "user6908947124047430886",
+            ]
+        );
+        assert_eq!(
+            key_names(WorkloadSpec::default().ordered_insert(true)),
+            vec!["user4294967296", "user4294967297", "user1099511627776"]
+        );
+
+        // Requests follow inserts past 2^32 without wrapping.
+        let start = 1 << 32;
+        let count = Arc::new(generator::AcknowledgedCounterGenerator::new(start + 10));
+        let sampler = generator::GrowingUniformGenerator::new(count, start, 0, usize::MAX);
+        assert!((0..100).all(|_| sampler.next_value() < 10));
+    }
+
+    #[test]
+    fn test_sharing_key_space() {
+        let spec = || WorkloadSpec::default().record_count(10);
+        let workload = CoreWorkload::new(spec()).unwrap();
+        let writer =
+            CoreWorkload::sharing_key_space(spec().insert_proportion(1.0), &workload).unwrap();
+
+        let table = &writer.tables()[0];
+        let key_num = writer.next_insert_key_num(table);
+        writer.acknowledge_insert(table, key_num);
+        assert_eq!(workload.inserted_key_count(), 11);
+        assert_eq!(workload.next_insert_key_num(&workload.tables()[0]), 11);
+
+        let err = CoreWorkload::sharing_key_space(
+            spec().record_count(20).key_prefix("item").table("items"),
+            &workload,
+        );
+        match err {
+            Err(Error::InvalidSpec(msg)) => assert_eq!(
+                msg,
+                "workloads sharing a key space differ in tables, key_prefix, record_count"
+            ),
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_next_missing_key() {
+        let workload = CoreWorkload::new(WorkloadSpec::default().record_count(10)).unwrap();
+        assert!((0..100).all(|_| workload.next_missing_key().is_none()));
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .read_miss_proportion(1.0);
+        let workload = CoreWorkload::new(spec).unwrap();
+        let key = workload.next_missing_key().unwrap();
+        assert!(key.starts_with("missing"), "{}", key);
+
+        let e = WorkloadSpec::default()
+            .read_miss_proportion(1.5)
+            .validate()
+            .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("read_miss_proportion must be in [0, 1]"));
+        let e = WorkloadSpec::default()
+            .read_miss_proportion(0.1)
+            .key_prefix("missing_")
+            .validate()
+            .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("key_prefix not starting with 'missing'"));
+    }
+
+    #[test]
+    fn test_field_names() {
+        let names = || vec!["name".to_owned(), "email".to_owned(), "payload".to_owned()];
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .read_all_fields(false)
+            .field_names(names());
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let values = workload.build_values("user0");
+        assert_eq!(
+            values
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>(),
+            names()
+        );
+        assert_eq!(workload.field_names(), names());
+
+        let mut picked = HashSet::new();
+        for _ in 0..100 {
+            picked.extend(workload.next_read_fields().unwrap());
+        }
+        assert_eq!(picked, names().into_iter().collect());
+
+        let spec = WorkloadSpec::from_toml_str("field_names = [\"a\", \"b\"]").unwrap();
+        assert_eq!(spec.field_names, Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert!(!serde_json::to_string(&WorkloadSpec::default())
+            .unwrap()
+            .contains("field_names"));
+
+        let e = WorkloadSpec::default()
+            .record_count(1)
+            .field_names(vec!["a".to_owned(), "a".to_owned()])
+            .validate()
+            .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("field_names contains 'a' more than once"));
+    }
+
+    #[test]
+    fn test_value_kind() {
+        let value = |kind| {
+            let spec = WorkloadSpec::default()
+                .record_count(1)
+                .field_len_const(30)
+                .value_kind(kind);
+            CoreWorkload::new(spec).unwrap().next_field_value()
+        };
+
+        let v = value(ValueKind::Alphanumeric);
+        assert!(v.len() == 30 && v.bytes().all(|c| c.is_ascii_alphanumeric()));
+        let v = value(ValueKind::Ascii);
+        assert!(v.len() == 30 && v.bytes().all(|c| (b' '..=b'~').contains(&c)));
+        let v = value(ValueKind::Hex);
+        assert!(v.len() == 30 && v.bytes().all(|c| HEX.contains(&c)));
+        let v = value(ValueKind::Bytes);
+        assert!(v.len() == 40 && v.bytes().all(|c| BASE64.contains(&c)));
+
+        let encoded = ["", "f", "fo", "foo", "foob"]
+            .iter()
+            .map(|s| {
+                let mut encoded = Vec::new();
+                base64_encode(s.as_bytes(), &mut encoded);
+                String::from_utf8(encoded).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(encoded, ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg=="]);
+
+        let spec = WorkloadSpec::from_toml_str("value_kind = \"hex\"").unwrap();
+        assert_eq!(spec.value_kind, ValueKind::Hex);
+        assert!(WorkloadSpec::default()
+            .field_len_const(100)
+            .data_integrity(true)
+            .value_kind(ValueKind::Hex)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_value_compressibility() {
+        for &kind in &[ValueKind::Alphanumeric, ValueKind::Hex] {
+            for &target in &[2.0, 4.0] {
+                let spec = WorkloadSpec::default()
+                    .record_count(1)
+                    .field_len_const(1000)
+                    .value_kind(kind)
+                    .value_compressibility(target);
+                let workload = CoreWorkload::new(spec).unwrap();
+
+                let sample = (0..1000)
+                    .map(|_| workload.next_field_value())
+                    .collect::<String>();
+                let compressed = zstd::encode_all(sample.as_bytes(), 3).unwrap();
+                let ratio = sample.len() as f64 / compressed.len() as f64;
+
+                assert!((ratio / target - 1.0).abs() < 0.2, "{:?} {}", kind, ratio);
+            }
+        }
+
+        let spec = WorkloadSpec::default().value_compressibility(0.5);
+        assert!(spec.validate().is_err());
+    }
+
+    #[test]
+    fn test_tables() {
+        let tables = || {
+            vec![
+                ("users".to_owned(), 5.0),
+                ("orders".to_owned(), 3.0),
+                ("items".to_owned(), 2.0),
+            ]
+        };
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .ordered_insert(true)
+            .tables(tables());
+        assert_eq!(spec.records_per_table(), vec![500, 300, 200]);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let names = workload
+            .tables()
+            .iter()
+            .map(|t| t.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["users", "orders", "items"]);
+
+        let (table, key_num) = workload.load_key_num(499);
+        assert_eq!((table.name(), key_num), ("users", 499));
+        let (table, key_num) = workload.load_key_num(500);
+        assert_eq!((table.name(), key_num), ("orders", 0));
+        let (table, key_num) = workload.load_key_num(999);
+        assert_eq!((table.name(), key_num), ("items", 199));
+
+        let mut picked = [0; 3];
+        for _ in 0..10_000 {
+            let table = workload.next_table();
+            let i = names.iter().position(|&name| name == table.name()).unwrap();
+            let key = workload.next_transaction_key(table);
+            assert!(key["user".len()..].parse::<usize>().unwrap() < [500, 300, 200][i]);
+            picked[i] += 1;
+        }
+        assert!(picked[0] > 4500 && picked[0] < 5500, "{:?}", picked);
+        assert!(picked[2] > 1500 && picked[2] < 2500, "{:?}", picked);
+
+        let spec = WorkloadSpec::default().record_count(2).tables(tables());
+        assert!(CoreWorkload::new(spec).is_err());
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .tables(vec![("a".to_owned(), 1.0), ("a".to_owned(), 1.0)]);
+        assert!(CoreWorkload::new(spec).is_err());
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .tables(vec![("a".to_owned(), 1.0), ("b".to_owned(), 0.0)]);
+        assert!(CoreWorkload::new(spec).is_err());
+        assert!(
+            CoreWorkload::new(WorkloadSpec::default().record_count(10).tables(vec![])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_data_integrity() {
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .field_count(2)
+            .data_integrity(true);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let values = workload.build_values("user7");
+        assert_eq!(values, workload.build_values("user7"));
+        assert_ne!(values, workload.build_values("user8"));
+        assert!(values[0].1.starts_with("user7:field0:"));
+        assert!(values.iter().all(|(_, value)| value.len() == 100));
+
+        assert!(workload.check_values("user7", &values));
+        assert!(!workload.check_values("user8", &values));
+        assert!(workload.check_row(&values));
+
+        let mut corrupted = values;
+        corrupted[1].1.replace_range(50..51, "#");
+        assert!(!workload.check_values("user7", &corrupted));
+        assert!(!workload.check_row(&corrupted));
+
+        let problems = |spec: WorkloadSpec| spec.validate().unwrap_err().to_string();
+        assert!(problems(
+            WorkloadSpec::default()
+                .record_count(1)
+                .data_integrity(true)
+                .field_len_histogram(vec![(100, 1.0), (200, 1.0)])
+        )
+        .contains("data_integrity needs a constant field_len_dist"));
+        assert!(problems(
+            WorkloadSpec::default()
+                .record_count(1)
+                .data_integrity(true)
+                .field_len_const(20)
+        )
+        .contains("data_integrity needs field values of at least 32 bytes"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let draw = |seed: u64, stream: usize| {
+            std::thread::spawn(move || {
+                let spec = WorkloadSpec::default()
+                    .record_count(1000)
+                    .read_proportion(0.5)
+                    .update_proportion(0.5)
+                    .request_zipfian(0.99)
+                    .seed(seed);
+                let workload = CoreWorkload::new(spec).unwrap();
+                workload.seed_thread(stream);
+
+                (0..50)
+                    .map(|_| {
+                        (
+                            workload.next_operation(),
+                            workload.next_transaction_key(workload.next_table()),
+                            workload.next_field_value(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .join()
+            .unwrap()
+        };
+
+        assert_eq!(draw(42, 0), draw(42, 0));
+        assert_ne!(draw(42, 0), draw(42, 1));
+        assert_ne!(draw(42, 0), draw(43, 0));
+    }
+
+    #[test]
+    fn test_rng_kind() {
+        let draw = |kind: RngKind, stream: usize| {
+            let spec = WorkloadSpec::default()
+                .record_count(1000)
+                .seed(42)
+                .rng(kind);
+            let workload = CoreWorkload::new(spec).unwrap();
+            let mut session = workload.session(stream);
+
+            (0..50)
+                .map(|_| {
+                    let table = session.next_table();
+                    session.next_transaction_key(table)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for &kind in &[RngKind::Small, RngKind::ChaCha8, RngKind::ChaCha20] {
+            assert_eq!(draw(kind, 0), draw(kind, 0));
+            assert_ne!(draw(kind, 0), draw(kind, 1));
+        }
+        assert_ne!(draw(RngKind::Small, 0), draw(RngKind::ChaCha8, 0));
+        assert_ne!(draw(RngKind::ChaCha8, 0), draw(RngKind::ChaCha20, 0));
+
+        let spec = WorkloadSpec::from_json_str(r#"{"rng": "chacha8"}"#).unwrap();
+        assert_eq!(spec.get_rng(), RngKind::ChaCha8);
+        assert!(WorkloadSpec::from_json_str(r#"{"rng": "mersenne"}"#).is_err());
+    }
+
+    #[test]
+    fn test_session() {
+        let spec = WorkloadSpec::default()
+            .record_count(1000)
+            .read_proportion(0.5)
+            .update_proportion(0.5)
+            .request_zipfian(0.99)
+            .seed(42);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let draw = |stream: usize| {
+            let mut session = workload.session(stream);
+
+            (0..50)
+                .map(|_| {
+                    let table = session.next_table();
+                    (
+                        session.next_operation(),
+                        session.next_transaction_key(table),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Sessions don't depend on the thread they run on.
+        let other_thread = std::thread::scope(|s| s.spawn(|| draw(0)).join().unwrap());
+        assert_eq!(draw(0), other_thread);
+        assert_ne!(draw(0), draw(1));
+    }
+
+    #[test]
+    fn test_batched_draws() {
+        let spec = || {
+            WorkloadSpec::default()
+                .record_count(1000)
+                .insert_start(10)
+                .read_proportion(0.5)
+                .update_proportion(0.5)
+                .seed(42)
+        };
+
+        for spec in [
+            spec().request_zipfian(0.99),
+            spec().request_uniform(0, 999),
+            spec().request_latest(),
+            spec().skip_deleted_keys(true),
+        ] {
+            let workload = CoreWorkload::new(spec).unwrap();
+            let table = &workload.tables()[0];
+
+            let mut session = workload.session(0);
+            let ops = (0..50)
+                .map(|_| session.next_operation())
+                .collect::<Vec<_>>();
+            let keys = (0..50)
+                .map(|_| session.next_transaction_key(table))
+                .collect::<Vec<_>>();
+
+            let mut session = workload.session(0);
+            assert_eq!(session.next_operations(50), ops);
+            assert_eq!(session.next_transaction_keys(table, 50), keys);
+        }
+
+        let workload = CoreWorkload::new(spec()).unwrap();
+        assert_eq!(workload.next_operations(20).len(), 20);
+        let keys = workload.next_transaction_keys(&workload.tables()[0], 20);
+        assert_eq!(keys.len(), 20);
+        assert!(keys.iter().all(|key| key.starts_with("user")));
+    }
+
+    #[test]
+    fn test_key_file() {
+        let path = std::env::temp_dir().join("ycsb_rs_test_keys.txt");
+        let workload = |keys: &str, record_count: usize| {
+            fs::write(&path, keys).unwrap();
+            let spec = WorkloadSpec::default()
+                .record_count(record_count)
+                .request_keys_from(&path);
+            CoreWorkload::new(spec)
+        };
+
+        let w = workload("alice 8\nbob\n\ncarol 1\n", 3).unwrap();
+        let loaded = (0..3).map(|_| w.next_sequence_key().1).collect::<Vec<_>>();
+        assert_eq!(loaded, ["alice", "bob", "carol"]);
+
+        let keys = (0..10_000)
+            .map(|_| w.next_transaction_key(w.next_table()))
+            .collect::<Vec<_>>();
+        let alice = keys.iter().filter(|&key| key == "alice").count();
+        assert!(keys.iter().all(|key| loaded.contains(key)));
+        assert!(alice > 7700 && alice < 8300, "{}", alice);
+
+        // Only the keys loaded are requested.
+        let w = workload("alice\nbob\ncarol\n", 2).unwrap();
+        assert!((0..1000).all(|_| w.next_transaction_key(w.next_table()) != "carol"));
+
+        let msg = |keys: &str, record_count: usize| {
+            workload(keys, record_count).err().unwrap().to_string()
+        };
+        assert!(msg("", 1).contains("lists no keys"));
+        assert!(msg("a\nb\na\n", 1).contains("lists key 'a' more than once"));
+        assert!(msg("a 0\n", 1).contains(":1: invalid weight '0'"));
+        assert!(msg("a b c\n", 1).contains(":1: expected a key"));
+        assert!(msg("a\nb\n", 3).contains("lists 2 keys, fewer than the 3 to load"));
+        fs::remove_file(&path).unwrap();
+
+        let spec =
+            WorkloadSpec::from_toml_str("request_dist = { type = \"file\", path = \"k.txt\" }")
+                .unwrap();
+        assert_eq!(spec.request_dist, DistributionSpec::File("k.txt".into()));
+    }
+
+    #[test]
+    fn test_field_len_histogram() {
+        let path = std::env::temp_dir().join("ycsb_rs_test_histogram.txt");
+        fs::write(&path, "BlockSize\t10\n# blocks count\n10\t9\n1000\t1\n").unwrap();
+        let dist = DistributionSpec::histogram_from_path(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            dist,
+            DistributionSpec::Histogram(vec![(100, 9.0), (10_000, 1.0)])
+        );
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_histogram(vec![(100, 9.0), (10_000, 1.0)]);
+        let toml = toml::to_string(&toml::Value::try_from(&spec).unwrap()).unwrap();
+        assert_eq!(WorkloadSpec::from_toml_str(&toml).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        assert!((0..100)
+            .map(|_| workload.next_field_value().len())
+            .all(|len| len == 100 || len == 10_000));
+    }
+
+    #[test]
+    fn test_field_len_histogram_file() {
+        let path = std::env::temp_dir().join("ycsb_rs_test_histogram_file.txt");
+        fs::write(&path, "# length count\n\n1000 1\n10 3\n500 0\n").unwrap();
+
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_histogram_file(&path);
+        let toml = toml::to_string(&toml::Value::try_from(&spec).unwrap()).unwrap();
+        assert!(toml.contains("type = \"histogram_file\""));
+        assert_eq!(WorkloadSpec::from_toml_str(&toml).unwrap(), spec);
+
+        let workload = CoreWorkload::new(spec).unwrap();
+        let lengths = (0..4000)
+            .map(|_| workload.next_field_value().len())
+            .collect::<Vec<_>>();
+        assert!(lengths.iter().all(|&len| len == 10 || len == 1000));
+        let short = lengths.iter().filter(|&&len| len == 10).count();
+        assert!((2800..3200).contains(&short), "{}", short);
+
+        fs::write(&path, "10 1\n20 x\n").unwrap();
+        let spec = WorkloadSpec::default()
+            .record_count(10)
+            .field_len_histogram_file(&path);
+        let msg = CoreWorkload::new(spec).err().unwrap().to_string();
+        assert!(msg.ends_with(":2: invalid weight 'x'"), "{}", msg);
+
+        fs::write(&path, "# nothing\n10 0\n").unwrap();
+        let msg = DistributionSpec::histogram_from_path(&path)
+            .unwrap_err()
+            .to_string();
+        assert!(msg.ends_with("histogram has no buckets"), "{}", msg);
+        fs::remove_file(&path).unwrap();
+
+        let props = "fieldlengthdistribution=histogram\nfieldlengthhistogram=h.txt\n";
+        let (spec, _) = WorkloadSpec::from_properties(props.as_bytes()).unwrap();
+        assert_eq!(
+            spec.field_len_dist,
+            DistributionSpec::HistogramFile("h.txt".into())
+        );
+
+        let dir = std::env::temp_dir().join("ycsb_rs_test_histogram_props");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("workload.properties");
+        fs::write(&file, props).unwrap();
+        let spec = WorkloadSpec::from_path(&file).unwrap();
+        assert_eq!(
+            spec.field_len_dist,
+            DistributionSpec::HistogramFile(dir.join("h.txt"))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let props = "fieldlength=50\nfieldlengthdistribution=uniform\n";
+        let (spec, _) = WorkloadSpec::from_properties(props.as_bytes()).unwrap();
+        assert_eq!(spec.field_len_dist, DistributionSpec::Uniform(1, 50));
+    }
+}
@@ -0,0 +1,50 @@
+//! Checks that `CoreWorkload::build_values_into` reuses the buffers it is
+//! given. Counting allocations needs its own global allocator, so this runs
+//! without the test harness, on the main thread alone.
+
+extern crate ycsb_rs;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ycsb_rs::{CoreWorkload, WorkloadSpec};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn main() {
+    for &data_integrity in &[false, true] {
+        let spec = WorkloadSpec::default()
+            .record_count(1)
+            .data_integrity(data_integrity);
+        let workload = CoreWorkload::new(spec).unwrap();
+
+        let mut values = Vec::new();
+        workload.build_values_into("user1", &mut values);
+        assert_eq!(values.len(), 10);
+
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        workload.build_values_into("user2", &mut values);
+        assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+
+        assert!(workload.check_values("user2", &values));
+        assert_eq!(values[0].0, "field0");
+    }
+}